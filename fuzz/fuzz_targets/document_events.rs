@@ -0,0 +1,29 @@
+//! Feeds arbitrary bytes as XML into [`DocumentContext`].
+//!
+//! Malformed input must only ever produce a [`ParseError`], never a panic.
+//!
+//! Run with: `cargo +nightly fuzz run document_events`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quick_xml::{events::Event as XMLEvent, Reader as XMLReader};
+use wiki_extractor::{dump_data::DocumentContext, xml_util::HandleEvent};
+
+fuzz_target!(|data: &[u8]| {
+    let mut xml_reader = XMLReader::from_reader(data);
+    let mut stream_buffer = Vec::new();
+    let mut document = DocumentContext::with_file_name("fuzz.xml");
+
+    loop {
+        let event = match xml_reader.read_event_into(&mut stream_buffer) {
+            Ok(it) => it,
+            Err(_) => break,
+        };
+        let is_eof = matches!(event, XMLEvent::Eof);
+        if document.handle_event(event).is_err() || is_eof {
+            break;
+        }
+        stream_buffer.clear();
+    }
+});
@@ -1,9 +1,14 @@
 #![allow(dead_code)]
 
+use std::collections::{HashSet, VecDeque};
+
 use quick_xml::events::Event as XMLEvent;
 use serde::{Deserialize, Serialize};
 
-use crate::{close_all_nested, forward_closeable, impl_forwarding_closeable_handler};
+use crate::{
+    close_all_nested, empty_closeable, forward_closeable, impl_forwarding_closeable_handler,
+    start_closeable,
+};
 use crate::{input::data::DumpLocation, xml_util::*};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -14,6 +19,19 @@ pub struct Namespace {
     state: CloseableState,
 }
 
+impl Namespace {
+    #[inline]
+    pub fn key(&self) -> isize {
+        self.key
+    }
+
+    /// Namespace name; main (article) namespace has an empty name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 impl FromAttributes for Namespace {
     fn from_attributes(attr: AttributeMap<'_>) -> ParseResult<Self> {
         let mut key = None;
@@ -48,14 +66,18 @@ impl HandleEvent for Namespace {
                 return self.close();
             }
             XMLEvent::Text(text) => {
-                self.name += std::str::from_utf8(&text).unwrap();
+                self.name += std::str::from_utf8(&text)?;
             }
             XMLEvent::CData(chars) => {
-                self.name += std::str::from_utf8(&chars).unwrap();
+                self.name += std::str::from_utf8(&chars)?;
             }
             XMLEvent::PI(_) | XMLEvent::DocType(_) | XMLEvent::Comment(_) => {}
-            XMLEvent::Eof => panic!("unclosed Namespace"),
-            _ => unimplemented!("unhandled Namespace child"),
+            XMLEvent::Eof => return Err(ParseError::BadCloseableState(self.close_state())),
+            _ => {
+                return Err(ParseError::UnhandledEvent {
+                    reason: "namespace can't contain nested tags",
+                })
+            }
         }
         Ok(())
     }
@@ -85,6 +107,68 @@ pub struct SiteInfo {
     state: CloseableState,
 }
 
+impl SiteInfo {
+    /// Name of the wiki (e.g. `Wikipedia`).
+    pub fn site_name(&self) -> Option<&str> {
+        self.site_name.value().map(String::as_str)
+    }
+
+    /// Database name of the wiki (e.g. `enwiki`).
+    pub fn db_name(&self) -> Option<&str> {
+        self.db_name.value().map(String::as_str)
+    }
+
+    /// URL of the main page of the wiki.
+    pub fn base(&self) -> Option<&str> {
+        self.base.value().map(String::as_str)
+    }
+
+    /// MediaWiki version that produced the dump.
+    pub fn generator(&self) -> Option<&str> {
+        self.generator.value().map(String::as_str)
+    }
+
+    /// Namespaces declared by the dump.
+    pub fn namespaces(&self) -> &[Namespace] {
+        self.ns.partial_value().unwrap_or_default()
+    }
+
+    /// Finds namespace key from either its key or (case-insensitive) name.
+    ///
+    /// If the dump doesn't declare namespaces any numeric key is accepted.
+    pub fn namespace_key(&self, name: &str) -> Option<isize> {
+        let name = name.trim();
+        let namespaces = self.namespaces();
+        match name.parse::<isize>() {
+            Ok(key) if namespaces.is_empty() => Some(key),
+            Ok(key) => namespaces.iter().find(|it| it.key == key).map(|it| it.key),
+            Err(_) => namespaces
+                .iter()
+                .find(|it| it.name.eq_ignore_ascii_case(name))
+                .map(|it| it.key),
+        }
+    }
+
+    /// Splits page title into namespace prefix and base title.
+    ///
+    /// Prefix is empty for pages in the main namespace, or if the prefix isn't
+    /// a namespace declared by the dump (e.g. `Star Wars: Episode I`).
+    pub fn split_title<'a>(&self, title: &'a str) -> (&'a str, &'a str) {
+        if let Some((prefix, base)) = title.split_once(':') {
+            let prefix = prefix.trim();
+            let name = prefix.replace('_', " ");
+            let is_namespace = self
+                .namespaces()
+                .iter()
+                .any(|it| !it.name.is_empty() && it.name.eq_ignore_ascii_case(&name));
+            if is_namespace {
+                return (prefix, base.trim());
+            }
+        }
+        ("", title.trim())
+    }
+}
+
 impl FromAttributes for SiteInfo {
     fn from_attributes(_: AttributeMap<'_>) -> ParseResult<Self> {
         Ok(SiteInfo::default())
@@ -128,13 +212,97 @@ impl Closeable for SiteInfo {
     }
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct Contributor {
+    pub username: ValueTag<String, "username">,
+    pub id: ValueTag<usize, "id">,
+    pub ip: ValueTag<String, "ip">,
+    /// Contributor information was suppressed (`deleted="deleted"`).
+    ///
+    /// Deleted contributors have no username, id, nor ip.
+    pub deleted: bool,
+    pub state: CloseableState,
+}
+
+impl Contributor {
+    /// Returns `true` if contributor is known, i.e. wasn't suppressed.
+    pub fn is_known(&self) -> bool {
+        !self.deleted && (self.username.value().is_some() || self.ip.value().is_some())
+    }
+
+    /// Returns contributor information as it's reported in output.
+    pub fn info(&self) -> ContributorInfo<'_> {
+        if self.deleted {
+            return ContributorInfo::Suppressed;
+        }
+        if let Some(name) = self.username.value() {
+            return ContributorInfo::User {
+                name,
+                id: self.id.value().copied(),
+            };
+        }
+        match self.ip.value() {
+            Some(address) => ContributorInfo::Ip { address },
+            None => ContributorInfo::Absent,
+        }
+    }
+}
+
+/// Revision author as reported in output.
+///
+/// Suppressed contributors (`deleted="deleted"`) are distinct from revisions
+/// without any contributor information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ContributorInfo<'a> {
+    Absent,
+    Suppressed,
+    User { name: &'a str, id: Option<usize> },
+    Ip { address: &'a str },
+}
+
+impl FromAttributes for Contributor {
+    fn from_attributes(attr: AttributeMap<'_>) -> ParseResult<Self> {
+        let deleted = match attr.get("deleted") {
+            Some(value) => value? == "deleted",
+            None => false,
+        };
+        Ok(Contributor {
+            deleted,
+            state: CloseableState::Open,
+            ..Default::default()
+        })
+    }
+}
+
+impl_forwarding_closeable_handler! {Contributor as contributor => [
+    contributor.username,
+    contributor.id,
+    contributor.ip,
+]}
+
+impl Closeable for Contributor {
+    const KEY: &'static str = "contributor";
+
+    fn close_state(&self) -> CloseableState {
+        self.state
+    }
+
+    fn close(&mut self) -> ParseResult<()> {
+        close_all_nested![self.username, self.id, self.ip];
+        self.state = CloseableState::Closed;
+        Ok(())
+    }
+}
+
 // TODO: Use DateTime<Utc> for timestamp & proper sha1 type
 #[derive(Debug, Default)]
 pub struct Revision {
     pub id: ValueTag<usize, "id">,
     pub parent_id: ValueTag<usize, "parentid">,
     pub timestamp: ValueTag<String, "timestamp">,
-    // contributor { username: str, id: usize }
+    /// Revision author; [`CloseableState::Unopened`] if absent from dump.
+    pub contributor: Contributor,
     // minor
     pub comment: ValueTag<String, "comment">,
     pub model: ValueTag<String, "model">,
@@ -148,6 +316,7 @@ impl_forwarding_closeable_handler! {Revision as rev => [
     rev.id,
     rev.parent_id,
     rev.timestamp,
+    rev.contributor,
     rev.comment,
     rev.model,
     rev.format,
@@ -174,6 +343,7 @@ impl Closeable for Revision {
             self.id,
             self.parent_id,
             self.timestamp,
+            self.contributor,
             self.comment,
             self.model,
             self.format,
@@ -190,68 +360,117 @@ pub struct WikiPage {
     pub title: ValueTag<String, "title">,
     pub ns: ValueTag<isize, "ns">,
     pub id: ValueTag<usize, "id">,
+    /// Page protection, e.g. `edit=sysop:move=sysop`; only in older dumps.
+    pub restrictions: ValueTag<String, "restrictions">,
+    pub sha1: ValueTag<String, "sha1">,
     pub redirect: Option<String>,
     pub revisions: Vec<Revision>,
+    /// Set if page is excluded by namespace; rest of its content is discarded
+    /// while parsing.
+    pub skip_content: bool,
     pub closed: bool,
 }
 
 fn redirect_target(tag: AttributeMap<'_>) -> String {
     if let Some(Ok(value)) = tag.get("title") {
-        value.to_string()
+        match quick_xml::escape::unescape(value) {
+            Ok(it) => it.into_owned(),
+            Err(_) => value.to_string(),
+        }
     } else {
         "unknown".to_string()
     }
 }
 
-impl_forwarding_closeable_handler! {WikiPage as page => [
-    page.title,
-    page.ns,
-    page.id,
-] or {
-    match event {
-        XMLEvent::Start(tag) => {
-            let last_rev = page.revisions.last_mut();
-            if let Some(last_rev) = last_rev {
-                return last_rev.handle_event(XMLEvent::Start(tag));
-            }
-
-            if tag.name().0 == b"revision" {
-                page.revisions.push(Revision {
-                    state: CloseableState::Open,
-                    ..Default::default()
-                });
-                return Ok(());
+// Not using impl_forwarding_closeable_handler because open revisions must
+// receive events before page fields: both pages and revisions have an `<id>`.
+impl HandleEvent for WikiPage {
+    fn handle_event(&mut self, event: XMLEvent<'_>) -> ParseResult<()> {
+        log::trace!("WikiPage handling event: {:?}", event);
+        if self.skip_content {
+            match &event {
+                XMLEvent::End(tag) if tag.name().0 == b"page" => return self.close(),
+                _ => return Ok(()),
             }
-        },
-        XMLEvent::Empty(tag) => {
-            let last_rev = page.revisions.last_mut();
-            if let Some(last_rev) = last_rev {
-                return last_rev.handle_event(XMLEvent::Empty(tag));
+        }
+        if let Some(last_rev) = self.revisions.last_mut() {
+            if last_rev.is_open() {
+                return last_rev.handle_event(event);
             }
+        }
 
-            if tag.name().0 == b"redirect" {
-                page.redirect = Some(redirect_target(AttributeMap::of(&tag)));
-                return Ok(());
+        match event {
+            XMLEvent::Start(tag) => {
+                forward_closeable!(XMLEvent::Start(tag) => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
+                start_closeable!(tag => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
+                if tag.name().0 == b"revision" {
+                    self.revisions.push(Revision {
+                        state: CloseableState::Open,
+                        ..Default::default()
+                    });
+                }
             }
-        },
-        XMLEvent::End(tag) => {
-            let last_rev = page.revisions.last_mut();
-            if let Some(last_rev) = last_rev {
-                return last_rev.handle_event(XMLEvent::End(tag));
+            XMLEvent::Empty(tag) => {
+                forward_closeable!(XMLEvent::Empty(tag) => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
+                empty_closeable!(tag => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
+                if tag.name().0 == b"redirect" {
+                    self.redirect = Some(redirect_target(AttributeMap::of(&tag)));
+                }
             }
-            if tag.name().0 == b"page" {
-                page.closed = true;
-                return Ok(());
+            XMLEvent::End(tag) => {
+                if tag.name().0 == b"title" && self.title.is_open() {
+                    // titles are compared with plain text, e.g. `--title-list`
+                    self.title.handle_event(XMLEvent::End(tag))?;
+                    return self.title.unescape();
+                }
+                forward_closeable!(XMLEvent::End(tag) => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
+                if tag.name().0 == b"page" {
+                    return self.close();
+                }
             }
-        }
-        other => {
-            let last_rev = page.revisions.last_mut();
-            if let Some(last_rev) = last_rev {
-                return last_rev.handle_event(other);
+            other => {
+                forward_closeable!(other => [
+                    self.title,
+                    self.ns,
+                    self.id,
+                    self.restrictions,
+                    self.sha1,
+                ]);
             }
         }
+        Ok(())
     }
-}}
+}
 
 impl Closeable for WikiPage {
     const KEY: &'static str = "page";
@@ -275,25 +494,98 @@ pub struct DocumentContext {
     pub file_name: String,
     pub namespace: Option<String>,
     pub site_info: SiteInfo,
-    pub pages: Vec<WikiPage>,
+    /// Parsed pages waiting to be processed; last one may still be open.
+    pub pages: VecDeque<WikiPage>,
+    /// Maximum number of pages in [`pages`](Self::pages) before parsing fails.
+    pub max_pages: Option<usize>,
+    /// Keys of namespaces to parse pages from; content of other pages is
+    /// skipped once their `<ns>` is read.
+    pub namespace_filter: Option<HashSet<isize>>,
+    /// Discard revision `<text>` while parsing, so only revision metadata is
+    /// kept.
+    pub skip_text: bool,
+    /// Set while content of a discarded `<text>` is being read.
+    in_skipped_text: bool,
+    /// Skip pages' content from their first `<revision>`, so only title,
+    /// namespace, id and redirect are parsed.
+    pub skip_revisions: bool,
 }
 
 impl DocumentContext {
     pub fn new(dump_file: &DumpLocation) -> Self {
+        Self::with_file_name(dump_file.name().to_string())
+    }
+
+    pub fn with_file_name(file_name: impl Into<String>) -> Self {
         DocumentContext {
-            file_name: dump_file.name().to_string(),
+            file_name: file_name.into(),
             namespace: None,
             site_info: SiteInfo::default(),
-            pages: Vec::with_capacity(1),
+            pages: VecDeque::with_capacity(2),
+            max_pages: None,
+            namespace_filter: None,
+            skip_text: false,
+            in_skipped_text: false,
+            skip_revisions: false,
         }
     }
+
+    /// Fails parsing if more than `limit` pages are waiting to be processed.
+    pub fn with_page_limit(mut self, limit: usize) -> Self {
+        self.max_pages = Some(limit);
+        self
+    }
 }
 
 const VALIDATE_NAMESPACE: bool = true;
 
 impl HandleEvent for DocumentContext {
     fn handle_event(&mut self, event: XMLEvent<'_>) -> ParseResult<()> {
+        if self.skip_text {
+            match &event {
+                XMLEvent::Start(tag) if tag.name().0 == b"text" => {
+                    self.in_skipped_text = true;
+                    return Ok(());
+                }
+                XMLEvent::End(tag) if tag.name().0 == b"text" => {
+                    self.in_skipped_text = false;
+                    return Ok(());
+                }
+                XMLEvent::Empty(tag) if tag.name().0 == b"text" => return Ok(()),
+                _ if self.in_skipped_text => return Ok(()),
+                _ => {}
+            }
+        }
         match event {
+            // text is assumed to be UTF-8 everywhere; other encodings would
+            // only fail later with invalid characters
+            XMLEvent::Decl(decl) if self.namespace.is_none() => {
+                if let Some(encoding) = decl.encoding().and_then(Result::ok) {
+                    if !encoding.eq_ignore_ascii_case(b"utf-8")
+                        && !encoding.eq_ignore_ascii_case(b"utf8")
+                    {
+                        return Err(ParseError::UnsupportedEncoding {
+                            encoding: String::from_utf8_lossy(&encoding).into_owned(),
+                        });
+                    }
+                }
+                return Ok(());
+            }
+            // prolog (BOM, DocType, whitespace) before root
+            XMLEvent::PI(_) | XMLEvent::DocType(_) | XMLEvent::Comment(_) | XMLEvent::Text(_)
+                if VALIDATE_NAMESPACE && self.namespace.is_none() =>
+            {
+                return Ok(());
+            }
+            XMLEvent::Empty(tag) if VALIDATE_NAMESPACE && self.namespace.is_none() => {
+                return Err(ParseError::InvalidFormat {
+                    reason: if tag.name().0 == b"mediawiki" {
+                        "empty mediawiki XML document"
+                    } else {
+                        "not a mediawiki XML document"
+                    },
+                });
+            }
             XMLEvent::Start(tag) if VALIDATE_NAMESPACE && self.namespace.is_none() => {
                 // this match case only handles document validation
                 if tag.name().0 != b"mediawiki" {
@@ -329,9 +621,13 @@ impl HandleEvent for DocumentContext {
                 forward_closeable!(XMLEvent::Start(tag) => [
                     self.site_info
                 ]);
-                let last_page = self.pages.last_mut();
+                let last_page = self.pages.back_mut();
                 if let Some(last_page) = last_page {
                     if !last_page.closed {
+                        if self.skip_revisions && tag.name().0 == b"revision" {
+                            last_page.skip_content = true;
+                            return Ok(());
+                        }
                         return last_page.handle_event(XMLEvent::Start(tag));
                     }
                 }
@@ -340,7 +636,10 @@ impl HandleEvent for DocumentContext {
                     self.site_info.state = CloseableState::Open;
                     return Ok(());
                 } else if tag.name().0 == b"page" {
-                    self.pages.push(WikiPage::default());
+                    if let Some(limit) = self.max_pages.filter(|it| self.pages.len() >= *it) {
+                        return Err(ParseError::PageLimit { limit });
+                    }
+                    self.pages.push_back(WikiPage::default());
                     return Ok(());
                 }
             }
@@ -348,18 +647,29 @@ impl HandleEvent for DocumentContext {
                 forward_closeable!(XMLEvent::End(tag) => [
                     self.site_info
                 ]);
-                let last_page = self.pages.last_mut();
+                let last_page = self.pages.back_mut();
                 if let Some(last_page) = last_page {
                     if !last_page.closed {
-                        return last_page.handle_event(XMLEvent::End(tag));
+                        last_page.handle_event(XMLEvent::End(tag))?;
+                        if let (Some(filter), Some(ns), false) = (
+                            &self.namespace_filter,
+                            last_page.ns.value(),
+                            last_page.skip_content,
+                        ) {
+                            last_page.skip_content = !filter.contains(ns);
+                        }
+                        return Ok(());
                     }
                 }
             }
+            XMLEvent::Empty(tag) if tag.name().0 == b"page" => {
+                log::warn!("ignoring empty <page/> element");
+            }
             other => {
                 forward_closeable!(other => [
                     self.site_info
                 ]);
-                let last_page = self.pages.last_mut();
+                let last_page = self.pages.back_mut();
                 if let Some(last_page) = last_page {
                     if !last_page.closed {
                         return last_page.handle_event(other);
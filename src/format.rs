@@ -1,13 +1,18 @@
 //! Logging and formatting utilites.
 
 use std::{
-    io::Write as _,
-    sync::atomic::{AtomicBool, Ordering},
+    io::{IsTerminal as _, Write as _},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
 };
 
 use env_logger::fmt::Formatter;
 use itertools::Itertools;
 use log::Record;
+use serde::Serialize;
 
 use crate::state::{get_tracker_global, DownloadTracker};
 
@@ -18,9 +23,57 @@ const ANSI_CLEAR_LINE: &str = "\x1b[0K";
 const ANSI_LIME: &[u8] = b"\x1b[92m";
 const ANSI_RESET: &[u8] = b"\x1b[39m";
 
+/// Minimum time between progress bar redraws in milliseconds.
+static PROGRESS_INTERVAL: AtomicU64 = AtomicU64::new(100);
+
+pub fn set_progress_interval(interval: Duration) {
+    PROGRESS_INTERVAL.store(interval.as_millis() as u64, Ordering::Release);
+}
+
+/// Whether progress is also shown in terminal window title.
+static TERMINAL_TITLE: AtomicBool = AtomicBool::new(false);
+
+/// Enables reporting progress in terminal window/tab title.
+///
+/// Title is only set when standard error is a terminal.
+pub fn set_terminal_title(enabled: bool) {
+    TERMINAL_TITLE.store(
+        enabled && std::io::stderr().is_terminal(),
+        Ordering::Release,
+    );
+}
+
+/// Whether progress is reported as JSON lines instead of a progress bar.
+static PROGRESS_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Replaces the progress bar with JSON lines written by [`print_progress_json`].
+///
+/// Log records are then formatted like with [`format_plain`].
+pub fn set_progress_json(enabled: bool) {
+    PROGRESS_JSON.store(enabled, Ordering::Release);
+}
+
+/// Time the last JSON progress line was written at.
+static LAST_PROGRESS_JSON: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Last drawn progress bar, reused by log records until the redraw interval
+/// elapses.
+struct PaintedProgress {
+    painted_at: Option<Instant>,
+    bar: Vec<u8>,
+}
+
+static LAST_PROGRESS: Mutex<PaintedProgress> = Mutex::new(PaintedProgress {
+    painted_at: None,
+    bar: Vec::new(),
+});
+
+/// Pads `text` with spaces on the left to `size` characters.
+///
+/// Text that's already `size` or more characters long is returned unchanged.
 pub fn left_pad(text: impl AsRef<str>, size: usize) -> String {
     let text = text.as_ref();
-    " ".repeat(size - text.len()) + text
+    " ".repeat(size.saturating_sub(text.chars().count())) + text
 }
 
 pub fn format_bytes(bytes: usize) -> String {
@@ -71,7 +124,12 @@ pub fn percent_pad(percent: f32, precision: usize) -> String {
     )
 }
 
-fn format_bar(out: &mut Vec<u8>, percent: f32, max_width: usize) -> std::io::Result<()> {
+/// Writes a progress bar exactly `max_width` characters wide.
+///
+/// `percent` outside of `0.0..=1.0` is clamped.
+pub fn format_bar(out: &mut Vec<u8>, percent: f32, max_width: usize) -> std::io::Result<()> {
+    // sizes from dump status can be smaller than actually read data
+    let percent = percent.clamp(0., 1.);
     let fill_width = (max_width as f32 * percent) as usize;
     let key = (((max_width as f32 * percent) - fill_width as f32) * 8.0).round() as u32;
 
@@ -134,7 +192,8 @@ fn print_progress_bar(tracker: &DownloadTracker) -> std::io::Result<Vec<u8>> {
             format_seconds(tracker.eta())
         )
     };
-    let bar_width = total_width - 45;
+    let bar_width =
+        total_width.saturating_sub(left_display.chars().count() + right_display.chars().count());
 
     let mut out = Vec::with_capacity(total_width + 128);
     out.write_all(ANSI_CLEAR_LINE.as_bytes())?;
@@ -147,12 +206,112 @@ fn print_progress_bar(tracker: &DownloadTracker) -> std::io::Result<Vec<u8>> {
     out.write_all(b" > ")?;
     out.write_all(current_file.as_ref().as_bytes())?;
     out.write_all(b"\n")?;
+    if TERMINAL_TITLE.load(Ordering::Acquire) {
+        // OSC 0: set window and tab title
+        write!(
+            out,
+            "\x1b]0;{} {:.0}% - {}\x07",
+            env!("CARGO_PKG_NAME"),
+            percent * 100.,
+            current_file.as_ref()
+        )?;
+    }
     out.flush()?;
 
     Ok(out)
 }
 
+/// Progress report written by `--progress-json`.
+#[derive(Serialize)]
+struct ProgressLine<'a> {
+    file: Option<&'a str>,
+    percent: f32,
+    bytes: usize,
+    eta_seconds: usize,
+    pages: usize,
+}
+
+/// Writes progress as a single JSON line to standard error.
+///
+/// Does nothing unless enabled with [`set_progress_json`], or if the last line
+/// was written less than the progress interval ago.
+pub fn print_progress_json(tracker: &DownloadTracker, pages: usize) -> std::io::Result<()> {
+    if !PROGRESS_JSON.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    {
+        let mut last = LAST_PROGRESS_JSON
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let interval = Duration::from_millis(PROGRESS_INTERVAL.load(Ordering::Acquire));
+        if last.is_some_and(|it| it.elapsed() < interval) {
+            return Ok(());
+        }
+        *last = Some(Instant::now());
+    }
+
+    let line = ProgressLine {
+        file: tracker.current_file().map(AsRef::as_ref),
+        // sizes from dump status can be smaller than actually read data
+        percent: (tracker.download_percent().clamp(0., 1.) * 10000.).round() / 100.,
+        bytes: tracker.downloaded(),
+        eta_seconds: tracker.eta(),
+        pages,
+    };
+    let mut out = serde_json::to_vec(&line)?;
+    out.push(b'\n');
+    std::io::stderr().lock().write_all(&out)
+}
+
+/// Enables processing of ANSI escape sequences by the terminal.
+///
+/// Older Windows consoles print escape sequences literally unless virtual
+/// terminal processing is enabled. Returns `false` if escapes aren't
+/// supported, in which case [`format_plain`] should be used.
+#[cfg(windows)]
+pub fn enable_ansi() -> bool {
+    use windows_sys::Win32::{
+        Foundation::INVALID_HANDLE_VALUE,
+        System::Console::{
+            GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+            STD_ERROR_HANDLE,
+        },
+    };
+
+    unsafe {
+        // SAFETY: handle is checked before use and mode points to a local.
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        if handle == INVALID_HANDLE_VALUE || handle == 0 {
+            return false;
+        }
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+            return true;
+        }
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+/// Enables processing of ANSI escape sequences by the terminal.
+///
+/// Always supported outside of Windows.
+#[cfg(not(windows))]
+pub fn enable_ansi() -> bool {
+    true
+}
+
+/// Log formatter without escape sequences or progress bar.
+pub fn format_plain(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    writeln!(buf, "[{}]: {}", record.level(), record.args())
+}
+
 pub fn format(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    if PROGRESS_JSON.load(Ordering::Acquire) {
+        return format_plain(buf, record);
+    }
     static HAS_BAR: AtomicBool = AtomicBool::new(false);
     let tracker = unsafe { get_tracker_global() };
 
@@ -165,7 +324,19 @@ pub fn format(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
 
     if let Some(tracker) = tracker {
         let message = record.args().to_string().split('\n').join("\n\x1b[0K");
-        let progress = print_progress_bar(tracker)?;
+        let progress = {
+            let mut last = LAST_PROGRESS.lock().unwrap_or_else(PoisonError::into_inner);
+            let interval = Duration::from_millis(PROGRESS_INTERVAL.load(Ordering::Acquire));
+            let expired = last
+                .painted_at
+                .map(|it| it.elapsed() >= interval)
+                .unwrap_or(true);
+            if expired {
+                last.bar = print_progress_bar(tracker)?;
+                last.painted_at = Some(Instant::now());
+            }
+            last.bar.clone()
+        };
 
         writeln!(buf, "[{}]: {}\x1b[0K", record.level(), message)?;
         buf.write_all(&progress)?;
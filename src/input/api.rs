@@ -0,0 +1,275 @@
+//! Input of current page revisions from the MediaWiki API.
+//!
+//! Fetched pages are written into a document in the same format as XML dumps,
+//! so they're processed exactly like pages read from a dump.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+};
+
+use clap::Parser;
+use quick_xml::escape::escape;
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use url::Url;
+
+/// Maximum number of titles the API accepts in a single query.
+const MAX_TITLES_PER_QUERY: usize = 50;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Parser, Serialize, Deserialize)]
+pub struct ApiParams {
+    /// URL of the API endpoint (e.g. `https://en.wikipedia.org/w/api.php`).
+    #[arg(name = "URL")]
+    pub url: Url,
+    /// Title of a page to extract.
+    ///
+    /// Can be specified multiple times. Redirects are followed to their target
+    /// page.
+    #[arg(long = "title", value_name = "TITLE")]
+    #[serde(default)]
+    pub titles: Vec<String>,
+    /// Extract all pages that are direct members of a category.
+    #[arg(long = "category", value_name = "CATEGORY")]
+    pub category: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: String,
+    info: String,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    query: Option<T>,
+    #[serde(rename = "continue")]
+    next: Option<HashMap<String, String>>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct GeneralInfo {
+    sitename: String,
+    #[serde(default)]
+    wikiid: String,
+    base: String,
+    generator: String,
+    case: String,
+}
+
+#[derive(Deserialize)]
+struct NamespaceInfo {
+    id: isize,
+    case: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SiteInfoQuery {
+    general: GeneralInfo,
+    namespaces: HashMap<String, NamespaceInfo>,
+}
+
+#[derive(Deserialize)]
+struct MainSlot {
+    contentmodel: String,
+    contentformat: String,
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct Slots {
+    main: MainSlot,
+}
+
+#[derive(Deserialize)]
+struct Revision {
+    revid: usize,
+    timestamp: Option<String>,
+    slots: Slots,
+}
+
+#[derive(Deserialize)]
+struct Page {
+    pageid: Option<usize>,
+    /// Missing for invalid titles.
+    #[serde(default)]
+    ns: isize,
+    title: String,
+    #[serde(default)]
+    missing: bool,
+    #[serde(default)]
+    invalid: bool,
+    #[serde(default)]
+    revisions: Vec<Revision>,
+}
+
+#[derive(Deserialize)]
+struct PagesQuery {
+    #[serde(default)]
+    pages: Vec<Page>,
+}
+
+fn api_error(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::ConnectionRefused, err)
+}
+
+impl ApiParams {
+    /// Sends a query, following continuations until all results are received.
+    ///
+    /// Returns query results of every response.
+    async fn query<T: DeserializeOwned>(
+        &self,
+        client: &Client,
+        params: &[(&str, &str)],
+    ) -> std::io::Result<Vec<T>> {
+        let mut result = Vec::new();
+        let mut next = HashMap::new();
+        loop {
+            let response = client
+                .get(self.url.clone())
+                .query(&[
+                    ("action", "query"),
+                    ("format", "json"),
+                    ("formatversion", "2"),
+                ])
+                .query(params)
+                .query(&next)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(api_error)?;
+            let body = response.text().await.map_err(api_error)?;
+            let response: Response<T> = serde_json::from_str(&body)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            if let Some(ApiError { code, info }) = response.error {
+                return Err(api_error(format!("API error '{}': {}", code, info)));
+            }
+            result.extend(response.query);
+            match response.next {
+                Some(it) => next = it,
+                None => return Ok(result),
+            }
+        }
+    }
+
+    /// Fetches requested pages and returns them as an XML export document.
+    ///
+    /// Missing pages are skipped with a warning.
+    pub async fn export(&self, client: &Client) -> std::io::Result<String> {
+        let site_info = self
+            .query::<SiteInfoQuery>(
+                client,
+                &[("meta", "siteinfo"), ("siprop", "general|namespaces")],
+            )
+            .await?
+            .pop()
+            .ok_or_else(|| api_error("API returned no site info"))?;
+
+        let revisions = [
+            ("prop", "revisions"),
+            ("rvprop", "ids|timestamp|content"),
+            ("rvslots", "main"),
+            ("redirects", "1"),
+        ];
+        let mut batches = Vec::new();
+        for titles in self.titles.chunks(MAX_TITLES_PER_QUERY) {
+            let titles = titles.join("|");
+            let params = [revisions.as_slice(), &[("titles", titles.as_str())]].concat();
+            batches.extend(self.query::<PagesQuery>(client, &params).await?);
+        }
+        if let Some(category) = &self.category {
+            let category = match category.contains(':') {
+                true => category.clone(),
+                false => format!("Category:{}", category),
+            };
+            let params = [
+                revisions.as_slice(),
+                &[
+                    ("generator", "categorymembers"),
+                    ("gcmtitle", category.as_str()),
+                    ("gcmlimit", "max"),
+                ],
+            ]
+            .concat();
+            batches.extend(self.query::<PagesQuery>(client, &params).await?);
+        }
+
+        // continued queries list a page again for each batch of revisions
+        let mut pages = BTreeMap::<String, Page>::new();
+        for page in batches.into_iter().flat_map(|it| it.pages) {
+            if page.missing || page.invalid {
+                log::warn!("page '{}' doesn't exist", page.title);
+                continue;
+            }
+            match pages.get_mut(&page.title) {
+                Some(existing) => existing.revisions.extend(page.revisions),
+                None => {
+                    pages.insert(page.title.clone(), page);
+                }
+            }
+        }
+
+        Ok(export_document(&site_info, pages.values()))
+    }
+}
+
+/// Writes site info and pages in XML dump format.
+fn export_document<'a>(site_info: &SiteInfoQuery, pages: impl Iterator<Item = &'a Page>) -> String {
+    let general = &site_info.general;
+    let mut result = String::with_capacity(4096);
+    result.push_str(
+        "<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\" version=\"0.11\">\n",
+    );
+    // writing into a String can't fail
+    let _ = write!(
+        result,
+        "<siteinfo><sitename>{}</sitename><dbname>{}</dbname><base>{}</base>\
+         <generator>{}</generator><case>{}</case><namespaces>",
+        escape(&general.sitename),
+        escape(&general.wikiid),
+        escape(&general.base),
+        escape(&general.generator),
+        escape(&general.case),
+    );
+    let mut namespaces = site_info.namespaces.values().collect::<Vec<_>>();
+    namespaces.sort_by_key(|it| it.id);
+    for namespace in namespaces {
+        let _ = write!(
+            result,
+            "<namespace key=\"{}\" case=\"{}\">{}</namespace>",
+            namespace.id,
+            escape(&namespace.case),
+            escape(&namespace.name),
+        );
+    }
+    result.push_str("</namespaces></siteinfo>\n");
+
+    for page in pages {
+        let _ = write!(
+            result,
+            "<page><title>{}</title><ns>{}</ns><id>{}</id>",
+            escape(&page.title),
+            page.ns,
+            page.pageid.unwrap_or_default(),
+        );
+        for revision in &page.revisions {
+            let main = &revision.slots.main;
+            let _ = write!(
+                result,
+                "<revision><id>{}</id><timestamp>{}</timestamp><model>{}</model>\
+                 <format>{}</format><text xml:space=\"preserve\">{}</text></revision>",
+                revision.revid,
+                escape(revision.timestamp.as_deref().unwrap_or_default()),
+                escape(&main.contentmodel),
+                escape(&main.contentformat),
+                escape(&main.content),
+            );
+        }
+        result.push_str("</page>\n");
+    }
+    result.push_str("</mediawiki>\n");
+    result
+}
@@ -0,0 +1,190 @@
+//! Local copies of remote dump files.
+//!
+//! Files are written to a `.part` file while they're streamed, and only moved
+//! to their final path once the whole file was received and matches the size
+//! and checksum listed by the mirror. Interrupted downloads are resumed from
+//! the `.part` file on the next run.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Chain, ErrorKind, Read, Result, Take, Write},
+    path::PathBuf,
+};
+
+use sha1::{Digest, Sha1};
+
+/// Location of a file in the cache directory.
+#[derive(Debug, Clone)]
+pub struct CachePath {
+    path: PathBuf,
+    /// Size listed by the mirror.
+    size: usize,
+    /// SHA-1 checksum listed by the mirror, if any.
+    sha1: Option<String>,
+}
+
+impl CachePath {
+    pub fn new(path: PathBuf, size: usize, sha1: Option<String>) -> Self {
+        CachePath { path, size, sha1 }
+    }
+
+    fn part_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".part");
+        self.path.with_file_name(name)
+    }
+
+    /// Opens the cached file if it's complete.
+    ///
+    /// The file is checked against the listed checksum as well, because the
+    /// `latest` version reuses the same path for newer dumps.
+    pub fn open_complete(&self) -> Result<Option<File>> {
+        match std::fs::metadata(&self.path) {
+            Ok(it) if it.len() == self.size as u64 && self.checksum_matches()? => {
+                Ok(Some(File::open(&self.path)?))
+            }
+            Ok(_) => {
+                log::warn!(
+                    "cached {} doesn't match listed size or checksum; downloading it again",
+                    self.path.display()
+                );
+                Ok(None)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks whether the cached file matches the listed checksum, if there
+    /// is one.
+    fn checksum_matches(&self) -> Result<bool> {
+        let expected = match &self.sha1 {
+            Some(it) => it,
+            None => return Ok(true),
+        };
+        let mut hasher = Sha1::new();
+        std::io::copy(&mut File::open(&self.path)?, &mut hasher)?;
+        Ok(expected.eq_ignore_ascii_case(&format!("{:x}", hasher.finalize())))
+    }
+
+    /// Opens the partially downloaded file.
+    ///
+    /// Returns the file along with the number of bytes it contains. Files
+    /// that can't be part of the listed file are discarded.
+    pub fn open_partial(&self) -> Result<(File, usize)> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let part_path = self.part_path();
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .truncate(false)
+            .open(&part_path)?;
+        let len = file.metadata()?.len() as usize;
+        if len >= self.size && len > 0 {
+            file.set_len(0)?;
+            return Ok((file, 0));
+        }
+        Ok((file, len))
+    }
+
+    /// Wraps a reader which continues the partial file at `offset`.
+    ///
+    /// Bytes already in the partial file are read before those of `remote`.
+    /// If `offset` is zero, partial file is discarded.
+    pub fn writer<R: Read>(
+        &self,
+        partial: File,
+        offset: usize,
+        remote: R,
+    ) -> Result<CacheWriter<Chain<Take<File>, R>>> {
+        if offset == 0 {
+            partial.set_len(0)?;
+        }
+        let cached = File::open(self.part_path())?.take(offset as u64);
+        Ok(CacheWriter {
+            inner: cached.chain(remote),
+            partial,
+            offset,
+            position: 0,
+            hasher: Sha1::new(),
+            target: self.clone(),
+            finished: false,
+        })
+    }
+}
+
+/// Reader that writes remote bytes it reads to the partial cache file.
+///
+/// Once the end of input or listed size is reached, the partial file is moved
+/// to the cache path if it's complete, or removed otherwise. A file that
+/// turns out longer than the listed size isn't cached.
+pub struct CacheWriter<R: Read> {
+    inner: R,
+    /// Partial file, opened for appending.
+    partial: File,
+    /// Number of bytes that were already in the partial file.
+    offset: usize,
+    /// Number of bytes read so far.
+    position: usize,
+    hasher: Sha1,
+    target: CachePath,
+    finished: bool,
+}
+
+impl<R: Read> CacheWriter<R> {
+    fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        self.partial.flush()?;
+
+        let part_path = self.target.part_path();
+        let checksum = format!("{:x}", std::mem::take(&mut self.hasher).finalize());
+        let valid_checksum = match &self.target.sha1 {
+            Some(it) => it.eq_ignore_ascii_case(&checksum),
+            None => true,
+        };
+        if self.position != self.target.size || !valid_checksum {
+            log::warn!(
+                "downloaded {} doesn't match listed size or checksum; not caching it",
+                self.target.path.display()
+            );
+            return std::fs::remove_file(part_path);
+        }
+        std::fs::rename(part_path, &self.target.path)?;
+        log::info!("Cached {}", self.target.path.display());
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for CacheWriter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            if !self.finished {
+                self.finish()?;
+            }
+            return Ok(0);
+        }
+
+        // cache file was already moved; bytes past the listed size must not
+        // be appended to it
+        if self.finished {
+            return Ok(read);
+        }
+
+        let read_bytes = &buf[..read];
+        self.hasher.update(read_bytes);
+        // bytes from the partial file are already cached
+        let cached = self.offset.saturating_sub(self.position).min(read);
+        self.partial.write_all(&read_bytes[cached..])?;
+        self.position += read;
+        // decompression ends with the compressed stream, without reading
+        // end of input
+        if self.position >= self.target.size && !self.finished {
+            self.finish()?;
+        }
+        Ok(read)
+    }
+}
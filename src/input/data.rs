@@ -2,28 +2,43 @@ use std::{
     collections::{BTreeMap, HashMap},
     fmt::Display,
     fs::File,
-    io::{ErrorKind, Seek},
+    io::{BufRead as _, Cursor, ErrorKind, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{Arc, Mutex},
 };
 
-use bytes::Bytes;
 use clap::{Parser, Subcommand};
+use reqwest::{header::RANGE, Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::runtime::Handle;
 use url::Url;
 
-use super::io::{CompressionAdapter, DocumentStream, SourceAdapter};
-use crate::client;
+use super::{
+    api::ApiParams,
+    cache::CachePath,
+    io::{Compression, CompressionAdapter, DocumentStream, SourceAdapter, DEFAULT_CHUNK_RETRIES},
+    pages::{PageReader, ReadError},
+};
+use crate::dump_data::DocumentContext;
 
 static DUMP_STATUS_FILE: &str = "dumpstatus.json";
+/// URL layout used by Wikimedia and most of its mirrors.
+pub const DEFAULT_URL_TEMPLATE: &str = "{base}/{lang}wiki/{version}/{file}";
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Parser, Serialize, Deserialize)]
 pub struct RemoteParams {
     /// Remote mirror file
     #[arg(name = "URL")]
     pub base: Url,
+    /// Fallback mirror, tried if requests to earlier ones fail.
+    ///
+    /// Can be specified multiple times; mirrors are tried in order, after the
+    /// primary one.
+    #[arg(long = "mirror", value_name = "URL")]
+    #[serde(default)]
+    pub mirrors: Vec<Url>,
     /// Dump version (i.e. date) to download.
     #[arg(
         short = 'w',
@@ -38,9 +53,113 @@ pub struct RemoteParams {
         default_value_t = {"en".to_string()},
     )]
     pub language: String,
+    /// Number of times a failed read of a dump file is retried before giving
+    /// up.
+    ///
+    /// Each retry reconnects to the mirror and continues where the failed
+    /// read stopped, waiting twice as long as the previous one (at most a
+    /// minute).
+    #[arg(long = "max-retries", value_name = "N", default_value_t = DEFAULT_CHUNK_RETRIES)]
+    pub max_retries: u32,
+    /// Older dump version to compare against; only pages with a newer latest
+    /// revision are extracted.
+    ///
+    /// Latest revision ids of all pages in the older version are read (and
+    /// kept in memory) before extraction starts.
+    #[arg(long = "since-version", value_name = "VERSION")]
+    pub since_version: Option<String>,
+    /// Layout of file URLs on the mirror.
+    ///
+    /// Placeholders `{base}`, `{lang}`, `{version}` and `{file}` are replaced
+    /// with mirror URL (without a trailing `/`), language, dump version and
+    /// file name.
+    #[arg(
+        long = "url-template",
+        value_name = "TEMPLATE",
+        default_value = DEFAULT_URL_TEMPLATE
+    )]
+    pub url_template: String,
+    /// Directory to keep downloaded dump files in.
+    ///
+    /// Files are downloaded into it on the first run and read from it on
+    /// later runs if they still match the listed checksum. Interrupted
+    /// downloads are resumed.
+    #[arg(long = "cache-dir", value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl RemoteParams {
+    /// URL of a `file` from the dump, following the
+    /// [URL template](Self::url_template).
+    pub fn file_url(&self, file: impl AsRef<str>) -> String {
+        self.mirror_file_url(&self.base, file)
+    }
+
+    /// URLs of a `file` on the primary and all fallback mirrors, in the order
+    /// they should be tried.
+    pub fn file_urls(&self, file: impl AsRef<str>) -> Vec<String> {
+        std::iter::once(&self.base)
+            .chain(&self.mirrors)
+            .map(|base| self.mirror_file_url(base, file.as_ref()))
+            .collect()
+    }
+
+    fn mirror_file_url(&self, base: &Url, file: impl AsRef<str>) -> String {
+        self.url_template
+            .replace("{base}", base.as_str().trim_end_matches('/'))
+            .replace("{lang}", &self.language)
+            .replace("{version}", &self.version)
+            .replace("{file}", file.as_ref())
+    }
+
+    /// Sends a GET request for a `file`, falling back to other mirrors on
+    /// connection failure or an HTTP error status.
+    ///
+    /// Returns the URL that responded along with its response.
+    pub async fn get_file(
+        &self,
+        client: &Client,
+        file: impl AsRef<str>,
+    ) -> reqwest::Result<(String, reqwest::Response)> {
+        self.get_file_from(client, file, 0).await
+    }
+
+    /// Same as [`get_file`](Self::get_file), but requests content starting
+    /// at byte `start`.
+    ///
+    /// Mirrors that don't support ranges respond with the whole file, so
+    /// response status has to be checked for
+    /// [`PARTIAL_CONTENT`](StatusCode::PARTIAL_CONTENT).
+    pub async fn get_file_from(
+        &self,
+        client: &Client,
+        file: impl AsRef<str>,
+        start: usize,
+    ) -> reqwest::Result<(String, reqwest::Response)> {
+        let mut urls = self.file_urls(file).into_iter().peekable();
+        loop {
+            let url = urls.next().expect("no mirror URLs");
+            let mut request = client.get(&url);
+            if start > 0 {
+                request = request.header(RANGE, format!("bytes={}-", start));
+            }
+            let result = request
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+            match result {
+                Ok(resp) => return Ok((url, resp)),
+                Err(err) if urls.peek().is_some() => {
+                    log::warn!("Request to {} failed: {}; trying next mirror", url, err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SourceLocation {
     /// Use remote dump file(s) as input.
     Remote {
@@ -53,6 +172,11 @@ pub enum SourceLocation {
         #[arg(name = "PATH")]
         path: PathBuf,
     },
+    /// Use current revisions of pages fetched from a MediaWiki API as input.
+    Api {
+        #[clap(flatten)]
+        params: ApiParams,
+    },
 }
 
 impl Default for SourceLocation {
@@ -60,8 +184,13 @@ impl Default for SourceLocation {
         SourceLocation::Remote {
             params: RemoteParams {
                 base: Url::parse("https://dumps.wikimedia.org/").unwrap(),
+                mirrors: Vec::new(),
                 version: "latest".to_string(),
                 language: "en".to_string(),
+                max_retries: DEFAULT_CHUNK_RETRIES,
+                since_version: None,
+                url_template: DEFAULT_URL_TEMPLATE.to_string(),
+                cache_dir: None,
             },
         }
     }
@@ -76,6 +205,7 @@ impl Display for SourceLocation {
                         base,
                         version,
                         language,
+                        ..
                     },
             } => f.write_fmt(format_args!(
                 "{}/{}wiki/{}",
@@ -84,6 +214,7 @@ impl Display for SourceLocation {
                 language
             )),
             SourceLocation::Local { path } => f.write_str(path.display().to_string().as_str()),
+            SourceLocation::Api { params } => f.write_str(params.url.as_str()),
         }
     }
 }
@@ -96,8 +227,13 @@ impl FromStr for SourceLocation {
             Ok(it) => Ok(SourceLocation::Remote {
                 params: RemoteParams {
                     base: it,
+                    mirrors: Vec::new(),
                     version: "latest".to_string(),
                     language: "en".to_string(),
+                    max_retries: DEFAULT_CHUNK_RETRIES,
+                    since_version: None,
+                    url_template: DEFAULT_URL_TEMPLATE.to_string(),
+                    cache_dir: None,
                 },
             }),
             Err(_) => PathBuf::from_str(s).map(|path| SourceLocation::Local { path }),
@@ -105,10 +241,201 @@ impl FromStr for SourceLocation {
     }
 }
 
+/// File contained in a local tar archive.
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
+pub struct ArchiveMember {
+    /// Offset of member data within the (decompressed) archive.
+    offset: u64,
+    size: u64,
+}
+
+/// Checks whether path points to a tar archive, and whether the archive is
+/// bzip2 compressed.
+fn tar_archive_kind(path: &Path) -> Option<bool> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".tar") {
+        Some(false)
+    } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz2") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Creates descriptor for an XML file contained in a tar archive.
+fn member_descriptor<R: Read>(
+    archive: &Path,
+    entry: &tar::Entry<'_, R>,
+) -> std::io::Result<Option<FileDescriptor>> {
+    if !entry.header().entry_type().is_file() {
+        return Ok(None);
+    }
+    let entry_path = entry.path()?.to_string_lossy().to_string();
+    // XML files may be compressed on their own as well
+    let xml_name = match entry_path.rsplit_once('.') {
+        Some((stem, ext)) if Compression::from_extension(Some(ext)) != Compression::None => stem,
+        _ => entry_path.as_str(),
+    };
+    if !xml_name.ends_with(".xml") {
+        log::debug!("skipping non-XML archive member: {}", entry_path);
+        return Ok(None);
+    }
+    Ok(Some(FileDescriptor {
+        size: entry.size() as usize,
+        path: DumpLocation {
+            base: SourceLocation::Local {
+                path: archive.to_path_buf(),
+            },
+            file_name: FileName(entry_path),
+            member: Some(ArchiveMember {
+                offset: entry.raw_file_position(),
+                size: entry.size(),
+            }),
+        },
+        md5: None,
+        sha1: None,
+    }))
+}
+
+/// Lists XML files contained in a local tar archive.
+///
+/// Compressed archives have to be fully decompressed to find their members.
+fn archive_files(path: &Path) -> std::io::Result<BTreeMap<FileName, FileDescriptor>> {
+    let file = File::open(path)?;
+
+    let mut members = Vec::new();
+    if tar_archive_kind(path).unwrap_or_default() {
+        let mut archive = tar::Archive::new(bzip2::read::MultiBzDecoder::new(file));
+        for entry in archive.entries()? {
+            members.extend(member_descriptor(path, &entry?)?);
+        }
+    } else {
+        let mut archive = tar::Archive::new(file);
+        for entry in archive.entries_with_seek()? {
+            members.extend(member_descriptor(path, &entry?)?);
+        }
+    }
+
+    if members.is_empty() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("archive {} contains no XML files", path.display()),
+        ));
+    }
+    Ok(members
+        .into_iter()
+        .map(|it| (it.path.file_name.clone(), it))
+        .collect())
+}
+
+/// Decompressed archive left over after reading one of its members.
+///
+/// Members are usually read in archive order, so following members continue
+/// where the previous one ended instead of decompressing the archive from
+/// the start again. Owned by [`DumpInfo`] and shared with readers of its
+/// members, which return the decoder once they're dropped.
+#[derive(Clone, Default)]
+struct ArchiveDecoder(Arc<Mutex<Option<OpenArchive>>>);
+
+impl std::fmt::Debug for ArchiveDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ArchiveDecoder")
+    }
+}
+
+struct OpenArchive {
+    path: PathBuf,
+    /// Offset within the decompressed archive the decoder is at.
+    position: u64,
+    decoder: bzip2::read::MultiBzDecoder<File>,
+}
+
+impl OpenArchive {
+    /// Opens member data at `offset` of a bzip2 compressed archive, reusing
+    /// the decoder left in `slot` if it didn't pass the member yet.
+    fn member(
+        slot: &ArchiveDecoder,
+        path: &Path,
+        offset: u64,
+        size: u64,
+    ) -> std::io::Result<ArchiveMemberReader> {
+        let reused = slot
+            .0
+            .lock()
+            .ok()
+            .and_then(|mut it| it.take())
+            .filter(|it| it.path == path && it.position <= offset);
+        let mut archive = match reused {
+            Some(it) => it,
+            None => OpenArchive {
+                path: path.to_path_buf(),
+                position: 0,
+                decoder: bzip2::read::MultiBzDecoder::new(File::open(path)?),
+            },
+        };
+
+        // compressed stream can't be seeked; skip preceding data instead
+        let skip = offset - archive.position;
+        let skipped = std::io::copy(&mut (&mut archive.decoder).take(skip), &mut std::io::sink())?;
+        archive.position += skipped;
+        if skipped < skip {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("archive {} ended before member data", path.display()),
+            ));
+        }
+        Ok(ArchiveMemberReader {
+            slot: slot.clone(),
+            archive: Some(archive),
+            end: offset + size,
+        })
+    }
+}
+
+/// Reader of member data in a bzip2 compressed archive.
+///
+/// Archive is kept for following members once the reader is dropped.
+struct ArchiveMemberReader {
+    slot: ArchiveDecoder,
+    archive: Option<OpenArchive>,
+    end: u64,
+}
+
+impl Read for ArchiveMemberReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let archive = match &mut self.archive {
+            Some(it) => it,
+            None => return Ok(0),
+        };
+        let left = (self.end - archive.position).min(buf.len() as u64) as usize;
+        match archive.decoder.read(&mut buf[..left]) {
+            Ok(read) => {
+                archive.position += read as u64;
+                Ok(read)
+            }
+            Err(err) => {
+                // decoder state is unknown after an error
+                self.archive = None;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Drop for ArchiveMemberReader {
+    fn drop(&mut self) {
+        if let (Some(archive), Ok(mut open)) = (self.archive.take(), self.slot.0.lock()) {
+            *open = Some(archive);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct DumpLocation {
     base: SourceLocation,
     file_name: FileName,
+    /// Location within a tar archive, if file is an archive member.
+    member: Option<ArchiveMember>,
 }
 
 impl DumpLocation {
@@ -117,44 +444,78 @@ impl DumpLocation {
         &self.file_name
     }
 
+    /// Compression of the file according to its extension.
     #[inline(always)]
-    pub fn is_compressed(&self) -> bool {
-        self.file_name.ext() == Some("bz2")
+    pub fn extension_compression(&self) -> Compression {
+        Compression::from_extension(self.file_name.ext())
     }
 
-    fn read_adapter(&self, rt: &Handle) -> std::io::Result<SourceAdapter> {
+    fn read_adapter(
+        &self,
+        rt: &Handle,
+        client: &Client,
+        archive: &ArchiveDecoder,
+    ) -> std::io::Result<SourceAdapter> {
         Ok(match &self.base {
             SourceLocation::Local { path } => {
-                let file = File::open(path)?;
-                SourceAdapter::Local(std::io::BufReader::new(file))
+                let mut file = File::open(path)?;
+                match &self.member {
+                    Some(ArchiveMember { offset, size }) => {
+                        if tar_archive_kind(path).unwrap_or_default() {
+                            SourceAdapter::new_local(OpenArchive::member(
+                                archive, path, *offset, *size,
+                            )?)
+                        } else {
+                            file.seek(SeekFrom::Start(*offset))?;
+                            SourceAdapter::new_local(file.take(*size))
+                        }
+                    }
+                    None => SourceAdapter::new_seekable(file),
+                }
             }
             SourceLocation::Remote { params } => {
-                let file_url = format!(
-                    "{}/{}wiki/{}/{}",
-                    params.base, params.language, params.version, self.file_name
-                );
-                let file_response = rt.block_on(client().get(file_url).send()).map_err(|err| {
-                    std::io::Error::new(std::io::ErrorKind::ConnectionRefused, err)
-                })?;
-                SourceAdapter::Remote {
-                    resp: file_response,
-                    buffer: Bytes::new(),
-                    pos: 0,
-                    runtime: rt.clone(),
-                }
+                let (file_url, file_response) = rt
+                    .block_on(params.get_file(client, &self.file_name))
+                    .map_err(|err| {
+                        std::io::Error::new(std::io::ErrorKind::ConnectionRefused, err)
+                    })?;
+                SourceAdapter::new_remote(
+                    client.clone(),
+                    file_url,
+                    file_response,
+                    0,
+                    params.max_retries,
+                    rt.clone(),
+                )
+            }
+            SourceLocation::Api { params } => {
+                let document = rt.block_on(params.export(client))?;
+                SourceAdapter::new_seekable(Cursor::new(document.into_bytes()))
             }
         })
     }
 
-    pub fn stream(&self, rt: &Handle) -> std::io::Result<DocumentStream> {
-        let reader = self.read_adapter(rt)?;
+    pub fn stream(&self, rt: &Handle, client: &Client) -> std::io::Result<DocumentStream> {
+        self.document_stream(self.read_adapter(rt, client, &ArchiveDecoder::default())?)
+    }
 
-        let reader = if self.is_compressed() {
-            CompressionAdapter::new_bzip2(reader)
-        } else {
-            CompressionAdapter::new_passthrough(reader)
+    /// Decompresses file `reader`, detecting its compression.
+    fn document_stream(&self, mut reader: SourceAdapter) -> std::io::Result<DocumentStream> {
+        // mirrors don't always name files correctly, so content is trusted
+        // over extension
+        let named = self.extension_compression();
+        let compression = match Compression::sniff(reader.fill_buf()?) {
+            Some(it) => {
+                if it != named {
+                    log::warn!("{} is {}, but named as {}", self.file_name, it, named);
+                }
+                it
+            }
+            None => named,
         };
 
+        let reader = CompressionAdapter::new(compression, reader)?;
+
         Ok(DocumentStream::new(reader))
     }
 }
@@ -167,6 +528,84 @@ pub struct FileDescriptor {
     pub sha1: Option<String>,
 }
 
+impl FileDescriptor {
+    /// Opens the file for reading.
+    ///
+    /// Remote files are read through the [cache directory](RemoteParams::cache_dir)
+    /// if one is set; otherwise this is the same as [`DumpLocation::stream`].
+    ///
+    /// Members of compressed archives are decompressed from the start of the
+    /// archive; use [`DumpInfo::stream`] to continue from previously read
+    /// members instead.
+    pub fn stream(&self, rt: &Handle, client: &Client) -> std::io::Result<DocumentStream> {
+        self.open(rt, client, &ArchiveDecoder::default())
+    }
+
+    fn open(
+        &self,
+        rt: &Handle,
+        client: &Client,
+        archive: &ArchiveDecoder,
+    ) -> std::io::Result<DocumentStream> {
+        let reader = match &self.path.base {
+            SourceLocation::Remote {
+                params:
+                    params @ RemoteParams {
+                        cache_dir: Some(dir),
+                        ..
+                    },
+            } => self.cached_adapter(params, dir, rt, client)?,
+            _ => self.path.read_adapter(rt, client, archive)?,
+        };
+        self.path.document_stream(reader)
+    }
+
+    /// Reads a remote file from the cache, downloading it into the cache
+    /// first if it's missing or incomplete.
+    fn cached_adapter(
+        &self,
+        params: &RemoteParams,
+        dir: &Path,
+        rt: &Handle,
+        client: &Client,
+    ) -> std::io::Result<SourceAdapter> {
+        let file_name = &self.path.file_name;
+        let path = dir
+            .join(format!("{}wiki", params.language))
+            .join(&params.version)
+            .join(file_name.as_ref());
+        let cache = CachePath::new(path, self.size, self.sha1.clone());
+        if let Some(file) = cache.open_complete()? {
+            log::info!("Reading {} from cache", file_name);
+            return Ok(SourceAdapter::new_seekable(file));
+        }
+
+        let (partial, cached) = cache.open_partial()?;
+        let (file_url, file_response) = rt
+            .block_on(params.get_file_from(client, file_name, cached))
+            .map_err(|err| std::io::Error::new(ErrorKind::ConnectionRefused, err))?;
+        // mirror sent the whole file if it doesn't support ranges
+        let offset = match file_response.status() {
+            StatusCode::PARTIAL_CONTENT => cached,
+            _ => 0,
+        };
+        if offset > 0 {
+            log::info!("Resuming download of {} at {} bytes", file_name, offset);
+        }
+        let remote = SourceAdapter::new_remote(
+            client.clone(),
+            file_url,
+            file_response,
+            offset,
+            params.max_retries,
+            rt.clone(),
+        );
+        Ok(SourceAdapter::new_local(
+            cache.writer(partial, offset, remote)?,
+        ))
+    }
+}
+
 #[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 struct MirrorDumpEntry {
     pub size: usize,
@@ -191,6 +630,7 @@ impl MirrorDumpEntry {
                     params: source.clone(),
                 },
                 file_name,
+                member: None,
             },
             md5: self.md5.clone(),
             sha1: self.sha1.clone(),
@@ -210,12 +650,36 @@ pub struct NotAFile {
 pub struct FileName(String);
 
 impl FileName {
+    /// File name without URL query or fragment, and without leading dot of
+    /// hidden files.
+    fn stem_and_ext(&self) -> &str {
+        let name = self.0.split(['?', '#']).next().unwrap_or_default();
+        name.strip_prefix('.').unwrap_or(name)
+    }
+
+    /// All extensions, e.g. `xml.bz2` for `pages-articles.xml.bz2`.
     pub fn full_ext(&self) -> Option<&str> {
-        self.0.find('.').map(|i| &self.0[(i + 1)..])
+        let name = self.stem_and_ext();
+        name.find('.')
+            .map(|i| &name[(i + 1)..])
+            .filter(|it| !it.is_empty())
     }
 
+    /// Last extension, e.g. `bz2` for `pages-articles.xml.bz2`.
     pub fn ext(&self) -> Option<&str> {
-        self.0.rfind('.').map(|i| &self.0[(i + 1)..])
+        self.extensions().last().copied()
+    }
+
+    /// Extension segments in order, e.g. `["xml", "bz2"]` for
+    /// `pages-articles.xml.bz2`.
+    ///
+    /// Empty if the name has no extension.
+    pub fn extensions(&self) -> Vec<&str> {
+        self.stem_and_ext()
+            .split('.')
+            .skip(1)
+            .filter(|it| !it.is_empty())
+            .collect()
     }
 }
 
@@ -282,33 +746,54 @@ impl PartialOrd for FileName {
     }
 }
 
+/// Status of the articles dump job on the mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Done,
+    /// Job hasn't started yet.
+    Waiting,
+    /// Files are still being written.
+    Running,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
 #[derive(Debug, Default, Clone, Deserialize)]
 pub struct DumpInfo {
-    pub status: Option<String>,
+    /// Status of the job; `None` for local dumps.
+    pub status: Option<JobStatus>,
     pub updated: Option<String>,
     pub files: BTreeMap<FileName, FileDescriptor>,
+    #[serde(skip)]
+    archive: ArchiveDecoder,
 }
 
 impl DumpInfo {
+    /// Opens one of the dump `file`s for reading.
+    ///
+    /// Members of a compressed archive share its decoder, so reading them in
+    /// archive order decompresses the archive only once.
+    pub fn stream(
+        &self,
+        file: &FileDescriptor,
+        rt: &Handle,
+        client: &Client,
+    ) -> std::io::Result<DocumentStream> {
+        file.open(rt, client, &self.archive)
+    }
+
     // TODO: Return errors
-    async fn new_remote(params: &RemoteParams) -> DumpInfo {
+    async fn new_remote(client: &Client, params: &RemoteParams) -> DumpInfo {
         use serde_json::*;
 
-        let RemoteParams {
-            base: base_url,
-            version,
-            language,
-        } = params;
-
-        let file = format!(
-            "{}/{}wiki/{}/{}",
-            base_url, language, version, DUMP_STATUS_FILE
-        );
-        let dump_status_url = Url::parse(&file).expect("invalid dump status url format");
-
-        let resp = match client().get(dump_status_url).send().await {
-            Ok(it) => it,
-            Err(_) => panic!("invalid dump status url"),
+        let resp = match params.get_file(client, DUMP_STATUS_FILE).await {
+            Ok((_, it)) => it,
+            Err(err) => panic!(
+                "unable to get '{}' from any mirror: {}",
+                DUMP_STATUS_FILE, err
+            ),
         };
 
         let dump_status = match resp.text().await {
@@ -341,17 +826,18 @@ impl DumpInfo {
             Err(_) => panic!("dump remote URL doesn't have a supported JSON file"),
         };
 
+        let status: Option<JobStatus> = articlesdump
+            .remove("status")
+            .and_then(|it| from_value(it).ok());
         let file_list: HashMap<String, MirrorDumpEntry> = match articlesdump
             .remove("files")
             .and_then(|it| from_value(it).ok())
         {
             Some(value) => value,
+            // unfinished jobs don't list (all) files
+            None if status.is_some_and(|it| it != JobStatus::Done) => HashMap::new(),
             _ => panic!("unsupported '{}' format", DUMP_STATUS_FILE),
         };
-        let status = articlesdump.remove("status").and_then(|it| match it {
-            Value::String(it) => Some(it),
-            _ => None,
-        });
         let updated = articlesdump.remove("updated").and_then(|it| match it {
             Value::String(it) => Some(it),
             _ => None,
@@ -367,21 +853,57 @@ impl DumpInfo {
             status,
             updated,
             files,
+            ..Default::default()
         }
     }
 
-    // TODO: Return errors
+    /// Reads latest revision id of every page in the dump, keyed by page id.
+    ///
+    /// Whole dump is streamed and parsed with revision text discarded. The
+    /// returned map holds an id pair for every page, so it grows with the
+    /// size of the wiki (tens of millions of pages for English Wikipedia).
+    pub fn latest_revisions(
+        &self,
+        rt: &Handle,
+        client: &Client,
+    ) -> std::io::Result<HashMap<usize, usize>> {
+        let mut result = HashMap::new();
+        for (name, stats) in &self.files {
+            log::info!("Reading revisions from {name}...");
+            let mut document = DocumentContext::new(&stats.path);
+            document.skip_text = true;
+            let pages = PageReader::new(self.stream(stats, rt, client)?, document);
+            for page in pages {
+                let page = page.map_err(|err| match err {
+                    ReadError::Xml(err) => std::io::Error::other(err),
+                    ReadError::Parse(err) => {
+                        std::io::Error::new(ErrorKind::InvalidData, err.to_string())
+                    }
+                })?;
+                let revision = page.revisions.last().and_then(|it| it.id.value());
+                if let (Some(id), Some(revision)) = (page.id.value(), revision) {
+                    result.insert(*id, *revision);
+                }
+            }
+        }
+        Ok(result)
+    }
+
     // TODO: Support split files
-    pub fn new(rt: &Handle, source: &SourceLocation) -> DumpInfo {
-        match source {
+    pub fn new(rt: &Handle, client: &Client, source: &SourceLocation) -> std::io::Result<DumpInfo> {
+        Ok(match source {
+            SourceLocation::Local { path } if tar_archive_kind(path).is_some() => DumpInfo {
+                status: None,
+                updated: None,
+                files: archive_files(path)?,
+                ..Default::default()
+            },
             SourceLocation::Local { path } => {
                 let mut files = BTreeMap::<FileName, FileDescriptor>::new();
 
-                let file_name = FileName::try_from(path).expect("non UTF-8 dump file name");
-                let mut test_open = File::open(path).expect("unable to open dump file");
-                let size = test_open
-                    .seek(std::io::SeekFrom::End(0))
-                    .expect("unable to read (seek) dump file") as usize;
+                let file_name = FileName::try_from(path)?;
+                let mut test_open = File::open(path)?;
+                let size = test_open.seek(std::io::SeekFrom::End(0))? as usize;
                 files.insert(
                     file_name.clone(),
                     FileDescriptor {
@@ -389,6 +911,7 @@ impl DumpInfo {
                         path: DumpLocation {
                             base: SourceLocation::Local { path: path.clone() },
                             file_name,
+                            member: None,
                         },
                         md5: None,
                         sha1: None,
@@ -396,12 +919,30 @@ impl DumpInfo {
                 );
 
                 DumpInfo {
-                    status: None,
-                    updated: None,
                     files,
+                    ..Default::default()
                 }
             }
-            SourceLocation::Remote { params } => rt.block_on(Self::new_remote(params)),
-        }
+            SourceLocation::Remote { params } => rt.block_on(Self::new_remote(client, params)),
+            SourceLocation::Api { .. } => {
+                // pages are only fetched once the document is read, so its
+                // size isn't known
+                let file_name = FileName("api.xml".to_string());
+                let descriptor = FileDescriptor {
+                    size: 0,
+                    path: DumpLocation {
+                        base: source.clone(),
+                        file_name: file_name.clone(),
+                        member: None,
+                    },
+                    md5: None,
+                    sha1: None,
+                };
+                DumpInfo {
+                    files: BTreeMap::from([(file_name, descriptor)]),
+                    ..Default::default()
+                }
+            }
+        })
     }
 }
@@ -1,9 +1,27 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::time::Duration;
 
 use bytes::{Buf as _, Bytes};
+use reqwest::{header::RANGE, Client, StatusCode};
+use thiserror::Error as ThisError;
 use tokio::runtime::Handle;
 
+/// Default number of times a failed remote chunk read is retried before
+/// giving up.
+pub const DEFAULT_CHUNK_RETRIES: u32 = 5;
+/// Delay before the first retry; doubled after every failed attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Longest delay between two retries.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Delay before retry number `attempt`, starting at 1.
+pub fn retry_delay(attempt: u32) -> Duration {
+    let factor = 2u32
+        .checked_pow(attempt.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    RETRY_BASE_DELAY.saturating_mul(factor).min(RETRY_MAX_DELAY)
+}
+
 #[repr(transparent)]
 pub struct DocumentStream(BufReader<CompressionAdapter<SourceAdapter>>);
 
@@ -13,6 +31,27 @@ impl DocumentStream {
     }
 }
 
+impl DocumentStream {
+    /// Number of bytes consumed from the source, before decompression.
+    pub fn source_position(&self) -> usize {
+        self.0.get_ref().get_ref().consumed()
+    }
+
+    /// Moves the source to a byte `offset`, before decompression.
+    ///
+    /// Compressed streams are restarted at `offset`, which has to be at the
+    /// start of a compressed stream (e.g. one listed in a multistream index).
+    /// Fails with [`ErrorKind::Unsupported`] for sources that can't seek.
+    pub fn seek_source(self, offset: u64) -> Result<Self> {
+        let inner = self.0.into_inner();
+        let compression = inner.compression();
+        let mut source = inner.into_inner();
+        source.seek(SeekFrom::Start(offset))?;
+        let inner = CompressionAdapter::new(compression, source)?;
+        Ok(DocumentStream::new(inner))
+    }
+}
+
 impl Read for DocumentStream {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         self.0.read(buf)
@@ -28,57 +67,355 @@ impl BufRead for DocumentStream {
     }
 }
 
+/// Failure to decompress input, as opposed to an error reading it.
+#[derive(Debug, ThisError)]
+pub enum DecompressionError {
+    #[error("compressed stream is truncated (incomplete download?)")]
+    Truncated,
+    #[error("compressed stream is corrupt: {0}")]
+    Corrupt(Error),
+}
+
+impl DecompressionError {
+    /// Returns decompression error wrapped by an IO error, if any.
+    pub fn find(err: &Error) -> Option<&DecompressionError> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+/// Compression format of a dump file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Bzip2,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects compression from leading bytes of a file.
+    ///
+    /// Returns `None` if bytes match neither a known compression format nor
+    /// start of an XML document.
+    pub fn sniff(bytes: &[u8]) -> Option<Compression> {
+        const SIGNATURES: &[(&[u8], Compression)] = &[
+            (b"BZh", Compression::Bzip2),
+            (b"\x1f\x8b", Compression::Gzip),
+            (b"\xfd7zXZ\x00", Compression::Xz),
+            (b"\x28\xb5\x2f\xfd", Compression::Zstd),
+            (b"\xef\xbb\xbf", Compression::None),
+            (b"<", Compression::None),
+        ];
+        SIGNATURES
+            .iter()
+            .find(|(signature, _)| bytes.starts_with(signature))
+            .map(|(_, compression)| *compression)
+    }
+
+    /// Guesses compression from file extension.
+    pub fn from_extension(ext: Option<&str>) -> Compression {
+        match ext {
+            Some("bz2") => Compression::Bzip2,
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Compression::None => "uncompressed",
+            Compression::Bzip2 => "bzip2",
+            Compression::Gzip => "gzip",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
 pub enum CompressionAdapter<R: Read> {
     Normal(R),
-    Decompressed(bzip2::read::BzDecoder<R>),
+    Bzip2(bzip2::read::BzDecoder<R>),
+    Gzip(flate2::read::MultiGzDecoder<R>),
+    Xz(xz2::read::XzDecoder<R>),
+    Zstd(zstd::stream::read::Decoder<'static, BufReader<R>>),
 }
 
 impl<R: Read> CompressionAdapter<R> {
+    /// Wraps `inner` with a decoder for `compression`.
+    pub fn new(compression: Compression, inner: R) -> Result<Self> {
+        Ok(match compression {
+            Compression::None => Self::new_passthrough(inner),
+            Compression::Bzip2 => Self::new_bzip2(inner),
+            Compression::Gzip => Self::new_gzip(inner),
+            Compression::Xz => Self::new_xz(inner),
+            Compression::Zstd => Self::new_zstd(inner)?,
+        })
+    }
+
     pub fn new_passthrough(inner: R) -> Self {
         CompressionAdapter::Normal(inner)
     }
 
     pub fn new_bzip2(inner: R) -> Self {
-        CompressionAdapter::Decompressed(bzip2::read::BzDecoder::<R>::new(inner))
+        CompressionAdapter::Bzip2(bzip2::read::BzDecoder::new(inner))
+    }
+
+    pub fn new_gzip(inner: R) -> Self {
+        CompressionAdapter::Gzip(flate2::read::MultiGzDecoder::new(inner))
+    }
+
+    pub fn new_xz(inner: R) -> Self {
+        CompressionAdapter::Xz(xz2::read::XzDecoder::new_multi_decoder(inner))
+    }
+
+    pub fn new_zstd(inner: R) -> Result<Self> {
+        Ok(CompressionAdapter::Zstd(zstd::stream::read::Decoder::new(
+            inner,
+        )?))
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self {
+            CompressionAdapter::Normal(_) => Compression::None,
+            CompressionAdapter::Bzip2(_) => Compression::Bzip2,
+            CompressionAdapter::Gzip(_) => Compression::Gzip,
+            CompressionAdapter::Xz(_) => Compression::Xz,
+            CompressionAdapter::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        match self {
+            CompressionAdapter::Normal(pass) => pass,
+            CompressionAdapter::Bzip2(pass) => pass.get_ref(),
+            CompressionAdapter::Gzip(pass) => pass.get_ref(),
+            CompressionAdapter::Xz(pass) => pass.get_ref(),
+            CompressionAdapter::Zstd(pass) => pass.get_ref().get_ref(),
+        }
+    }
+
+    /// Unwraps the compressed reader, dropping decoder state.
+    pub fn into_inner(self) -> R {
+        match self {
+            CompressionAdapter::Normal(pass) => pass,
+            CompressionAdapter::Bzip2(pass) => pass.into_inner(),
+            CompressionAdapter::Gzip(pass) => pass.into_inner(),
+            CompressionAdapter::Xz(pass) => pass.into_inner(),
+            CompressionAdapter::Zstd(pass) => pass.finish().into_inner(),
+        }
     }
 }
 
 impl<R: Read> Read for CompressionAdapter<R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        match self {
-            CompressionAdapter::Normal(pass) => pass.read(buf),
-            CompressionAdapter::Decompressed(pass) => pass.read(buf),
-        }
+        let result = match self {
+            CompressionAdapter::Normal(pass) => return pass.read(buf),
+            CompressionAdapter::Bzip2(pass) => pass.read(buf),
+            CompressionAdapter::Gzip(pass) => pass.read(buf),
+            CompressionAdapter::Xz(pass) => pass.read(buf),
+            CompressionAdapter::Zstd(pass) => pass.read(buf),
+        };
+        // decoders report invalid data as InvalidInput or InvalidData and
+        // missing end of stream as UnexpectedEof; errors of inner reader are
+        // passed as is
+        result.map_err(|err| match err.kind() {
+            ErrorKind::UnexpectedEof => {
+                Error::new(ErrorKind::UnexpectedEof, DecompressionError::Truncated)
+            }
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+                Error::new(ErrorKind::InvalidData, DecompressionError::Corrupt(err))
+            }
+            _ => err,
+        })
+    }
+}
+
+/// Reader that may support seeking.
+pub trait LocalRead: Read + Seek {}
+
+impl<R: Read + Seek> LocalRead for R {}
+
+/// Local reader that fails to seek, e.g. an archive member.
+struct Unseekable<R: Read>(R);
+
+impl<R: Read> Read for Unseekable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read> Seek for Unseekable<R> {
+    fn seek(&mut self, _: SeekFrom) -> Result<u64> {
+        Err(Error::new(ErrorKind::Unsupported, "source can't seek"))
     }
 }
 
 pub enum SourceAdapter {
-    Local(BufReader<File>),
+    Local {
+        reader: BufReader<Box<dyn LocalRead>>,
+        /// Number of bytes consumed from the file.
+        consumed: usize,
+    },
     Remote {
+        /// Client used to reconnect after failed reads.
+        client: Client,
+        url: String,
         resp: reqwest::Response,
+        /// Number of bytes received from the server so far.
+        received: usize,
+        /// Number of times a failed read is retried.
+        max_retries: u32,
         buffer: Bytes,
         pos: usize,
         runtime: Handle,
     },
 }
 
+/// Reads next chunk of a remote response.
+///
+/// Failed reads are retried with exponential backoff. Each retry reconnects
+/// and requests the rest of the file with a `Range` header. Fails once
+/// `max_retries` reconnects or reads after reconnecting have failed.
+fn next_chunk(
+    client: &Client,
+    url: &str,
+    resp: &mut reqwest::Response,
+    received: &mut usize,
+    max_retries: u32,
+    runtime: &Handle,
+) -> Result<Option<Bytes>> {
+    let mut attempt = 0;
+    let mut result = runtime.block_on(resp.chunk());
+    loop {
+        let err = match result {
+            Ok(chunk) => {
+                if let Some(chunk) = &chunk {
+                    *received += chunk.len();
+                }
+                return Ok(chunk);
+            }
+            Err(err) => err,
+        };
+
+        attempt += 1;
+        if attempt > max_retries {
+            return Err(Error::new(ErrorKind::ConnectionAborted, err));
+        }
+        let delay = retry_delay(attempt);
+        log::warn!(
+            "reading {} failed: {}; retrying in {}s ({}/{})",
+            url,
+            err,
+            delay.as_secs(),
+            attempt,
+            max_retries
+        );
+        std::thread::sleep(delay);
+
+        let request = client
+            .get(url)
+            .header(RANGE, format!("bytes={}-", received))
+            .send();
+        result = match runtime.block_on(request) {
+            Ok(it) if it.status() == StatusCode::PARTIAL_CONTENT => {
+                *resp = it;
+                runtime.block_on(resp.chunk())
+            }
+            Ok(it) => {
+                return Err(Error::new(
+                    ErrorKind::ConnectionAborted,
+                    format!(
+                        "server doesn't support resuming {} (status: {})",
+                        url,
+                        it.status()
+                    ),
+                ));
+            }
+            // old response is closed, so reading it again can't succeed
+            Err(err) => Err(err),
+        };
+    }
+}
+
+impl SourceAdapter {
+    /// Creates adapter for a local reader that can't seek, such as an archive
+    /// member.
+    pub fn new_local(reader: impl Read + 'static) -> Self {
+        Self::new_seekable(Unseekable(reader))
+    }
+
+    /// Creates adapter for a local file, which supports [`Seek`].
+    pub fn new_seekable(reader: impl Read + Seek + 'static) -> Self {
+        SourceAdapter::Local {
+            reader: BufReader::new(Box::new(reader)),
+            consumed: 0,
+        }
+    }
+
+    /// Creates adapter for a remote file `resp`onse, which starts at
+    /// `received` bytes into the file.
+    ///
+    /// Failed reads are retried up to `max_retries` times.
+    pub fn new_remote(
+        client: Client,
+        url: String,
+        resp: reqwest::Response,
+        received: usize,
+        max_retries: u32,
+        runtime: Handle,
+    ) -> Self {
+        SourceAdapter::Remote {
+            client,
+            url,
+            resp,
+            received,
+            max_retries,
+            buffer: Bytes::new(),
+            pos: 0,
+            runtime,
+        }
+    }
+
+    /// Number of bytes consumed from the source.
+    pub fn consumed(&self) -> usize {
+        match self {
+            SourceAdapter::Local { consumed, .. } => *consumed,
+            SourceAdapter::Remote {
+                received,
+                buffer,
+                pos,
+                ..
+            } => received - (buffer.len() - (*pos).min(buffer.len())),
+        }
+    }
+}
+
 impl Read for SourceAdapter {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         match self {
-            SourceAdapter::Local(pass) => pass.read(buf),
+            SourceAdapter::Local { reader, consumed } => {
+                let read = reader.read(buf)?;
+                *consumed += read;
+                Ok(read)
+            }
             SourceAdapter::Remote {
+                client,
+                url,
                 resp,
+                received,
+                max_retries,
                 buffer,
                 pos,
                 runtime,
             } => {
                 if buffer.is_empty() || *pos >= buffer.len() {
-                    let next_chunk = resp.chunk();
-                    let next_chunk = match runtime.block_on(next_chunk) {
-                        Ok(it) => it,
-                        Err(err) => return Err(Error::new(ErrorKind::ConnectionAborted, err)),
-                    };
-                    *buffer = match next_chunk {
+                    *buffer = match next_chunk(client, url, resp, received, *max_retries, runtime)?
+                    {
                         Some(it) => it,
                         None => {
                             return {
@@ -101,20 +438,20 @@ impl Read for SourceAdapter {
 impl BufRead for SourceAdapter {
     fn fill_buf(&mut self) -> Result<&[u8]> {
         match self {
-            SourceAdapter::Local(pass) => pass.fill_buf(),
+            SourceAdapter::Local { reader, .. } => reader.fill_buf(),
             SourceAdapter::Remote {
+                client,
+                url,
                 resp,
+                received,
+                max_retries,
                 buffer,
                 pos,
                 runtime,
             } => {
                 if buffer.is_empty() || *pos >= buffer.len() {
-                    let next_chunk = resp.chunk();
-                    let next_chunk = match runtime.block_on(next_chunk) {
-                        Ok(it) => it,
-                        Err(err) => return Err(Error::new(ErrorKind::ConnectionAborted, err)),
-                    };
-                    *buffer = match next_chunk {
+                    *buffer = match next_chunk(client, url, resp, received, *max_retries, runtime)?
+                    {
                         Some(it) => it,
                         None => return Ok(&[0]),
                     };
@@ -134,10 +471,29 @@ impl BufRead for SourceAdapter {
 
     fn consume(&mut self, amt: usize) {
         match self {
-            SourceAdapter::Local(pass) => pass.consume(amt),
+            SourceAdapter::Local { reader, consumed } => {
+                reader.consume(amt);
+                *consumed += amt;
+            }
             SourceAdapter::Remote { pos, .. } => {
                 *pos += amt;
             }
         }
     }
 }
+
+impl Seek for SourceAdapter {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        match self {
+            SourceAdapter::Local { reader, consumed } => {
+                let position = reader.seek(pos)?;
+                *consumed = position as usize;
+                Ok(position)
+            }
+            SourceAdapter::Remote { url, .. } => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("remote stream {} can't seek", url),
+            )),
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod api;
+pub mod cache;
 pub mod data;
 pub mod io;
+pub mod pages;
@@ -0,0 +1,90 @@
+use quick_xml::{events::Event as XMLEvent, Error as XMLError, Reader as XMLReader};
+use thiserror::Error;
+
+use super::io::DocumentStream;
+use crate::{
+    dump_data::{DocumentContext, WikiPage},
+    xml_util::{HandleEvent as _, ParseError},
+};
+
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error(transparent)]
+    Xml(#[from] XMLError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Feeds XML events of a dump file into a [`DocumentContext`].
+///
+/// Closed pages can be taken from the document directly, or by iterating
+/// the reader, which reads further events as needed.
+pub struct PageReader {
+    xml_reader: XMLReader<DocumentStream>,
+    buffer: Vec<u8>,
+    document: DocumentContext,
+    eof: bool,
+}
+
+impl PageReader {
+    pub fn new(stream: DocumentStream, document: DocumentContext) -> Self {
+        PageReader {
+            xml_reader: XMLReader::from_reader(stream),
+            buffer: Vec::new(),
+            document,
+            eof: false,
+        }
+    }
+
+    #[inline]
+    pub fn document(&self) -> &DocumentContext {
+        &self.document
+    }
+
+    #[inline]
+    pub fn document_mut(&mut self) -> &mut DocumentContext {
+        &mut self.document
+    }
+
+    #[inline]
+    pub fn into_document(self) -> DocumentContext {
+        self.document
+    }
+
+    /// Number of bytes consumed from the source, before decompression.
+    #[inline]
+    pub fn source_position(&self) -> usize {
+        self.xml_reader.get_ref().source_position()
+    }
+
+    /// Handles the next event of the file.
+    ///
+    /// Returns `true` once the end of file was handled.
+    pub fn read_event(&mut self) -> Result<bool, ReadError> {
+        self.buffer.clear();
+        let event = self.xml_reader.read_event_into(&mut self.buffer)?;
+        self.eof = matches!(event, XMLEvent::Eof);
+        self.document.handle_event(event)?;
+        Ok(self.eof)
+    }
+}
+
+impl Iterator for PageReader {
+    type Item = Result<WikiPage, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.document.pages.front().is_some_and(|it| it.closed) {
+                return self.document.pages.pop_front().map(Ok);
+            }
+            if self.eof {
+                return None;
+            }
+            if let Err(err) = self.read_event() {
+                // reading can't continue past a broken event
+                self.eof = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
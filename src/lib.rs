@@ -0,0 +1,31 @@
+#![allow(incomplete_features)]
+#![feature(adt_const_params)]
+
+use reqwest::Client;
+
+pub mod dump_data;
+pub mod format;
+pub mod input;
+pub mod output;
+pub mod state;
+pub mod xml_util;
+
+/// Builds the HTTP client shared by all requests, so connections to the
+/// mirror are reused.
+///
+/// HTTP/2 is negotiated when the server supports it, unless `http1_only` is
+/// set.
+pub fn client(http1_only: bool) -> Client {
+    static APP_USER_AGENT: &str = concat![
+        env!("CARGO_PKG_NAME"),
+        "/",
+        env!("CARGO_PKG_VERSION"),
+        " (github.com/Caellian/wiki-extractor)"
+    ];
+
+    let mut builder = reqwest::Client::builder().user_agent(APP_USER_AGENT);
+    if http1_only {
+        builder = builder.http1_only();
+    }
+    builder.build().expect("unable to create app web client")
+}
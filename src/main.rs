@@ -1,51 +1,53 @@
-#![allow(incomplete_features)]
-#![feature(adt_const_params)]
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use clap::Parser;
+use anyhow::Context as _;
+use clap::{parser::ValueSource, Arg, ArgMatches, Command, CommandFactory, FromArgMatches, Parser};
 use env_logger::Env;
-use quick_xml::Reader as XMLReader;
-use reqwest::Client;
-
-use crate::{
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use wiki_extractor::{
     dump_data::DocumentContext,
-    input::data::DumpInfo,
-    output::DataGenerator,
+    format::{format_seconds, percent_pad},
+    input::{
+        data::{DumpInfo, JobStatus, RemoteParams, SourceLocation},
+        pages::{PageReader, ReadError},
+    },
+    output::{
+        self,
+        manifest::Manifest,
+        mediawiki,
+        options::{TextFormat, TextOptions},
+        processing::{MapXMLEntities, ProcessingPass as _},
+        DataGenerator,
+    },
     state::{set_tracker_global, DownloadTracker},
-    xml_util::HandleEvent,
 };
 
-mod dump_data;
-mod format;
-mod input;
-mod output;
-mod state;
-mod xml_util;
-
-pub fn client() -> Client {
-    static APP_USER_AGENT: &str = concat![
-        env!("CARGO_PKG_NAME"),
-        "/",
-        env!("CARGO_PKG_VERSION"),
-        " (github.com/Caellian/wiki-extractor)"
-    ];
-
-    reqwest::Client::builder()
-        .user_agent(APP_USER_AGENT)
-        .build()
-        .expect("unable to create app web client")
-}
-
-#[derive(Parser)]
+#[derive(Parser, Serialize, Deserialize)]
 #[command(version, about)]
 #[command(name = env!("CARGO_PKG_NAME"))]
 #[command(about = env!("CARGO_PKG_DESCRIPTION"))]
 pub struct Args {
     /// Input mirror/file.
     #[clap(subcommand)]
-    pub input: input::data::SourceLocation,
+    pub input: Option<SourceLocation>,
     /// Path to output directory.
     #[arg(short = 'o', long = "output", default_value = "./dump")]
-    pub output: std::path::PathBuf,
+    pub output: PathBuf,
+    /// Path to a TOML or JSON file with options.
+    ///
+    /// Keys are long argument names, e.g. `include-headings = true` (or
+    /// `include_headings`) in TOML; they can also be nested in their group,
+    /// e.g. `text.include_headings`. Options specified on the command line
+    /// override ones from the file.
+    #[arg(long = "config")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
 
     /// Selection of generated files.
     #[clap(flatten)]
@@ -53,40 +55,551 @@ pub struct Args {
     /// Options for text dump generation.
     #[clap(flatten)]
     pub text: output::options::TextOptions,
+
+    /// Number of async runtime worker threads.
+    ///
+    /// Dump files are always streamed one at a time (see note in `main`), so
+    /// these threads only drive network IO and internal async tasks. Parsing
+    /// happens on the main thread. Defaults to the number of CPU cores.
+    #[arg(long = "io-threads")]
+    pub io_threads: Option<usize>,
+    /// Maximum number of threads used for blocking/CPU-bound tasks.
+    ///
+    /// Defaults to tokio's default of 512.
+    #[arg(long = "cpu-threads")]
+    pub cpu_threads: Option<usize>,
+    /// Only use HTTP/1.1 for requests to the mirror.
+    ///
+    /// For mirrors that misbehave with HTTP/2.
+    #[arg(long = "http1-only", default_value_t = false)]
+    pub http1_only: bool,
+    /// Maximum number of parsed pages waiting to be processed.
+    ///
+    /// Pages are processed as soon as they're closed, so this is only reached
+    /// if processing stalls; reading of the file is stopped with an error
+    /// instead of growing memory use without bound.
+    #[arg(
+        long = "max-pages-in-memory",
+        value_name = "N",
+        default_value_t = 1024,
+        value_parser = parse_page_limit
+    )]
+    pub max_pages_in_memory: usize,
+    /// Continue with remaining dump files if one of them can't be read.
+    ///
+    /// By default extraction stops at the first file that can't be opened,
+    /// decompressed or parsed. Output written so far is closed in both cases,
+    /// and exit status is 1 if any file failed.
+    #[arg(long = "keep-going", default_value_t = false)]
+    pub keep_going: bool,
+    /// Minimum interval between progress bar redraws in milliseconds.
+    #[arg(long = "progress-interval", default_value_t = 100)]
+    pub progress_interval: u64,
+    /// Report progress as JSON lines on standard error instead of a progress
+    /// bar.
+    ///
+    /// Each line contains current `file`, `percent`, downloaded `bytes`,
+    /// `eta_seconds` and processed `pages`; lines are written at most once per
+    /// `--progress-interval`.
+    #[arg(long = "progress-json", default_value_t = false)]
+    pub progress_json: bool,
+    /// Show progress percentage in terminal window title.
+    #[arg(long = "set-terminal-title", default_value_t = false)]
+    pub set_terminal_title: bool,
+    /// Print first N pages of the dump as JSON to standard output, then exit.
+    ///
+    /// Nothing is written to the output directory; useful for a quick look at
+    /// an unfamiliar dump.
+    #[arg(long = "head", value_name = "N")]
+    #[serde(skip)]
+    pub head: Option<usize>,
+    /// Print number of pages, redirects and pages per namespace, then exit.
+    ///
+    /// Revision content isn't parsed, so this is much faster than extraction.
+    #[arg(long = "count-only", default_value_t = false)]
+    #[serde(skip)]
+    pub count_only: bool,
+    /// Print available generators and files they produce, then exit.
+    #[arg(long = "list-generators", default_value_t = false)]
+    #[serde(skip)]
+    pub list_generators: bool,
+}
+
+/// Summary of a page printed by `--head`.
+#[derive(Serialize)]
+struct PagePreview<'a> {
+    title: Option<&'a str>,
+    ns: Option<isize>,
+    redirect: Option<&'a str>,
+    /// Number of characters in extracted text.
+    text_length: usize,
+    /// Start of extracted text.
+    text: String,
+}
+
+/// Number of leading text characters shown by `--head`.
+const PREVIEW_LENGTH: usize = 200;
+
+/// Parses and prints first `count` pages of the dump, without a
+/// [`DataGenerator`].
+fn print_head(
+    rt: &tokio::runtime::Runtime,
+    client: &reqwest::Client,
+    dump: &DumpInfo,
+    count: usize,
+    text_options: &TextOptions,
+) -> anyhow::Result<()> {
+    let parser = mediawiki::configuration(&text_options.extension_tags);
+    let mut printed = 0;
+    for stats in dump.files.values() {
+        let stream = dump.stream(stats, rt.handle(), client)?;
+        for page in PageReader::new(stream, DocumentContext::new(&stats.path)) {
+            let page = page.map_err(|err| anyhow::anyhow!("{}", err))?;
+            if printed >= count {
+                return Ok(());
+            }
+            let wikitext = page.revisions.last().and_then(|it| it.text.value());
+            let text = match wikitext {
+                Some(wikitext) if page.redirect.is_none() => {
+                    let wikitext = MapXMLEntities::process(wikitext);
+                    match parser.parse(&wikitext) {
+                        Ok(it) => mediawiki::nodes_to_text(&it.nodes, text_options),
+                        Err(err) => format!("<unparseable: {:?}>", err),
+                    }
+                }
+                _ => String::new(),
+            };
+            let preview = PagePreview {
+                title: page.title.value().map(String::as_str),
+                ns: page.ns.value().copied(),
+                redirect: page.redirect.as_deref(),
+                text_length: text.chars().count(),
+                text: text.chars().take(PREVIEW_LENGTH).collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&preview)?);
+            printed += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Counts pages in the dump by namespace and prints the totals.
+fn print_counts(
+    rt: &tokio::runtime::Runtime,
+    client: &reqwest::Client,
+    dump: &DumpInfo,
+) -> anyhow::Result<()> {
+    let mut total = 0;
+    let mut redirects = 0;
+    let mut namespaces = BTreeMap::<isize, usize>::new();
+    let mut site_info = None;
+    for (name, stats) in &dump.files {
+        log::info!("Counting pages in {name}...");
+        let mut document = DocumentContext::new(&stats.path);
+        document.skip_revisions = true;
+        let stream = dump.stream(stats, rt.handle(), client)?;
+        let mut pages = PageReader::new(stream, document);
+        for page in pages.by_ref() {
+            let page = page.map_err(|err| anyhow::anyhow!("{}", err))?;
+            total += 1;
+            if page.redirect.is_some() {
+                redirects += 1;
+            }
+            if let Some(ns) = page.ns.value() {
+                *namespaces.entry(*ns).or_default() += 1;
+            }
+        }
+        site_info.get_or_insert(pages.into_document().site_info);
+    }
+
+    println!("Pages: {}", total);
+    println!("Redirects: {}", redirects);
+    println!("Namespaces:");
+    let site_info = site_info.unwrap_or_default();
+    for (key, count) in namespaces {
+        let name = site_info
+            .namespaces()
+            .iter()
+            .find(|it| it.key() == key)
+            .map(|it| it.name())
+            .filter(|it| !it.is_empty())
+            .unwrap_or(if key == 0 { "(main)" } else { "" });
+        println!("{:>6} {:<24}{}", key, name, count);
+    }
+    Ok(())
+}
+
+fn list_generators() {
+    for generator in &output::options::GENERATORS {
+        println!(
+            "{:<16}{:<26}{}",
+            generator.name, generator.argument, generator.output
+        );
+    }
+}
+
+/// Checks for option combinations that contradict each other.
+///
+/// Checked once arguments are parsed, as conflicts with any enabled generator
+/// can't be declared with clap.
+fn validate_args(args: &Args) -> Result<(), String> {
+    let generator = &args.generator;
+    let text = &args.text;
+
+    if generator.validate_only && generator.any() {
+        return Err(format!(
+            "`--validate-only` doesn't write any output, but generators are enabled: {}",
+            generator.enabled().join(", ")
+        ));
+    }
+    if text.include_formatting {
+        let format = match generator.format {
+            TextFormat::Conll => Some("conll"),
+            TextFormat::Paragraphs => Some("paragraphs"),
+            TextFormat::Plain | TextFormat::MarkdownFiles => None,
+        };
+        if let Some(format) = format {
+            return Err(format!(
+                "`--markdown` can't be combined with `--format {}`",
+                format
+            ));
+        }
+    }
+    if let Some(SourceLocation::Api { params }) = &args.input {
+        if params.titles.is_empty() && params.category.is_none() {
+            return Err("`api` input requires a `--title` or `--category`".to_string());
+        }
+    }
+    if !generator.namespaces.is_empty() && !generator.exclude_namespaces.is_empty() {
+        return Err("`--namespaces` can't be combined with `--exclude-namespaces`".to_string());
+    }
+    if text.keep_raw_templates && text.template_rules_path.is_some() {
+        return Err(
+            "`--template-rules` has no effect when `--keep-raw-templates` is used".to_string(),
+        );
+    }
+    if !text.passthrough_templates.is_empty() && text.keep_raw_templates {
+        return Err(
+            "`--passthrough-template` has no effect when `--keep-raw-templates` is used"
+                .to_string(),
+        );
+    }
+    if !text.passthrough_templates.is_empty() && text.template_rules_path.is_some() {
+        return Err(
+            "`--passthrough-template` can't be combined with `--template-rules`".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Set when a status line is requested with `SIGUSR1`.
+static STATUS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Requests a status line whenever `SIGUSR1` is received.
+///
+/// Handler only sets a flag; status is logged by the reading loop, which owns
+/// the tracker.
+#[cfg(unix)]
+fn install_status_handler(rt: &tokio::runtime::Runtime) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let _context = rt.enter();
+    match signal(SignalKind::user_defined1()) {
+        Ok(mut signals) => {
+            rt.spawn(async move {
+                while signals.recv().await.is_some() {
+                    STATUS_REQUESTED.store(true, Ordering::Release);
+                }
+            });
+        }
+        Err(err) => log::warn!("can't install SIGUSR1 handler: {}", err),
+    }
+}
+
+/// Status requests aren't supported outside of Unix.
+#[cfg(not(unix))]
+fn install_status_handler(_: &tokio::runtime::Runtime) {}
+
+fn log_status(tracker: &DownloadTracker, processed_pages: usize) {
+    log::info!(
+        "Status: {} at{} ETA: {}; {} pages processed",
+        tracker
+            .current_file()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        percent_pad(tracker.download_percent(), 2),
+        format_seconds(tracker.eta()),
+        processed_pages
+    );
+}
+
+/// Whether writing output failed because there's no space left for it.
+fn is_storage_full(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::StorageFull
+            | std::io::ErrorKind::QuotaExceeded
+            | std::io::ErrorKind::WriteZero
+    )
+}
+
+/// Closes output written so far and exits after output storage filled up.
+fn abort_storage_full(gen: DataGenerator, err: std::io::Error) -> ! {
+    log::error!("Unable to write output, storage is full: {}", err);
+    match gen.finalize() {
+        Ok(()) => log::error!("Output written so far was closed; it's valid but incomplete"),
+        Err(err) => log::error!("Unable to close output written so far: {}", err),
+    }
+    std::process::exit(1);
+}
+
+fn load_config(path: &Path) -> anyhow::Result<Map<String, Value>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("can't read config file '{}'", path.display()))?;
+    let config = match path.extension().and_then(|it| it.to_str()) {
+        Some("toml") => toml::from_str(&content)?,
+        _ => serde_json::from_str(&content)?,
+    };
+    match config {
+        Value::Object(it) => Ok(it),
+        _ => anyhow::bail!("config file '{}' must contain a map", path.display()),
+    }
+}
+
+/// Appends command line arguments equivalent to options in `config` to
+/// `args`, skipping ones that were explicitly specified on the command line.
+///
+/// Flags can't be disabled from the command line, so ids of flags disabled
+/// by `config` are collected in `disabled` instead.
+///
+/// Options are named by their long argument name, with dashes or
+/// underscores, either at the top level or within their group (e.g.
+/// `text.include_headings`).
+fn config_args(
+    config: Map<String, Value>,
+    command: &Command,
+    matches: &ArgMatches,
+    args: &mut Vec<OsString>,
+    disabled: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    for (key, value) in config {
+        let value = match value {
+            // flattened option groups
+            Value::Object(inner) => {
+                config_args(inner, command, matches, args, disabled)?;
+                continue;
+            }
+            Value::Null => continue,
+            it => it,
+        };
+        let arg = config_option(command, &key)
+            .ok_or_else(|| anyhow::anyhow!("unknown config option '{key}'"))?;
+        let long = match arg.get_long() {
+            Some(it) if arg.get_id() != "config" => it,
+            _ => anyhow::bail!("config option '{key}' isn't allowed here"),
+        };
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let values = match value {
+            Value::Array(it) => it,
+            it => vec![it],
+        };
+        for value in values {
+            match value {
+                Value::Bool(enabled) if !arg.get_action().takes_values() => {
+                    if enabled {
+                        args.push(format!("--{long}").into());
+                    } else {
+                        disabled.push(arg.get_id().to_string());
+                    }
+                }
+                Value::String(it) => args.push(format!("--{long}={it}").into()),
+                other => args.push(format!("--{long}={other}").into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Argument a config `key` names, by either its long name or id.
+fn config_option<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    let key = key.replace('-', "_");
+    command.get_arguments().find(|arg| {
+        arg.get_id() == key.as_str() || arg.get_long().is_some_and(|it| it.replace('-', "_") == key)
+    })
+}
+
+fn parse_page_limit(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("at least one page has to fit in memory".to_string()),
+        Ok(it) => Ok(it),
+        Err(err) => Err(format!("{}", err)),
+    }
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut command = Args::command();
+    let matches = command.clone().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let config_path = match &args.config {
+        Some(it) => it.clone(),
+        None => return Ok(args),
+    };
+    let mut config = load_config(&config_path)?;
+    // input subcommand is replaced as a whole
+    let input = config.remove("input");
+
+    // config options are parsed as if they preceded command line ones, so
+    // they're validated the same way
+    let mut argv: Vec<OsString> = std::env::args_os().take(1).collect();
+    let mut disabled = Vec::new();
+    config_args(config, &command, &matches, &mut argv, &mut disabled)?;
+    argv.extend(std::env::args_os().skip(1));
+    for id in disabled {
+        command = command.mut_arg(id, |arg| arg.default_value("false"));
+    }
+    let invalid_config = || format!("invalid config file '{}'", config_path.display());
+    let matches = command
+        .try_get_matches_from(argv)
+        .with_context(invalid_config)?;
+    let mut args = Args::from_arg_matches(&matches).with_context(invalid_config)?;
+
+    if let (None, Some(input)) = (&args.input, input) {
+        args.input = Some(serde_json::from_value(input).with_context(invalid_config)?);
+    }
+    Ok(args)
 }
 
 fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
-        .format(crate::format::format)
-        .init();
+    let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    if wiki_extractor::format::enable_ansi() {
+        logger.format(wiki_extractor::format::format);
+    } else {
+        logger.format(wiki_extractor::format::format_plain);
+    }
+    logger.init();
 
+    let args = parse_args()?;
+    if args.list_generators {
+        list_generators();
+        return Ok(());
+    }
+    if let Err(message) = validate_args(&args) {
+        log::error!("{}", message);
+        std::process::exit(2);
+    }
+    let options = serde_json::to_value(&args)?;
     let Args {
         input,
         output,
+        config: _,
         generator: generator_options,
         text: text_options,
-    } = Args::parse();
+        io_threads,
+        cpu_threads,
+        http1_only,
+        max_pages_in_memory,
+        keep_going,
+        progress_interval,
+        progress_json,
+        set_terminal_title,
+        head,
+        count_only,
+        list_generators: _,
+    } = args;
+
+    let input = match input {
+        Some(it) => it,
+        None => {
+            log::error!("No input specified. Use `local`/`remote`/`api` subcommand or `input` config option.");
+            std::process::exit(2);
+        }
+    };
+    wiki_extractor::format::set_progress_interval(std::time::Duration::from_millis(
+        progress_interval,
+    ));
+    wiki_extractor::format::set_terminal_title(set_terminal_title);
+    wiki_extractor::format::set_progress_json(progress_json);
 
-    if !generator_options.any() {
+    let validate_only = generator_options.validate_only;
+    if !generator_options.any() && !validate_only && head.is_none() && !count_only {
         log::info!("Nothing to do. See `--help` for list of generators.");
         std::process::exit(0);
     }
 
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap();
+    let mut rt = tokio::runtime::Builder::new_multi_thread();
+    rt.enable_all();
+    if let Some(threads) = io_threads {
+        rt.worker_threads(threads.max(1));
+    }
+    if let Some(threads) = cpu_threads {
+        rt.max_blocking_threads(threads.max(1));
+    }
+    let rt = rt.build().unwrap();
+    install_status_handler(&rt);
 
-    let dump = DumpInfo::new(rt.handle(), &input);
+    let client = wiki_extractor::client(http1_only);
+    let dump = DumpInfo::new(rt.handle(), &client, &input).context("unable to read dump")?;
+
+    if let Some(count) = head {
+        return print_head(&rt, &client, &dump, count, &text_options);
+    }
+    if count_only {
+        return print_counts(&rt, &client, &dump);
+    }
 
-    if dump.status.map(|it| it != "done").unwrap_or_default() {
-        log::error!("mirror is currently generating the dump; specify older version or wait");
+    let unfinished = match dump.status {
+        None | Some(JobStatus::Done) => None,
+        Some(JobStatus::Waiting) => {
+            Some("dump hasn't been started by the mirror yet; try later or pick an older version")
+        }
+        Some(JobStatus::Running) => {
+            Some("dump is still being generated by the mirror; try later or pick an older version")
+        }
+        Some(JobStatus::Failed) => Some("dump failed on the mirror; pick another version"),
+        Some(JobStatus::Unknown) => {
+            Some("dump has an unknown status on the mirror; pick another version")
+        }
+    };
+    if let Some(message) = unfinished {
+        log::error!("{}", message);
         std::process::exit(1);
     }
 
-    let mut gen = DataGenerator::new(output, generator_options, text_options)?;
+    let previous_revisions = match &input {
+        SourceLocation::Remote {
+            params:
+                params @ RemoteParams {
+                    since_version: Some(version),
+                    ..
+                },
+        } => {
+            let previous = SourceLocation::Remote {
+                params: RemoteParams {
+                    version: version.clone(),
+                    since_version: None,
+                    ..params.clone()
+                },
+            };
+            log::info!("Reading page revisions of version {version}...");
+            let previous = DumpInfo::new(rt.handle(), &client, &previous)?;
+            Some(previous.latest_revisions(rt.handle(), &client)?)
+        }
+        _ => None,
+    };
+
+    let mut manifest = Manifest::new(input.clone(), options);
+    manifest.updated = dump.updated.clone();
+    let mut gen = DataGenerator::new(output, generator_options, text_options, manifest)?;
+    if let Some(revisions) = previous_revisions {
+        gen = gen.with_previous_revisions(revisions);
+    }
 
-    if let Some(updated) = dump.updated {
+    if let Some(updated) = &dump.updated {
         log::info!("Dump creation date: {updated}");
     }
 
@@ -106,39 +619,123 @@ fn main() -> anyhow::Result<()> {
 
     // Don't paralelize streaming because you'll get your IP address blocked and
     // it's very unpolite towards everyone else accessing the data.
-    for (name, stats) in dump.files {
+    let mut failed_files = 0;
+    for (name, stats) in &dump.files {
         log::info!("Handling {name}...");
 
-        let data_size = stats.size;
-
-        let stream = stats.path.stream(rt.handle())?;
-
-        let mut xml_reader = XMLReader::from_reader(stream);
-        let mut stream_buffer = Vec::new();
-        let mut document = DocumentContext::new(&stats.path);
+        let stream = match dump.stream(stats, rt.handle(), &client) {
+            Ok(it) => it,
+            Err(err) => {
+                log::error!("Unable to open {name}: {}", err);
+                failed_files += 1;
+                if !keep_going {
+                    log::error!(
+                        "Stopping extraction; use --keep-going to continue with other files"
+                    );
+                    break;
+                }
+                dt.advance_file();
+                continue;
+            }
+        };
+        let document = DocumentContext::new(&stats.path).with_page_limit(max_pages_in_memory);
+        let mut pages = PageReader::new(stream, document);
 
-        while xml_reader.buffer_position() < data_size {
-            dt.set_current_position(xml_reader.buffer_position());
+        // read until EOF; listed file size is compressed size, so it can't be
+        // compared with decompressed buffer position
+        let mut file_failed = false;
+        // pending pages aren't processed again after processing failed
+        let mut processing_failed = false;
+        loop {
+            dt.set_current_position(pages.source_position());
+            if STATUS_REQUESTED.swap(false, Ordering::AcqRel) {
+                log_status(&dt, gen.processed_pages());
+            }
+            wiki_extractor::format::print_progress_json(&dt, gen.processed_pages())?;
 
-            let event = xml_reader.read_event_into(&mut stream_buffer)?;
-            if let Err(err) = document.handle_event(event) {
-                log::error!("Error while reading {name}: {}", err.to_string());
-                break;
+            let is_eof = match pages.read_event() {
+                Ok(it) => it,
+                Err(ReadError::Xml(err)) => {
+                    log::error!("Unable to read {name}: {}", err);
+                    file_failed = true;
+                    break;
+                }
+                Err(ReadError::Parse(err)) => {
+                    log::error!("Error while reading {name}: {err}");
+                    file_failed = true;
+                    break;
+                }
             };
 
-            let process_result = rt.block_on(gen.process_document(&mut document));
+            let process_result = rt.block_on(gen.process_document(pages.document_mut()));
             
-            stream_buffer.clear();
             if let Err(err) = process_result {
+                if err.kind() == std::io::ErrorKind::InvalidInput {
+                    // invalid arguments; other files would fail as well
+                    log::error!("{}", err);
+                    std::process::exit(2);
+                }
+                if is_storage_full(&err) {
+                    abort_storage_full(gen, err);
+                }
                 log::error!("Error processing '{name}' document: {}", err);
+                file_failed = true;
+                processing_failed = true;
                 break;
             }
+            if is_eof {
+                break;
+            }
+        }
+
+        // closed pages left over if reading stopped early are still processed
+        if !processing_failed {
+            if let Err(err) = rt.block_on(gen.process_document(pages.document_mut())) {
+                if is_storage_full(&err) {
+                    abort_storage_full(gen, err);
+                }
+                log::error!("Error processing '{name}' document: {}", err);
+            }
         }
 
         dt.advance_file();
+
+        if file_failed {
+            failed_files += 1;
+            if !keep_going {
+                log::error!("Stopping extraction; use --keep-going to continue with other files");
+                break;
+            }
+        }
     }
     log::info!("Done!");
 
-    gen.finalize()?;
+    let processed_pages = gen.processed_pages();
+    let failed_pages = gen.failed_pages();
+    if let Err(err) = gen.finalize() {
+        if is_storage_full(&err) {
+            log::error!("Unable to write output, storage is full: {}", err);
+            std::process::exit(1);
+        }
+        return Err(err.into());
+    }
+
+    if validate_only {
+        log::info!(
+            "Parsed {} pages: {} failed; {} files couldn't be read",
+            processed_pages,
+            failed_pages,
+            failed_files
+        );
+        if failed_pages > 0 || failed_files > 0 {
+            std::process::exit(1);
+        }
+    } else if failed_files > 0 {
+        log::error!(
+            "{} files couldn't be read completely; output is incomplete",
+            failed_files
+        );
+        std::process::exit(1);
+    }
     Ok(())
 }
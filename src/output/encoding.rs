@@ -0,0 +1,104 @@
+//! Text output encoding.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use encoding_rs::{Encoder, EncoderResult, Encoding, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum OutputEncoding {
+    #[default]
+    #[value(name = "utf8", alias = "utf-8")]
+    #[serde(rename = "utf8", alias = "utf-8")]
+    Utf8,
+    /// Windows-1252 superset of ISO-8859-1, as used by most "Latin-1" tools.
+    #[value(name = "latin1", alias = "windows-1252")]
+    #[serde(rename = "latin1", alias = "windows-1252")]
+    Latin1,
+}
+
+impl OutputEncoding {
+    fn encoding(self) -> Option<&'static Encoding> {
+        match self {
+            OutputEncoding::Utf8 => None,
+            OutputEncoding::Latin1 => Some(WINDOWS_1252),
+        }
+    }
+}
+
+/// Writer that transcodes UTF-8 text into target [`OutputEncoding`].
+///
+/// Characters that can't be represented in the target encoding are replaced
+/// with a substitute character.
+pub struct TextWriter<W: Write> {
+    inner: W,
+    encoder: Option<Encoder>,
+    substitute: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn new(inner: W, encoding: OutputEncoding, substitute: char) -> Self {
+        let encoding = encoding.encoding();
+        let substitute = match encoding {
+            Some(encoding) => {
+                let substitute_utf8 = substitute.to_string();
+                let (bytes, _, had_errors) = encoding.encode(&substitute_utf8);
+                if had_errors {
+                    log::warn!(
+                        "substitute character '{}' can't be represented in {}; using '?'",
+                        substitute,
+                        encoding.name()
+                    );
+                    b"?".to_vec()
+                } else {
+                    bytes.into_owned()
+                }
+            }
+            None => Vec::new(),
+        };
+
+        TextWriter {
+            inner,
+            encoder: encoding.map(Encoding::new_encoder),
+            substitute,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn write_text(&mut self, text: &str) -> std::io::Result<()> {
+        let encoder = match &mut self.encoder {
+            Some(it) => it,
+            None => return self.inner.write_all(text.as_bytes()),
+        };
+
+        self.buffer.clear();
+        self.buffer.reserve(text.len());
+        let mut remaining = text;
+        loop {
+            let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(
+                remaining,
+                &mut self.buffer,
+                false,
+            );
+            remaining = &remaining[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => self.buffer.reserve(remaining.len().max(16)),
+                EncoderResult::Unmappable(_) => self.buffer.extend_from_slice(&self.substitute),
+            }
+        }
+
+        self.inner.write_all(&self.buffer)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Replaces wrapped writer, returning the previous one.
+    pub fn replace_inner(&mut self, inner: W) -> W {
+        std::mem::replace(&mut self.inner, inner)
+    }
+}
@@ -1,195 +1,497 @@
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
     fs::File,
-    io::{ErrorKind, Write as _},
-    path::{Path, PathBuf}, sync::Arc,
+    io::{BufWriter, ErrorKind, Write as _},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
 };
 
 use futures::future::BoxFuture;
 use itertools::Itertools;
-use parse_wiki_text_2::Configuration as MediawikiConfig;
+use parse_wiki_text_2::{Configuration as MediawikiConfig, ParseError};
+use serde::Serialize;
+use tokio::{sync::Semaphore, task::JoinHandle};
 
 use super::{
-    mediawiki::{self, WIKI_CONFIGURATION},
-    options::TextOptions,
+    manifest::{Manifest, SiteDescription},
+    mediawiki,
+    options::{DictionarySort, TextFormat, TextOptions},
+    redirects,
+    sink::PageSink,
+    templates::TemplateRules,
+    writer::{DirectoryTarget, JsonlFile, OutputTarget, OutputWriter, Record},
 };
 use super::{
     options::GeneratorOptions,
-    processing::{MapXMLEntities, ProcessingPass as _},
+    processing::{
+        sentence_count, sentence_words, ConllTokens, MapXMLEntities, ProcessingPass as _,
+    },
 };
-use crate::dump_data::{DocumentContext, WikiPage};
+use crate::dump_data::{ContributorInfo, DocumentContext, Revision, SiteInfo, WikiPage};
+use crate::input::data::SourceLocation;
+use crate::xml_util::{Closeable as _, CloseableState};
 
-fn sanitize_escapes(text: impl AsRef<str>, checked: char) -> String {
-    let mut result = String::with_capacity(text.as_ref().len() + 16);
-
-    let mut escaped = false;
-    for c in text.as_ref().chars() {
-        match c {
-            it if !escaped && it == checked => {
-                result.push('\\');
-                result.push(it);
-            }
-            '\\' => {
-                escaped = !escaped;
-            }
-            other => {
-                result.push(other);
-                escaped = false;
-            }
-        }
-    }
+/// Hashes page identity into a uniformly distributed value.
+///
+/// Uses SplitMix64 finalizer so that decisions are stable across runs and
+/// platforms for the same seed.
+fn page_hash(page: &WikiPage, seed: u64) -> u64 {
+    let key = match (page.id.value(), page.title.value()) {
+        (Some(id), _) => *id as u64,
+        (None, Some(title)) => title.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        }),
+        (None, None) => 0,
+    };
+    let mut z = key ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
 
-    result
+/// Maps page identity to a uniformly distributed value in `[0, 1)`.
+fn sample_point(page: &WikiPage, seed: u64) -> f64 {
+    (page_hash(page, seed) >> 11) as f64 / (1u64 << 53) as f64
 }
 
 pub struct Dictionary {
     file: PathBuf,
-    words: HashSet<String>,
+    /// Words and number of their occurrences.
+    words: Arc<Mutex<HashMap<String, u64>>>,
+    /// Order of written words; `None` writes words without counts.
+    counts: Option<DictionarySort>,
+    /// Limits number of texts counted in parallel.
+    permits: Arc<Semaphore>,
+    /// Counting jobs that might not have finished yet.
+    pending: Vec<JoinHandle<()>>,
 }
 
 impl Dictionary {
+    /// Creates a dictionary which adds to words already in `target` file.
     pub fn new(target: impl AsRef<Path>) -> Self {
+        Self::with_counts(target, None)
+    }
+
+    /// Creates a dictionary which writes word counts in given order, or only
+    /// words if `counts` is `None`.
+    pub fn with_counts(target: impl AsRef<Path>, counts: Option<DictionarySort>) -> Self {
         let file = target.as_ref().to_path_buf();
-        let words = if let Ok(base) = std::fs::read_to_string(&file) {
-            HashSet::from_iter(base.split('\n').map(str::to_string))
-        } else {
-            HashSet::with_capacity(1024)
-        };
+        let mut words = HashMap::with_capacity(1024);
+        if let Ok(base) = std::fs::read_to_string(&file) {
+            for line in base.split('\n').filter(|it| !it.is_empty()) {
+                let entry = match counts {
+                    Some(_) => line
+                        .rsplit_once('\t')
+                        .and_then(|(word, count)| Some((word, count.parse().ok()?))),
+                    None => None,
+                };
+                let (word, count) = entry.unwrap_or((line, 0));
+                *words.entry(word.to_string()).or_default() += count;
+            }
+        }
 
-        Dictionary { file, words }
+        Dictionary {
+            file,
+            words: Arc::new(Mutex::new(words)),
+            counts,
+            permits: Arc::new(Semaphore::new(1)),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Sets maximum number of texts [pushed](Self::push_arc) from a runtime
+    /// that are counted in parallel.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(jobs.max(1)));
+        self
+    }
+
+    fn count_words(words: &mut HashMap<String, u64>, text: &str) {
+        for (word, _) in sentence_words(text) {
+            if word.len() == 2 && word.ends_with('.') {
+                // name abbr.
+                continue;
+            }
+            match words.get_mut(word) {
+                Some(count) => *count += 1,
+                None => {
+                    words.insert(word.to_string(), 1);
+                }
+            }
+        }
     }
 
     /// Push text into dictionary.
     ///
     /// This method is a bit faulty because it can only rely on common grammar
-    /// rules to separate words out of the text.
-    ///
-    /// Examples of input that will be handled incorrectly:
-    /// - `I was there with Dr. Abigail to see the show.` is treated as two
-    ///   sentences and `Dr.` will be stripped of punctuation.
+    /// rules to separate words out of the text; see [`sentence_words`].
     pub async fn push(&mut self, text: impl AsRef<str>) {
-        // iterate over words with forward context
-        let words = text
-            .as_ref()
-            .split(' ')
-            .map(|word| {
-                let is_uppercase = word
-                    .chars()
-                    .next()
-                    .map(|it| it.is_uppercase())
-                    .unwrap_or_default();
-                (Some(word.trim()), is_uppercase)
-            })
-            .chain(std::iter::once((None, true)));
-        for ((word, _is_uppercase), (next_word, is_next_uppercase)) in words.tuple_windows() {
-            let mut word = unsafe {
-                // SAFETY: None is inserted only as next_word of last window.
-                word.unwrap_unchecked()
-            };
-            if word.ends_with('.') {
-                if word.len() == 2 {
-                    // name abbr.
-                    continue;
-                }
-                if let Some(next_word) = next_word {
-                    if next_word.starts_with('\n') || is_next_uppercase {
-                        // end of sentence
-                        word = word.strip_suffix('.').unwrap();
-                    } // else abbr.
-                } else {
-                    word = word.strip_suffix('.').unwrap();
-                }
-            }
-            self.words.insert(word.to_string());
-        }
+        let mut words = self.words.lock().unwrap_or_else(PoisonError::into_inner);
+        Self::count_words(&mut words, text.as_ref());
     }
 
+    /// Counts words of `text` on the blocking thread pool.
+    ///
+    /// Waits only if the [job limit](Self::with_jobs) is reached. Must be
+    /// called from a Tokio runtime.
     async fn push_arc(&mut self, text: Arc<String>) {
-        self.push(text.as_str()).await;
+        self.pending.retain(|it| !it.is_finished());
+        let permit = match self.permits.clone().acquire_owned().await {
+            Ok(it) => it,
+            Err(_) => unreachable!("dictionary semaphore is never closed"),
+        };
+        let words = self.words.clone();
+        self.pending.push(tokio::task::spawn_blocking(move || {
+            let mut counted = HashMap::new();
+            Self::count_words(&mut counted, &text);
+            let mut words = words.lock().unwrap_or_else(PoisonError::into_inner);
+            for (word, count) in counted {
+                *words.entry(word).or_default() += count;
+            }
+            drop(permit);
+        }));
     }
 
-    pub fn write(self) -> std::io::Result<()> {
-        let mut dictionary_file = File::create(self.file)?;
-        for item in self.words {
-            dictionary_file.write_all(item.as_bytes())?;
-            dictionary_file.write_all(b"\n")?;
+    /// Waits for pushed texts and writes the dictionary into target file.
+    pub fn write(mut self) -> std::io::Result<()> {
+        for job in std::mem::take(&mut self.pending) {
+            futures::executor::block_on(job).map_err(std::io::Error::other)?;
         }
+        let mut words = self.words.lock().unwrap_or_else(PoisonError::into_inner);
+        let words = std::mem::take(&mut *words);
+
+        let mut dictionary_file = BufWriter::new(File::create(self.file)?);
+        let order = match self.counts {
+            Some(it) => it,
+            None => {
+                for word in words.into_keys() {
+                    dictionary_file.write_all(word.as_bytes())?;
+                    dictionary_file.write_all(b"\n")?;
+                }
+                return dictionary_file.flush();
+            }
+        };
+
+        let mut words = words.into_iter().collect_vec();
+        match order {
+            DictionarySort::Frequency => {
+                words.sort_unstable_by(|(a, a_count), (b, b_count)| {
+                    b_count.cmp(a_count).then_with(|| a.cmp(b))
+                });
+            }
+            DictionarySort::Lexical => words.sort_unstable(),
+        }
+        for (word, count) in words {
+            writeln!(dictionary_file, "{}\t{}", word, count)?;
+        }
+        dictionary_file.flush()?;
 
         Ok(())
     }
 }
 
+/// Edit summary of a single revision.
+#[derive(Serialize)]
+struct CommentRecord<'a> {
+    page_id: Option<usize>,
+    rev_id: Option<usize>,
+    contributor: ContributorInfo<'a>,
+    comment: Cow<'a, str>,
+}
+
+/// Disambiguation page and pages it links to.
+#[derive(Serialize)]
+struct DisambiguationRecord<'a> {
+    title: &'a str,
+    targets: Vec<&'a str>,
+}
+
+/// Section tree of a single page.
+#[derive(Serialize)]
+struct SectionsRecord<'a> {
+    page_id: Option<usize>,
+    title: &'a str,
+    text: String,
+    sections: Vec<mediawiki::Section>,
+}
+
+/// Filter which excludes a page from output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exclusion {
+    /// Page comes before the `--resume-from-title` page.
+    Resume,
+    /// Page wasn't selected by `--sample`.
+    Sample,
+    /// Page isn't in one of selected namespaces.
+    Namespace,
+    /// Page isn't listed in `--title-list`.
+    TitleList,
+    /// Latest revision of the page is the same as in `--since-version` dump.
+    Unchanged,
+    /// Page with the same title was already processed.
+    DuplicateTitle,
+}
+
+/// Extracted text of a single article.
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    id: Option<usize>,
+    title: &'a str,
+    text: &'a str,
+    /// Target of a redirect page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirect_to: Option<&'a str>,
+}
+
+/// Single paragraph of an article.
+#[derive(Serialize)]
+struct ParagraphRecord<'a> {
+    title: &'a str,
+    id: Option<usize>,
+    paragraph_index: usize,
+    text: &'a str,
+}
+
+/// Categories of a single page.
+#[derive(Serialize)]
+struct CategoriesRecord<'a> {
+    page_id: Option<usize>,
+    title: &'a str,
+    /// Key set by `{{DEFAULTSORT:...}}`, if any.
+    sort_key: Option<String>,
+    categories: Vec<String>,
+}
+
+/// Internal links of a single page.
+#[derive(Serialize)]
+struct LinksRecord<'a> {
+    from: &'a str,
+    to: Vec<String>,
+}
+
+/// Normalized title of an article (main namespace page) a link points to.
+///
+/// Returns `None` for links to other namespaces and same-page section links.
+fn article_link_target(site_info: &SiteInfo, target: &str) -> Option<String> {
+    let target = target.trim().trim_start_matches(':');
+    let target = match target.split_once('#') {
+        Some((page, _)) => page,
+        None => target,
+    };
+    match site_info.split_title(target) {
+        ("", base) if !base.is_empty() => Some(mediawiki::normalize_title(base)),
+        _ => None,
+    }
+}
+
+/// Page that couldn't be processed.
+#[derive(Serialize)]
+struct ErrorRecord<'a> {
+    id: Option<usize>,
+    title: Option<&'a str>,
+    /// `unsupported` for unhandled content model/format, `invalid-data` for
+    /// pages that couldn't be parsed.
+    kind: &'a str,
+    message: String,
+}
+
+/// Serializes a JSONL record, including the trailing newline.
+fn jsonl(record: &impl Serialize) -> std::io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Single revision of a page in history mode.
+#[derive(Serialize)]
+struct RevisionRecord<'a> {
+    page_id: Option<usize>,
+    title: &'a str,
+    rev_id: Option<usize>,
+    parent_id: Option<usize>,
+    timestamp: Option<&'a str>,
+    contributor: ContributorInfo<'a>,
+    text_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+}
+
 pub struct DataGenerator {
-    metadata: Option<File>,
-    text_dump: Option<File>,
-    redirects: Option<File>,
+    output_path: PathBuf,
+    manifest: Manifest,
+    /// Writer thread which owns all streamed output files.
+    writer: OutputWriter,
     dictionary: Option<Dictionary>,
+    sinks: Vec<Box<dyn PageSink>>,
     mediawiki_parser: MediawikiConfig,
+    options: GeneratorOptions,
     text_options: TextOptions,
-    first_write: bool,
+    namespace_filter: Option<HashSet<isize>>,
+    /// Site information of the current document, used to split titles.
+    site_info: SiteInfo,
+    title_filter: Option<HashSet<String>>,
+    /// Normalized title of the page processing starts from; cleared once
+    /// the page is reached.
+    resume_from: Option<String>,
+    seen_titles: Option<HashSet<String>>,
+    duplicate_titles: usize,
+    /// Language pages are required to be in by `--detect-language`.
+    language: Option<whatlang::Lang>,
+    off_language_pages: usize,
+    /// Pages skipped by `--min-sentences`.
+    short_pages: usize,
+    /// Latest revision ids of pages in an older dump; pages that weren't
+    /// revised since are skipped.
+    previous_revisions: Option<HashMap<usize, usize>>,
+    unchanged_pages: usize,
+    processed_pages: usize,
+    failed_pages: usize,
+    timed_out_pages: usize,
     closed: bool,
 }
 
 impl DataGenerator {
+    /// Creates a generator writing all outputs to files in `output_path`.
     pub fn new(
         output_path: impl AsRef<Path>,
         generator_options: GeneratorOptions,
         text_options: TextOptions,
+        manifest: Manifest,
+    ) -> std::io::Result<Self> {
+        let target = DirectoryTarget(output_path.as_ref().to_path_buf());
+        Self::with_target(
+            output_path,
+            target,
+            generator_options,
+            text_options,
+            manifest,
+        )
+    }
+
+    /// Creates a generator writing streamed outputs (text and JSONL) to
+    /// streams opened by `target`.
+    ///
+    /// Other outputs, such as JSON files and the manifest, are still written
+    /// to `output_path`.
+    pub fn with_target(
+        output_path: impl AsRef<Path>,
+        target: impl OutputTarget,
+        mut generator_options: GeneratorOptions,
+        mut text_options: TextOptions,
+        mut manifest: Manifest,
     ) -> std::io::Result<Self> {
         let output_path = output_path.as_ref();
-        if output_path.is_file() {
-            log::error!("output path points to a file and not a directory");
-        }
-        if !output_path.exists() {
-            std::fs::create_dir_all(output_path)?;
+        if generator_options.validate_only {
+            // pages are still parsed, but nothing is written
+            generator_options.disable_outputs();
+        } else {
+            if output_path.is_file() {
+                log::error!("output path points to a file and not a directory");
+            }
+            if !output_path.exists() {
+                std::fs::create_dir_all(output_path)?;
+            }
         }
 
         // TODO: Allow disabling generation of individual files
-        let metadata = if generator_options.metadata {
-            let metadata = output_path.join("wiki_page_info.json");
-            let mut metadata = File::create(metadata)?;
-            metadata.write_all(b"[\n")?;
-            Some(metadata)
-        } else {
-            None
+        let title_filter = match &generator_options.title_list {
+            Some(path) => {
+                let titles: HashSet<String> = std::fs::read_to_string(path)?
+                    .lines()
+                    .map(mediawiki::normalize_title)
+                    .filter(|it| !it.is_empty())
+                    .collect();
+                log::info!("Extracting {} listed titles", titles.len());
+                Some(titles)
+            }
+            None => None,
         };
 
-        let text_dump = if generator_options.text {
-            let text_dump = output_path.join("wiki_sentences.txt");
-            let text_dump = File::create(text_dump)?;
-            Some(text_dump)
-        } else {
-            None
-        };
+        if generator_options.format == TextFormat::MarkdownFiles {
+            text_options.include_formatting = true;
+        }
 
-        let redirects = if generator_options.redirects {
-            let redirects = output_path.join("redirects.json");
-            let mut redirects = File::create(redirects)?;
-            redirects.write_all(b"{\n")?;
-            Some(redirects)
-        } else {
-            None
-        };
+        if !text_options.passthrough_templates.is_empty() {
+            text_options.template_rules =
+                TemplateRules::first_param_only(&text_options.passthrough_templates);
+        }
+        if let Some(path) = &text_options.template_rules_path {
+            text_options.template_rules.extend_from_file(path)?;
+        }
+
+        let writer = OutputWriter::spawn_with_target(
+            output_path,
+            target,
+            &generator_options,
+            &text_options,
+        )?;
 
         let dictionary = if generator_options.dictionary {
             let dictionary = output_path.join("dictionary.txt");
-            Some(Dictionary::new(dictionary))
+            let counts = generator_options
+                .dictionary_counts
+                .then_some(generator_options.dictionary_sort);
+            Some(
+                Dictionary::with_counts(dictionary, counts)
+                    .with_jobs(generator_options.dictionary_jobs),
+            )
         } else {
             None
         };
 
+        let language = generator_options
+            .detect_language
+            .as_deref()
+            .and_then(whatlang::Lang::from_code);
+
+        manifest.generators = generator_options.enabled();
+
         Ok(DataGenerator {
-            metadata,
-            text_dump,
-            redirects,
+            output_path: output_path.to_path_buf(),
+            manifest,
+            writer,
             dictionary,
-            mediawiki_parser: MediawikiConfig::new(&WIKI_CONFIGURATION),
+            sinks: Vec::new(),
+            mediawiki_parser: mediawiki::configuration(&text_options.extension_tags),
+            title_filter,
+            resume_from: generator_options
+                .resume_from_title
+                .as_deref()
+                .map(mediawiki::normalize_title),
+            seen_titles: generator_options.dedup_titles.then(HashSet::new),
+            options: generator_options,
             text_options,
-            first_write: true,
+            namespace_filter: None,
+            site_info: SiteInfo::default(),
+            duplicate_titles: 0,
+            language,
+            off_language_pages: 0,
+            short_pages: 0,
+            previous_revisions: None,
+            unchanged_pages: 0,
+            processed_pages: 0,
+            failed_pages: 0,
+            timed_out_pages: 0,
             closed: false,
         })
     }
 
+    /// Skips pages whose latest revision id matches the one in `revisions`,
+    /// keyed by page id.
+    pub fn with_previous_revisions(mut self, revisions: HashMap<usize, usize>) -> Self {
+        self.previous_revisions = Some(revisions);
+        self
+    }
+
+    /// Registers a custom [`PageSink`] invoked for every processed page.
+    pub fn with_page_sink(mut self, sink: impl PageSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
     pub async fn process_document(
         &mut self,
         document: &mut DocumentContext,
@@ -199,45 +501,370 @@ impl DataGenerator {
         }
 
         let has_pages =
-            |doc: &DocumentContext| doc.pages.first().map(|it| it.closed).unwrap_or_default();
+            |doc: &DocumentContext| doc.pages.front().map(|it| it.closed).unwrap_or_default();
+
+        if document.site_info.close_state() == CloseableState::Closed || has_pages(document) {
+            self.resolve_namespaces(&document.site_info)?;
+            if document.namespace_filter.is_none() {
+                document.namespace_filter.clone_from(&self.namespace_filter);
+            }
+            if self.site_info.close_state() != CloseableState::Closed {
+                self.site_info = document.site_info.clone();
+                if self.site_info.close_state() == CloseableState::Closed {
+                    self.manifest.site = Some(SiteDescription::from(&self.site_info));
+                }
+            }
+        }
 
         while has_pages(document) {
-            let page = document.pages.remove(0);
-            match self.process_page(page).await {
+            let page = match document.pages.pop_front() {
+                Some(it) => it,
+                None => break,
+            };
+            let processed_before = self.processed_pages;
+            let sequence = self.writer.next_sequence();
+            let mut records = Vec::new();
+            let identity = self
+                .options
+                .errors_file
+                .is_some()
+                .then(|| (page.id.value().copied(), page.title.value().cloned()));
+            let error = match self.process_page(page, &mut records).await {
                 Ok(jobs) => {
                     futures::future::join_all(jobs).await;
+                    None
+                }
+                Err(err) => Some(err),
+            };
+            let flush_interval = self.options.flush_interval;
+            if error.is_none()
+                && flush_interval != 0
+                && self.processed_pages != processed_before
+                && self.processed_pages.is_multiple_of(flush_interval)
+            {
+                records.push(Record::Flush);
+            }
+            if let (Some(err), Some((id, title))) = (&error, &identity) {
+                let kind = match err.kind() {
+                    ErrorKind::Unsupported => Some("unsupported"),
+                    ErrorKind::InvalidData => Some("invalid-data"),
+                    ErrorKind::TimedOut => Some("timed-out"),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    let record = ErrorRecord {
+                        id: *id,
+                        title: title.as_deref(),
+                        kind,
+                        message: err.to_string(),
+                    };
+                    records.push(Record::Jsonl(JsonlFile::Errors, jsonl(&record)?));
                 }
-                Err(err) => {
-                    if err.kind() == ErrorKind::Unsupported {
-                        continue;
-                    } else {
-                        return Err(err);
+            }
+            // records written before a failure are kept
+            self.writer.send(sequence, records)?;
+            if let Some(err) = error {
+                match err.kind() {
+                    ErrorKind::Unsupported => log::debug!("{}", err),
+                    ErrorKind::InvalidData => {
+                        log::warn!("{}", err);
+                        self.failed_pages += 1;
                     }
+                    ErrorKind::TimedOut => {
+                        log::warn!("{}", err);
+                        self.timed_out_pages += 1;
+                    }
+                    _ => return Err(err),
+                }
+            }
+        }
+
+        // Only the latest revision is used for content, so older ones are
+        // written out and dropped as soon as newer ones start to avoid
+        // buffering entire history of a page.
+        if let Some(page) = document.pages.back_mut() {
+            if !page.closed && page.revisions.len() > 1 {
+                let older = page
+                    .revisions
+                    .drain(..page.revisions.len() - 1)
+                    .collect_vec();
+                if self.exclusion(page).is_none() {
+                    let sequence = self.writer.next_sequence();
+                    let mut records = Vec::new();
+                    self.write_revisions(page, &older, &mut records)?;
+                    self.writer.send(sequence, records)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of pages that reached processing.
+    pub fn processed_pages(&self) -> usize {
+        self.processed_pages
+    }
+
+    /// Number of pages whose wikitext couldn't be parsed.
+    pub fn failed_pages(&self) -> usize {
+        self.failed_pages
+    }
+
+    /// Resolves requested namespace names into keys declared by the dump.
+    ///
+    /// Excluded namespaces are turned into a filter of all other declared
+    /// namespaces.
+    ///
+    /// Returns [`ErrorKind::InvalidInput`] listing valid namespaces if any of
+    /// requested ones doesn't exist.
+    fn resolve_namespaces(&mut self, site_info: &SiteInfo) -> std::io::Result<()> {
+        let exclude = !self.options.exclude_namespaces.is_empty();
+        let namespaces = match exclude {
+            true => &self.options.exclude_namespaces,
+            false => &self.options.namespaces,
+        };
+        if namespaces.is_empty() || self.namespace_filter.is_some() {
+            return Ok(());
+        }
+
+        let mut filter = HashSet::with_capacity(namespaces.len());
+        let mut unknown = Vec::new();
+        for name in namespaces {
+            match site_info.namespace_key(name) {
+                Some(key) => {
+                    filter.insert(key);
                 }
+                None => unknown.push(name.as_str()),
+            }
+        }
+
+        if !unknown.is_empty() {
+            let valid = site_info
+                .namespaces()
+                .iter()
+                .map(|it| match it.name() {
+                    "" => format!("{} (main)", it.key()),
+                    name => format!("{} ({})", it.key(), name),
+                })
+                .join(", ");
+            let message = format!(
+                "unknown namespaces: {}; valid namespaces are: {}",
+                unknown.join(", "),
+                valid
+            );
+            return Err(std::io::Error::new(ErrorKind::InvalidInput, message));
+        }
+
+        if exclude {
+            filter = site_info
+                .namespaces()
+                .iter()
+                .map(|it| it.key())
+                .filter(|it| !filter.contains(it))
+                .collect();
+        }
+        self.namespace_filter = Some(filter);
+        Ok(())
+    }
+
+    /// Returns filter which excludes page from output, if any.
+    ///
+    /// Revisions of open pages are checked the same way as closed pages, so
+    /// all outputs agree on which pages they include.
+    fn exclusion(&self, page: &WikiPage) -> Option<Exclusion> {
+        if let (Some(target), Some(title)) = (&self.resume_from, page.title.value()) {
+            if mediawiki::normalize_title(title) != *target {
+                return Some(Exclusion::Resume);
+            }
+        }
+        if !self.is_sampled(page) {
+            return Some(Exclusion::Sample);
+        }
+        if let (Some(filter), Some(ns)) = (&self.namespace_filter, page.ns.value()) {
+            if !filter.contains(ns) {
+                return Some(Exclusion::Namespace);
+            }
+        }
+        if let (Some(filter), Some(title)) = (&self.title_filter, page.title.value()) {
+            if !filter.contains(&mediawiki::normalize_title(title)) {
+                return Some(Exclusion::TitleList);
+            }
+        }
+        if let (Some(previous), Some(id)) = (&self.previous_revisions, page.id.value()) {
+            // latest revision of an open page isn't known yet
+            let latest = page.revisions.last().and_then(|it| it.id.value());
+            if page.closed && latest.is_some() && previous.get(id) == latest {
+                return Some(Exclusion::Unchanged);
+            }
+        }
+        if let (Some(seen_titles), Some(title)) = (&self.seen_titles, page.title.value()) {
+            if seen_titles.contains(&mediawiki::normalize_title(title)) {
+                return Some(Exclusion::DuplicateTitle);
+            }
+        }
+        None
+    }
+
+    /// Checks whether `text` is confidently detected to be in a language other
+    /// than the one required by `--detect-language`.
+    fn is_off_language(&self, text: &str) -> bool {
+        let language = match self.language {
+            Some(it) => it,
+            None => return false,
+        };
+        whatlang::detect(text).is_some_and(|info| {
+            info.lang() != language && info.confidence() >= self.options.language_confidence
+        })
+    }
+
+    /// Checks whether page is selected by `--sample`.
+    fn is_sampled(&self, page: &WikiPage) -> bool {
+        let sample = self.options.sample;
+        sample >= 1.0 || sample_point(page, self.options.seed) < sample
+    }
+
+    /// Produces per-revision outputs (edit summaries and history).
+    fn write_revisions(
+        &self,
+        page: &WikiPage,
+        revisions: &[Revision],
+        records: &mut Vec<Record>,
+    ) -> std::io::Result<()> {
+        if self.options.comments {
+            for rev in revisions {
+                let comment = match rev.comment.value() {
+                    Some(it) if !it.is_empty() => it,
+                    // suppressed comments (deleted="deleted") are empty
+                    _ => continue,
+                };
+                // edit summaries are written out as plain text; a malformed
+                // reference only affects this comment
+                let comment = quick_xml::escape::unescape(comment).unwrap_or_else(|err| {
+                    log::warn!(
+                        "unable to unescape comment of revision {:?}: {}",
+                        rev.id.value(),
+                        err
+                    );
+                    Cow::Borrowed(comment)
+                });
+                let record = CommentRecord {
+                    page_id: page.id.value().copied(),
+                    rev_id: rev.id.value().copied(),
+                    contributor: rev.contributor.info(),
+                    comment,
+                };
+                records.push(Record::Jsonl(JsonlFile::Comments, jsonl(&record)?));
+            }
+        }
+
+        if self.options.history {
+            for rev in revisions {
+                // same as text extracted from the latest revision
+                let text = rev.text.value().map(MapXMLEntities::process);
+                let record = RevisionRecord {
+                    page_id: page.id.value().copied(),
+                    title: page.title.value().map(String::as_str).unwrap_or_default(),
+                    rev_id: rev.id.value().copied(),
+                    parent_id: rev.parent_id.value().copied(),
+                    timestamp: rev.timestamp.value().map(String::as_str),
+                    contributor: rev.contributor.info(),
+                    text_length: text.as_ref().map(String::len),
+                    text: text.as_deref().filter(|_| self.options.history_text),
+                };
+                records.push(Record::Jsonl(JsonlFile::History, jsonl(&record)?));
             }
-            self.first_write = false;
         }
 
         Ok(())
     }
 
-    async fn process_page(&mut self, mut page: WikiPage) -> std::io::Result<Vec<BoxFuture<'_, ()>>> {
+    async fn process_page(
+        &mut self,
+        mut page: WikiPage,
+        records: &mut Vec<Record>,
+    ) -> std::io::Result<Vec<BoxFuture<'_, ()>>> {
+        if page.skip_content {
+            // excluded by namespace while parsing, so it's missing fields
+            return Ok(vec![]);
+        }
+        if page.title.value().is_none() || page.id.value().is_none() {
+            // empty or truncated page
+            log::warn!(
+                "skipping page without title or id (title: {:?}, id: {:?})",
+                page.title.value(),
+                page.id.value()
+            );
+            return Ok(vec![]);
+        }
+
+        let title = page.title.value().map(String::as_str).unwrap_or_default();
+        let (_, base_title) = self.site_info.split_title(title);
+        if base_title.is_empty() {
+            log::warn!(
+                "skipping page with empty title (title: {:?}, id: {:?})",
+                title,
+                page.id.value()
+            );
+            return Ok(vec![]);
+        }
+
+        let exclusion = self.exclusion(&page);
+        if self.resume_from.is_some() && exclusion != Some(Exclusion::Resume) {
+            log::info!("Resuming from page: {}", title);
+            self.resume_from = None;
+        }
+
+        match exclusion {
+            Some(Exclusion::Unchanged) => {
+                self.unchanged_pages += 1;
+                return Ok(vec![]);
+            }
+            Some(Exclusion::DuplicateTitle) => {
+                let title = page.title.value().map(String::as_str);
+                log::debug!("skipping duplicate page: {}", title.unwrap_or_default());
+                self.duplicate_titles += 1;
+                return Ok(vec![]);
+            }
+            Some(_) => return Ok(vec![]),
+            None => {}
+        }
+        if let (Some(seen_titles), Some(title)) = (&mut self.seen_titles, page.title.value()) {
+            seen_titles.insert(mediawiki::normalize_title(title));
+        }
+        self.processed_pages += 1;
+
+        self.write_revisions(&page, &page.revisions, records)?;
+
+        for sink in &mut self.sinks {
+            sink.process_page(&page)?;
+        }
+
         if let Some(redirect) = &page.redirect {
-            if let Some(redirect_file) = &mut self.redirects {
+            if self.options.redirects {
                 if let Some(title) = page.title.value() {
-                    if !self.first_write {
-                        let _ = redirect_file.write_all(b",\n");
-                    }
-                    let _ = redirect_file.write_all(b"  \"");
-                    let escaped = sanitize_escapes(title, '\"');
-                    let _ = redirect_file.write_all(escaped.as_bytes());
-                    let _ = redirect_file.write_all(b"\": \"");
-                    let escaped = sanitize_escapes(redirect, '\"');
-                    let _ = redirect_file.write_all(escaped.as_bytes());
-                    let _ = redirect_file.write_all(b"\"");
+                    let entry = format!(
+                        "  {}: {}",
+                        serde_json::to_string(title)?,
+                        serde_json::to_string(redirect)?
+                    );
+                    records.push(Record::Redirect(entry.into_bytes()));
                 }
             }
-            return Ok(vec![]);
+            // redirect bodies are processed like any other page, which also
+            // writes their page record
+            if !self.options.process_redirect_bodies {
+                if self.options.redirects_as_pages {
+                    let record = PageRecord {
+                        id: page.id.value().copied(),
+                        title: page.title.value().map(String::as_str).unwrap_or_default(),
+                        text: "",
+                        redirect_to: Some(redirect),
+                    };
+                    let shard = self.page_shard(&page);
+                    records.push(Record::Jsonl(JsonlFile::Pages(shard), jsonl(&record)?));
+                }
+                return Ok(vec![]);
+            }
         }
 
         let mut revisions = std::mem::take(&mut page.revisions);
@@ -246,9 +873,27 @@ impl DataGenerator {
             None => return Ok(vec![]),
         };
 
-        if rev.model.value().map(|it| it.as_str()) != Some("wikitext")
-            && rev.format.value().map(|it| it.as_str()) != Some("text/x-wiki")
-        {
+        let is_wikitext = rev.model.value().map(|it| it.as_str()) == Some("wikitext")
+            || rev.format.value().map(|it| it.as_str()) == Some("text/x-wiki");
+        let content_model = self.options.content_model.as_str();
+        if content_model != "wikitext" {
+            let model = match rev.model.value() {
+                Some(it) => it.as_str(),
+                None if is_wikitext => "wikitext",
+                None => "",
+            };
+            if !model.eq_ignore_ascii_case(content_model) {
+                return Ok(vec![]);
+            }
+            // content of other models isn't wikitext, so it's written as is
+            if let Some(text) = rev.text.take_value() {
+                let text = Arc::new(MapXMLEntities::process(text));
+                self.push_text(&page, &text, records)?;
+            }
+            return Ok(vec![]);
+        }
+
+        if !is_wikitext {
             // program is outdated/broken
             let message = format!(
                 "Unhandled page ({}: {}) model/format: {{ model: \"{}\"; format: \"{}\" }}\n{:#?}",
@@ -267,7 +912,8 @@ impl DataGenerator {
             None => return Ok(vec![]),
         };
 
-        let nodes = match self.mediawiki_parser.parse(&raw_text) {
+        let timeout = Duration::from_secs(self.options.page_timeout);
+        let nodes = match self.mediawiki_parser.parse_with_timeout(&raw_text, timeout) {
             Ok(it) => {
                 if !it.warnings.is_empty() {
                     let warnings = "- ".to_string()
@@ -286,50 +932,255 @@ impl DataGenerator {
                 }
                 it.nodes
             }
-            Err(err) => {
+            Err(ParseError::TimedOut { execution_time, .. }) => {
                 let message = format!(
-                    "can't parse page: ({}: {}): {:?}",
+                    "page ({}: {}) skipped, parsing was aborted after {:.1}s",
                     page.id.value().map(usize::to_string).unwrap_or_default(),
                     page.title.value().map(String::as_str).unwrap_or(""),
-                    err
+                    execution_time.as_secs_f32()
                 );
-                return Err(std::io::Error::new(ErrorKind::Unsupported, message));
+                return Err(std::io::Error::new(ErrorKind::TimedOut, message));
             }
         };
 
+        let text = Arc::new(mediawiki::nodes_to_text(&nodes, &self.text_options));
+        if self.is_off_language(&text) {
+            log::debug!(
+                "skipping page in other language: {}",
+                page.title.value().map(String::as_str).unwrap_or("")
+            );
+            self.off_language_pages += 1;
+            return Ok(vec![]);
+        }
+        if self.options.min_sentences > 0 && sentence_count(&text) < self.options.min_sentences {
+            log::debug!(
+                "skipping page with too few sentences: {}",
+                page.title.value().map(String::as_str).unwrap_or("")
+            );
+            self.short_pages += 1;
+            return Ok(vec![]);
+        }
+
+        if self.options.sections {
+            let root = mediawiki::nodes_to_sections(&raw_text, &nodes, &self.text_options);
+            let record = SectionsRecord {
+                page_id: page.id.value().copied(),
+                title: page.title.value().map(String::as_str).unwrap_or_default(),
+                text: root.text,
+                sections: root.children,
+            };
+            records.push(Record::Jsonl(JsonlFile::Sections, jsonl(&record)?));
+        }
+
+        if self.options.categories {
+            let record = CategoriesRecord {
+                page_id: page.id.value().copied(),
+                title: page.title.value().map(String::as_str).unwrap_or_default(),
+                sort_key: mediawiki::default_sort(&nodes),
+                categories: mediawiki::categories(&nodes),
+            };
+            if !record.categories.is_empty() {
+                records.push(Record::Jsonl(JsonlFile::Categories, jsonl(&record)?));
+            }
+        }
+
+        if self.options.links {
+            let include_skipped = self.options.include_see_also_links;
+            let targets = mediawiki::text_link_targets(&nodes, include_skipped)
+                .into_iter()
+                .filter_map(|it| article_link_target(&self.site_info, it))
+                .unique()
+                .collect_vec();
+            let record = LinksRecord {
+                from: page.title.value().map(String::as_str).unwrap_or_default(),
+                to: targets,
+            };
+            records.push(Record::Jsonl(JsonlFile::Links, jsonl(&record)?));
+        }
+
+        if mediawiki::is_disambiguation(&raw_text, &nodes) {
+            if self.options.disambiguation {
+                let record = DisambiguationRecord {
+                    title: page.title.value().map(String::as_str).unwrap_or_default(),
+                    targets: mediawiki::link_targets(&nodes),
+                };
+                records.push(Record::Jsonl(JsonlFile::Disambiguation, jsonl(&record)?));
+            }
+            if self.text_options.exclude_disambiguation {
+                return Ok(vec![]);
+            }
+        }
+
         let mut jobs: Vec<BoxFuture<'_, ()>> = Vec::with_capacity(2);
 
-        let text = Arc::new(mediawiki::nodes_to_text(&nodes, &self.text_options));
-        if let Some(dictionary) = &mut self.dictionary {
-            jobs.push(Box::pin(dictionary.push_arc(text.clone())));
+        self.push_text(&page, &text, records)?;
+
+        if self.options.format == TextFormat::Paragraphs {
+            let title = page.title.value().map(String::as_str).unwrap_or_default();
+            let paragraphs = mediawiki::nodes_to_paragraphs(&nodes, &self.text_options);
+            for (paragraph_index, text) in paragraphs.iter().enumerate() {
+                let record = ParagraphRecord {
+                    title,
+                    id: page.id.value().copied(),
+                    paragraph_index,
+                    text,
+                };
+                records.push(Record::Jsonl(JsonlFile::Paragraphs, jsonl(&record)?));
+            }
         }
 
-        if let Some(text_dump) = &mut self.text_dump {
-            text_dump.write_all(text.as_bytes())?;
+        if let Some(dictionary) = &mut self.dictionary {
+            jobs.push(Box::pin(dictionary.push_arc(text)));
         }
 
         Ok(jobs)
     }
 
+    /// Adds records for text, page and split outputs.
+    fn push_text(
+        &self,
+        page: &WikiPage,
+        text: &Arc<String>,
+        records: &mut Vec<Record>,
+    ) -> std::io::Result<()> {
+        if self.options.text {
+            records.push(Record::Text(text.clone()));
+        }
+
+        if self.options.format == TextFormat::Conll {
+            records.push(Record::Tokens(ConllTokens::process(text.as_str())));
+        }
+
+        if self.options.pages {
+            let record = PageRecord {
+                id: page.id.value().copied(),
+                title: page.title.value().map(String::as_str).unwrap_or_default(),
+                text,
+                redirect_to: page.redirect.as_deref(),
+            };
+            let shard = self.page_shard(page);
+            records.push(Record::Jsonl(JsonlFile::Pages(shard), jsonl(&record)?));
+        }
+
+        if self.options.split_output {
+            if let Some(title) = page.title.value().filter(|_| !text.trim().is_empty()) {
+                records.push(Record::Article {
+                    title: title.clone(),
+                    id: page.id.value().copied(),
+                    text: text.clone(),
+                });
+            }
+        }
+
+        if self.options.format == TextFormat::MarkdownFiles {
+            if let Some(title) = page.title.value().filter(|_| !text.trim().is_empty()) {
+                records.push(Record::MarkdownFile {
+                    title: title.clone(),
+                    id: page.id.value().copied(),
+                    document: self.markdown_document(page, text),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Index of JSONL page output shard `page` is written to.
+    fn page_shard(&self, page: &WikiPage) -> usize {
+        (page_hash(page, 0) % self.options.shards.max(1) as u64) as usize
+    }
+
+    /// Prepends YAML frontmatter describing the page to its Markdown `text`.
+    fn markdown_document(&self, page: &WikiPage, text: &str) -> String {
+        let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+        let mut result = String::with_capacity(text.len() + 256);
+        result.push_str("---\n");
+        if let Some(title) = page.title.value() {
+            let _ = writeln!(result, "title: {}", quote(title));
+        }
+        if let Some(id) = page.id.value() {
+            let _ = writeln!(result, "id: {}", id);
+        }
+        if let Some(ns) = page.ns.value() {
+            let _ = writeln!(result, "namespace: {}", ns);
+        }
+        let _ = writeln!(
+            result,
+            "source: {}",
+            quote(&self.manifest.source.to_string())
+        );
+        if let SourceLocation::Remote { params } = &self.manifest.source {
+            let _ = writeln!(result, "version: {}", quote(&params.version));
+        }
+        if let Some(updated) = &self.manifest.updated {
+            let _ = writeln!(result, "updated: {}", quote(updated));
+        }
+        result.push_str("---\n\n");
+        result.push_str(text.trim());
+        result.push('\n');
+        result
+    }
+
     pub fn finalize(mut self) -> std::io::Result<()> {
         if self.closed {
             panic!("called finalize on DataGenerator twice");
         }
 
-        if let Some(mut redirects) = self.redirects {
-            redirects.write_all(b"}\n")?;
-            redirects.flush()?;
+        if self.seen_titles.is_some() {
+            log::info!(
+                "skipped {} pages with duplicate titles",
+                self.duplicate_titles
+            );
+        }
+        if self.previous_revisions.is_some() {
+            log::info!("skipped {} unchanged pages", self.unchanged_pages);
+        }
+        if self.language.is_some() {
+            log::info!(
+                "skipped {} pages in other languages",
+                self.off_language_pages
+            );
+        }
+        if self.options.min_sentences > 0 {
+            log::info!("skipped {} pages with too few sentences", self.short_pages);
+        }
+        if self.timed_out_pages > 0 {
+            log::warn!(
+                "skipped {} pages which took too long to parse",
+                self.timed_out_pages
+            );
+        }
+        if let Some(target) = &self.resume_from {
+            log::warn!(
+                "page to resume from ({}) wasn't found; no pages were processed",
+                target
+            );
         }
 
-        if let Some(mut metadata) = self.metadata {
-            metadata.write_all(b"]\n")?;
-            metadata.flush()?;
+        self.writer.finish()?;
+
+        if self.options.follow_redirects {
+            redirects::write_resolved(
+                self.output_path.join("redirects.json"),
+                self.output_path.join("redirects_resolved.json"),
+            )?;
         }
 
         if let Some(dictionary) = self.dictionary {
             dictionary.write()?;
         }
 
+        for sink in &mut self.sinks {
+            sink.finalize()?;
+        }
+
+        if !self.options.validate_only {
+            self.manifest.pages_processed = self.processed_pages;
+            self.manifest.finished_at = Some(chrono::Utc::now());
+            self.manifest
+                .write(self.output_path.join("manifest.json"))?;
+        }
+
         self.closed = true;
 
         Ok(())
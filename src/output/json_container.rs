@@ -0,0 +1,101 @@
+//! Incrementally written JSON arrays and objects.
+
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// File containing a single JSON array or object which is written one entry
+/// at a time.
+///
+/// In crash-safe mode the closing bracket is written after every entry and
+/// overwritten by the next one, so the file on disk is always valid JSON even
+/// if the process is killed before [`finish`](Self::finish).
+pub struct JsonContainer {
+    file: File,
+    closing: &'static [u8],
+    crash_safe: bool,
+    /// Offset at which the next entry is written, if closing was written.
+    closing_at: Option<u64>,
+    empty: bool,
+}
+
+impl JsonContainer {
+    pub fn create(
+        path: impl AsRef<Path>,
+        opening: &'static [u8],
+        closing: &'static [u8],
+        crash_safe: bool,
+    ) -> std::io::Result<Self> {
+        let mut file = File::create(path)?;
+        file.write_all(opening)?;
+        let mut result = JsonContainer {
+            file,
+            closing,
+            crash_safe,
+            closing_at: None,
+            empty: true,
+        };
+        if crash_safe {
+            result.write_closing()?;
+        }
+        Ok(result)
+    }
+
+    /// Creates a JSON array (`[...]`) file.
+    pub fn array(path: impl AsRef<Path>, crash_safe: bool) -> std::io::Result<Self> {
+        Self::create(path, b"[\n", b"]\n", crash_safe)
+    }
+
+    /// Creates a JSON object (`{...}`) file.
+    pub fn object(path: impl AsRef<Path>, crash_safe: bool) -> std::io::Result<Self> {
+        Self::create(path, b"{\n", b"}\n", crash_safe)
+    }
+
+    fn write_closing(&mut self) -> std::io::Result<()> {
+        self.closing_at = Some(self.file.stream_position()?);
+        self.file.write_all(self.closing)
+    }
+
+    /// Writes a single serialized entry (array item or `"key": value` pair).
+    ///
+    /// If writing fails (e.g. because the disk is full), partially written
+    /// entry is truncated away so that [`finish`](Self::finish) still produces
+    /// valid JSON.
+    pub fn write_entry(&mut self, entry: &[u8]) -> std::io::Result<()> {
+        let start = match self.closing_at.take() {
+            Some(offset) => self.file.seek(SeekFrom::Start(offset))?,
+            None => self.file.stream_position()?,
+        };
+        if let Err(err) = self.append(entry) {
+            self.file.set_len(start)?;
+            self.file.seek(SeekFrom::Start(start))?;
+            return Err(err);
+        }
+        self.empty = false;
+        if self.crash_safe {
+            self.write_closing()?;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, entry: &[u8]) -> std::io::Result<()> {
+        if !self.empty {
+            self.file.write_all(b",\n")?;
+        }
+        self.file.write_all(entry)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+
+    /// Closes the container and flushes the file.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if self.closing_at.is_none() {
+            self.file.write_all(self.closing)?;
+        }
+        self.file.flush()
+    }
+}
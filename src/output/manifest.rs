@@ -0,0 +1,71 @@
+//! Description of an extraction run stored alongside generated files.
+
+use std::{fs::File, io::Write as _, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::dump_data::SiteInfo;
+use crate::input::data::SourceLocation;
+
+/// Wiki that produced the dump, as declared in its `siteinfo`.
+#[derive(Debug, Serialize)]
+pub struct SiteDescription {
+    pub site_name: Option<String>,
+    pub db_name: Option<String>,
+    pub base: Option<String>,
+    /// MediaWiki version (e.g. `MediaWiki 1.43.0-wmf.8`).
+    pub generator: Option<String>,
+}
+
+impl From<&SiteInfo> for SiteDescription {
+    fn from(site_info: &SiteInfo) -> Self {
+        SiteDescription {
+            site_name: site_info.site_name().map(str::to_string),
+            db_name: site_info.db_name().map(str::to_string),
+            base: site_info.base().map(str::to_string),
+            generator: site_info.generator().map(str::to_string),
+        }
+    }
+}
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub tool_version: &'static str,
+    pub source: SourceLocation,
+    /// Dump creation date, as reported by the mirror.
+    pub updated: Option<String>,
+    /// Site information of the first dump file that declared it.
+    pub site: Option<SiteDescription>,
+    pub generators: Vec<&'static str>,
+    /// Options the tool was run with.
+    pub options: serde_json::Value,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Number of pages that weren't excluded by filters and reached output.
+    pub pages_processed: usize,
+}
+
+impl Manifest {
+    pub fn new(source: SourceLocation, options: serde_json::Value) -> Self {
+        Manifest {
+            tool_version: env!("CARGO_PKG_VERSION"),
+            source,
+            updated: None,
+            site: None,
+            generators: Vec::new(),
+            options,
+            started_at: Utc::now(),
+            finished_at: None,
+            pages_processed: 0,
+        }
+    }
+
+    pub fn write(&self, target: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut file = File::create(target)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.write_all(b"\n")?;
+        file.flush()
+    }
+}
@@ -1,8 +1,16 @@
-use std::{fmt::Write as _, sync::LazyLock};
+use std::{fmt::Write as _, ops::ControlFlow, sync::LazyLock};
 
+use itertools::Itertools as _;
 use parse_wiki_text_2::*;
+use serde::Serialize;
 
-use super::{options::TextOptions, processing::{CollapseWhitespace, ProcessingPass as _}};
+use super::{
+    options::TextOptions,
+    processing::{
+        CollapseWhitespace, NormalizeUnicode, ProcessingPass as _, StripHtmlTags, UnicodeForm,
+    },
+    templates::TemplateRule,
+};
 
 pub const WIKI_CONFIGURATION: ConfigurationSource = ConfigurationSource {
     category_namespaces: &["category"],
@@ -87,14 +95,269 @@ pub const WIKI_CONFIGURATION: ConfigurationSource = ConfigurationSource {
     redirect_magic_words: &["REDIRECT"],
 };
 
-pub fn nodes_to_string(raw: &str, nodes: &Vec<Node<'_>>, options: &TextOptions) -> String {
+/// Builds parser configuration from [`WIKI_CONFIGURATION`] with additional
+/// extension tags.
+///
+/// Content of extension tags isn't parsed as wikitext, so wikis using custom
+/// extensions should list their tags here to avoid leaking tag content.
+pub fn configuration(extension_tags: &[String]) -> Configuration {
+    let extension_tags: Vec<String> = extension_tags
+        .iter()
+        .map(|it| it.trim().to_lowercase())
+        .filter(|it| !it.is_empty())
+        .collect();
+    let tags: Vec<&str> = WIKI_CONFIGURATION
+        .extension_tags
+        .iter()
+        .copied()
+        .chain(extension_tags.iter().map(String::as_str))
+        .unique()
+        .collect();
+
+    Configuration::new(&ConfigurationSource {
+        extension_tags: &tags,
+        ..WIKI_CONFIGURATION
+    })
+}
+
+pub fn nodes_to_string(raw: &str, nodes: &[Node<'_>], options: &TextOptions) -> String {
+    let mut buffer = String::with_capacity(128);
+    let mut emphasis = Emphasis::default();
+    let mut i = 0;
+    while i < nodes.len() {
+        if is_start_tag(&nodes[i], "table") {
+            let (content, consumed) = html_table_to_string(raw, &nodes[i..], options);
+            buffer.push_str(&content);
+            i += consumed;
+            continue;
+        }
+        if !(options.include_formatting && emphasis.toggle(&nodes[i], &mut buffer).is_some()) {
+            buffer.push_str(&node_to_string(raw, &nodes[i], options));
+        }
+        i += 1;
+    }
+    emphasis.close(&mut buffer);
+    buffer
+}
+
+/// Appends text of list `items` to `result`, followed by items of their
+/// nested lists, so they can be joined into a single clause.
+fn flatten_list_items(
+    raw: &str,
+    items: &[ListItem<'_>],
+    options: &TextOptions,
+    result: &mut Vec<String>,
+) {
+    for ListItem { nodes, .. } in items {
+        let mut text = String::new();
+        let mut nested = Vec::new();
+        let mut start = 0;
+        for (i, node) in nodes.iter().enumerate() {
+            if let Node::OrderedList { items, .. } | Node::UnorderedList { items, .. } = node {
+                text.push_str(&nodes_to_string(raw, &nodes[start..i], options));
+                nested.push(items);
+                start = i + 1;
+            }
+        }
+        text.push_str(&nodes_to_string(raw, &nodes[start..], options));
+        result.push(text);
+        for items in nested {
+            flatten_list_items(raw, items, options, result);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmphasisMarker {
+    Bold,
+    Italic,
+}
+
+impl EmphasisMarker {
+    fn as_str(self) -> &'static str {
+        match self {
+            EmphasisMarker::Bold => "**",
+            EmphasisMarker::Italic => "_",
+        }
+    }
+}
+
+/// Markdown emphasis markers that are currently open.
+///
+/// Wikitext bold and italic nodes toggle formatting instead of enclosing
+/// content, so opened markers are tracked to close them in the right order.
+#[derive(Debug, Default)]
+struct Emphasis(Vec<EmphasisMarker>);
+
+impl Emphasis {
+    /// Writes markers for a bold/italic toggle node.
+    ///
+    /// Returns whether written markers start by closing emphasis, or `None`
+    /// if `node` isn't a toggle.
+    fn toggle(&mut self, node: &Node<'_>, buffer: &mut String) -> Option<bool> {
+        let mut markers = match node {
+            Node::Bold { .. } => vec![EmphasisMarker::Bold],
+            Node::Italic { .. } => vec![EmphasisMarker::Italic],
+            Node::BoldItalic { .. } => vec![EmphasisMarker::Bold, EmphasisMarker::Italic],
+            _ => return None,
+        };
+        // innermost open markers are closed first
+        markers.sort_by_key(|marker| std::cmp::Reverse(self.0.iter().rposition(|it| it == marker)));
+        let closes = self.0.contains(&markers[0]);
+        for marker in markers {
+            self.toggle_marker(marker, buffer);
+        }
+        Some(closes)
+    }
+
+    fn toggle_marker(&mut self, marker: EmphasisMarker, buffer: &mut String) {
+        let index = match self.0.iter().rposition(|it| *it == marker) {
+            Some(it) => it,
+            None => {
+                buffer.push_str(marker.as_str());
+                self.0.push(marker);
+                return;
+            }
+        };
+        // markers opened after this one are closed and reopened around it
+        let nested = self.0.split_off(index + 1);
+        for it in nested.iter().rev() {
+            buffer.push_str(it.as_str());
+        }
+        buffer.push_str(marker.as_str());
+        self.0.pop();
+        for it in nested {
+            buffer.push_str(it.as_str());
+            self.0.push(it);
+        }
+    }
+
+    /// Closes all open markers.
+    fn close(&mut self, buffer: &mut String) {
+        while let Some(marker) = self.0.pop() {
+            buffer.push_str(marker.as_str());
+        }
+    }
+}
+
+/// Borrowed table cell of either a wiki-syntax or an HTML table.
+struct CellRef<'a, 'b> {
+    heading: bool,
+    content: &'b [Node<'a>],
+}
+
+fn table_to_string(raw: &str, rows: &[Vec<CellRef<'_, '_>>], options: &TextOptions) -> String {
     let mut buffer = String::with_capacity(128);
-    for inner in nodes {
-        buffer.push_str(&node_to_string(raw, inner, options));
+    buffer.push('\n');
+    if options.include_formatting {
+        // not the prettiest formatting, but valid markdown
+        let mut is_first_row = true;
+        for cells in rows {
+            buffer.push('|');
+            for CellRef { content, .. } in cells {
+                buffer.push(' ');
+                buffer.push_str(&nodes_to_string(raw, content, options));
+                buffer.push_str(" |");
+            }
+            buffer.push('\n');
+            if is_first_row {
+                buffer.push('|');
+                for _ in 0..cells.len() {
+                    buffer.push_str("-|");
+                }
+                buffer.push('\n');
+                is_first_row = false;
+            }
+        }
+    } else {
+        for cells in rows {
+            for CellRef { heading, content } in cells {
+                if *heading {
+                    continue;
+                }
+                let cell_text = nodes_to_string(raw, content, options);
+                if options.only_sentences && !cell_text.contains('.') {
+                    continue;
+                }
+                buffer.push_str(&cell_text);
+                buffer.push('\n');
+            }
+        }
     }
     buffer
 }
 
+fn is_start_tag(node: &Node<'_>, tag: &str) -> bool {
+    matches!(node, Node::StartTag { name, .. } if name == tag)
+}
+
+fn is_end_tag(node: &Node<'_>, tag: &str) -> bool {
+    matches!(node, Node::EndTag { name, .. } if name == tag)
+}
+
+/// Collects rows of an HTML `<table>` starting at the first node.
+///
+/// The parser doesn't nest HTML tags, it emits them as flat start/end tag
+/// siblings of their content. Cell content is therefore reconstructed from
+/// the ranges between `<td>`/`<th>` tags. Omitted end tags are tolerated and
+/// nested tables are kept as part of the enclosing cell content.
+///
+/// Returns collected rows and the number of consumed nodes.
+fn collect_html_table<'a, 'b>(nodes: &'b [Node<'a>]) -> (Vec<Vec<CellRef<'a, 'b>>>, usize) {
+    let mut rows: Vec<Vec<CellRef<'a, 'b>>> = Vec::new();
+    let mut row: Option<Vec<CellRef<'a, 'b>>> = None;
+    let mut cell: Option<(bool, usize)> = None;
+    let mut depth = 0;
+
+    let end_cell = |row: &mut Option<Vec<_>>, cell: &mut Option<(bool, usize)>, end| {
+        if let Some((heading, start)) = cell.take() {
+            row.get_or_insert_with(Vec::new).push(CellRef {
+                heading,
+                content: &nodes[start..end],
+            });
+        }
+    };
+
+    for (i, node) in nodes.iter().enumerate().skip(1) {
+        if is_start_tag(node, "table") {
+            depth += 1;
+        } else if is_end_tag(node, "table") && depth > 0 {
+            depth -= 1;
+        } else if depth > 0 {
+            continue;
+        } else if is_start_tag(node, "tr") || is_end_tag(node, "tr") {
+            end_cell(&mut row, &mut cell, i);
+            rows.extend(row.take());
+        } else if is_start_tag(node, "td") || is_start_tag(node, "th") {
+            end_cell(&mut row, &mut cell, i);
+            cell = Some((is_start_tag(node, "th"), i + 1));
+        } else if is_end_tag(node, "td") || is_end_tag(node, "th") {
+            end_cell(&mut row, &mut cell, i);
+        } else if is_end_tag(node, "table") {
+            end_cell(&mut row, &mut cell, i);
+            rows.extend(row.take());
+            return (rows, i + 1);
+        }
+    }
+
+    end_cell(&mut row, &mut cell, nodes.len());
+    rows.extend(row.take());
+    (rows, nodes.len())
+}
+
+/// Renders an HTML table starting at the first node in the same way as wiki
+/// tables are rendered.
+///
+/// Returns rendered text (empty if tables are excluded) and the number of
+/// consumed nodes.
+fn html_table_to_string(raw: &str, nodes: &[Node<'_>], options: &TextOptions) -> (String, usize) {
+    let (rows, consumed) = collect_html_table(nodes);
+    if !options.include_tables {
+        return (String::new(), consumed);
+    }
+    (table_to_string(raw, &rows, options), consumed)
+}
+
 pub fn node_to_string(raw: &str, node: &Node<'_>, options: &TextOptions) -> String {
     let mut buffer = String::with_capacity(128);
 
@@ -110,15 +373,14 @@ pub fn node_to_string(raw: &str, node: &Node<'_>, options: &TextOptions) -> Stri
                 buffer.push_str(&"#".repeat(*level as usize));
                 buffer.push(' ');
             }
-            for inner in nodes {
-                buffer.push_str(&node_to_string(raw, inner, options));
-            }
+            buffer.push_str(&nodes_to_string(raw, nodes, options));
             buffer.push('\n');
         }
+        Node::Image { text, .. } if options.include_image_captions => {
+            buffer.push_str(&image_caption(raw, text, options));
+        }
         Node::Link { text, .. } => {
-            for inner in text {
-                buffer.push_str(&node_to_string(raw, inner, options));
-            }
+            buffer.push_str(&nodes_to_string(raw, text, options));
         }
         Node::Preformatted { nodes, .. } if options.include_preformatted => {
             buffer.push('\n');
@@ -131,41 +393,44 @@ pub fn node_to_string(raw: &str, node: &Node<'_>, options: &TextOptions) -> Stri
             }
             buffer.push('\n');
         }
-        Node::Table { rows, .. } if options.include_tables && options.include_formatting => {
-            // not the prettiest formatting, but valid markdown
+        Node::Tag { name, nodes, .. } if options.include_poems && name == "poem" => {
+            buffer.push('\n');
+            buffer.push_str(&poem_to_string(raw, nodes, options));
             buffer.push('\n');
-            let mut is_first_row = true;
-            for TableRow { cells, .. } in rows {
-                buffer.push('|');
-                for TableCell { content, .. } in cells {
-                    buffer.push(' ');
-                    buffer.push_str(&nodes_to_string(raw, content, options));
-                    buffer.push_str(" |");
-                }
-                buffer.push('\n');
-                if is_first_row {
-                    buffer.push('|');
-                    for _ in 0..cells.len() {
-                        buffer.push_str("-|");
-                    }
-                    buffer.push('\n');
-                    is_first_row = false;
-                }
-            }
         }
         Node::Table { rows, .. } if options.include_tables => {
-            buffer.push('\n');
-            for TableRow { cells, .. } in rows {
-                for TableCell { content, type_, .. } in cells {
-                    if *type_ == TableCellType::Ordinary {
-                        let cell_text = nodes_to_string(raw, content, options);
-                        if options.only_sentences && !cell_text.contains('.') {
-                            continue;
-                        }
-                        buffer.push_str(&cell_text);
-                        buffer.push('\n');
-                    }
-                }
+            let rows = rows
+                .iter()
+                .map(|TableRow { cells, .. }| {
+                    cells
+                        .iter()
+                        .map(|TableCell { content, type_, .. }| CellRef {
+                            heading: *type_ == TableCellType::Heading,
+                            content,
+                        })
+                        .collect_vec()
+                })
+                .collect_vec();
+            buffer.push_str(&table_to_string(raw, &rows, options));
+        }
+        Node::OrderedList { items, .. } | Node::UnorderedList { items, .. }
+            if options.flatten_lists =>
+        {
+            let mut item_texts = Vec::with_capacity(items.len());
+            flatten_list_items(raw, items, options, &mut item_texts);
+            let content = item_texts
+                .iter()
+                .map(|it| {
+                    it.trim()
+                        .trim_end_matches(['.', ';', ','])
+                        .replace('\n', " ")
+                })
+                .filter(|it| !it.is_empty())
+                .join("; ");
+            if !content.is_empty() {
+                buffer.push('\n');
+                buffer.push_str(&content);
+                buffer.push_str(".\n");
             }
         }
         Node::OrderedList { items, .. } => {
@@ -234,30 +499,428 @@ pub fn node_to_string(raw: &str, node: &Node<'_>, options: &TextOptions) -> Stri
                 }
             }
         }
-        Node::Bold { .. } if options.include_formatting => {
-            buffer.push_str("**");
-        }
-        Node::Italic { .. } if options.include_formatting => {
-            buffer.push('_');
-        }
-        Node::BoldItalic { .. } if options.include_formatting => {
-            buffer.push_str("***");
+        // layout metadata, never part of text
+        Node::Template { name, .. } if default_sort_key(name).is_some() => {}
+        Node::Template {
+            name, parameters, ..
+        } if options.keep_raw_templates => {
+            buffer.push_str(&raw_template(raw, name, parameters, options));
         }
         Node::Template {
             name, parameters, ..
         } => {
-            buffer.push_str(&resolve_template(name, parameters));
+            buffer.push_str(&resolve_template(raw, name, parameters, options));
         }
-        _ => {}
+        // `----` separates paragraphs
+        Node::HorizontalDivider { .. } => buffer.push_str("\n\n"),
+        // excluded by options
+        Node::Image { .. } | Node::Preformatted { .. } | Node::Table { .. } => {}
+        // extension tags (e.g. `<ref>`, `<math>`) other than enabled poems
+        Node::Tag { .. } => {}
+        // formatting toggles, balanced by `Emphasis` of the enclosing nodes
+        Node::Bold { .. } | Node::Italic { .. } | Node::BoldItalic { .. } => {}
+        // behavior switches (e.g. `__TOC__`), comments and redirect markers
+        Node::MagicWord { .. } | Node::Comment { .. } | Node::Redirect { .. } => {}
+        // page metadata, collected separately
+        Node::Category { .. } => {}
+        // HTML tags; content between them is kept as sibling nodes
+        Node::StartTag { .. } | Node::EndTag { .. } => {}
+        // template parameters only have meaning in transcluded pages
+        Node::Parameter { .. } => {}
     }
 
     buffer
 }
 
-fn resolve_template(_name: &[Node<'_>], _parameters: &[Parameter<'_>]) -> String {
-    // TODO: {{lang-fr|anarchiste}}
-    // Unicode CLDR has mapping from country codes to short names
-    String::new()
+/// Lowercase image parameters which control layout and aren't captions.
+const IMAGE_OPTIONS: &[&str] = &[
+    "thumb",
+    "thumbnail",
+    "frame",
+    "framed",
+    "frameless",
+    "border",
+    "left",
+    "right",
+    "center",
+    "centre",
+    "none",
+    "upright",
+    "baseline",
+    "sub",
+    "super",
+    "top",
+    "text-top",
+    "middle",
+    "bottom",
+    "text-bottom",
+];
+
+/// Prefixes of lowercase image parameters with values.
+const IMAGE_OPTION_PREFIXES: &[&str] = &[
+    "alt=",
+    "class=",
+    "lang=",
+    "link=",
+    "page=",
+    "thumb=",
+    "thumbnail=",
+    "upright=",
+];
+
+fn is_image_option(param: &str) -> bool {
+    let param = param.trim().to_ascii_lowercase();
+    if param.is_empty() || IMAGE_OPTIONS.contains(&param.as_str()) {
+        return true;
+    }
+    if IMAGE_OPTION_PREFIXES
+        .iter()
+        .any(|prefix| param.starts_with(prefix))
+    {
+        return true;
+    }
+    // size: 200px, x200px, 200x200px
+    match param.strip_suffix("px") {
+        Some(size) => size.chars().all(|c| c.is_ascii_digit() || c == 'x'),
+        None => false,
+    }
+}
+
+enum ImageParamPart<'a, 'b> {
+    Text(&'b str),
+    Node(&'b Node<'a>),
+}
+
+/// Returns caption of an image, i.e. the last image parameter that isn't a
+/// layout option.
+///
+/// Image parameters are separated by `|` characters in text nodes; nested
+/// nodes (e.g. links in caption) are kept as part of the parameter they're
+/// in.
+fn image_caption(raw: &str, nodes: &[Node<'_>], options: &TextOptions) -> String {
+    let mut params: Vec<Vec<ImageParamPart>> = vec![Vec::new()];
+    for node in nodes {
+        match node {
+            Node::Text { value, .. } => {
+                let mut parts = value.split('|');
+                if let Some(first) = parts.next() {
+                    params.last_mut().unwrap().push(ImageParamPart::Text(first));
+                }
+                for part in parts {
+                    params.push(vec![ImageParamPart::Text(part)]);
+                }
+            }
+            other => params.last_mut().unwrap().push(ImageParamPart::Node(other)),
+        }
+    }
+
+    let caption = params.iter().rev().find(|param| {
+        let mut text = String::new();
+        for part in param.iter() {
+            match part {
+                ImageParamPart::Text(value) => text.push_str(value),
+                ImageParamPart::Node(_) => return true,
+            }
+        }
+        !is_image_option(&text)
+    });
+
+    let mut buffer = String::new();
+    for part in caption.into_iter().flatten() {
+        match part {
+            ImageParamPart::Text(value) => buffer.push_str(value),
+            ImageParamPart::Node(node) => buffer.push_str(&node_to_string(raw, node, options)),
+        }
+    }
+    buffer.trim().to_string()
+}
+
+/// Renders `<poem>` content, keeping line and stanza breaks.
+///
+/// Content of poems is parsed inline, so newlines are kept in text nodes.
+/// With formatting, lines end with a Markdown hard line break (`\`) so they
+/// aren't joined into a paragraph.
+fn poem_to_string(raw: &str, nodes: &[Node<'_>], options: &TextOptions) -> String {
+    let content = nodes_to_string(raw, nodes, options);
+    let mut lines = content.trim_matches('\n').lines().map(str::trim).peekable();
+
+    let mut buffer = String::with_capacity(content.len());
+    while let Some(line) = lines.next() {
+        buffer.push_str(line);
+        let next_empty = lines.peek().map(|it| it.is_empty()).unwrap_or(true);
+        if options.include_formatting && !line.is_empty() && !next_empty {
+            buffer.push('\\');
+        }
+        buffer.push('\n');
+    }
+    buffer
+}
+
+/// Reconstructs `{{name|params}}` markup of a template.
+fn raw_template(
+    raw: &str,
+    name: &[Node<'_>],
+    parameters: &[Parameter<'_>],
+    options: &TextOptions,
+) -> String {
+    let mut buffer = "{{".to_string();
+    buffer.push_str(nodes_to_string(raw, name, options).trim());
+    for param in parameters {
+        buffer.push('|');
+        if let Some(name) = &param.name {
+            buffer.push_str(&nodes_to_string(raw, name, options));
+            buffer.push('=');
+        }
+        buffer.push_str(&nodes_to_string(raw, &param.value, options));
+    }
+    buffer.push_str("}}");
+    buffer
+}
+
+/// Renders a template according to its rule in [`TextOptions::template_rules`].
+///
+/// Templates without a rule produce no text. Parameters are rendered like any
+/// other nodes, so templates nested in them are resolved (or dropped) by their
+/// own rules.
+fn resolve_template(
+    raw: &str,
+    name: &[Node<'_>],
+    parameters: &[Parameter<'_>],
+    options: &TextOptions,
+) -> String {
+    let positional = || parameters.iter().filter(|it| it.name.is_none());
+    let render = |param: &Parameter<'_>| nodes_to_string(raw, &param.value, options);
+
+    match options.template_rules.get(&template_name(name)) {
+        TemplateRule::Drop => String::new(),
+        TemplateRule::FirstParam => positional().next().map(render).unwrap_or_default(),
+        TemplateRule::Passthrough => positional()
+            .map(render)
+            .filter(|it| !it.trim().is_empty())
+            .join(" "),
+        TemplateRule::Param(key) => {
+            let named = parameters.iter().find(|it| {
+                it.name
+                    .as_ref()
+                    .map(|name| nodes_to_string(raw, name, options).trim() == key)
+                    .unwrap_or_default()
+            });
+            let param = match named {
+                Some(it) => Some(it),
+                None => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| positional().nth(index.checked_sub(1)?)),
+            };
+            param.map(render).unwrap_or_default()
+        }
+        TemplateRule::Text(text) => text.clone(),
+    }
+}
+
+/// Calls `f` for every node in the tree, parents before their children.
+pub fn visit_nodes<'a>(nodes: &[Node<'a>], f: &mut impl FnMut(&Node<'a>)) {
+    for node in nodes {
+        f(node);
+        match node {
+            Node::Category { ordinal: nodes, .. }
+            | Node::ExternalLink { nodes, .. }
+            | Node::Heading { nodes, .. }
+            | Node::Image { text: nodes, .. }
+            | Node::Link { text: nodes, .. }
+            | Node::Preformatted { nodes, .. }
+            | Node::Tag { nodes, .. } => visit_nodes(nodes, f),
+            Node::OrderedList { items, .. } | Node::UnorderedList { items, .. } => {
+                for ListItem { nodes, .. } in items {
+                    visit_nodes(nodes, f);
+                }
+            }
+            Node::DefinitionList { items, .. } => {
+                for DefinitionListItem { nodes, .. } in items {
+                    visit_nodes(nodes, f);
+                }
+            }
+            Node::Table { captions, rows, .. } => {
+                for TableCaption { content, .. } in captions {
+                    visit_nodes(content, f);
+                }
+                for TableRow { cells, .. } in rows {
+                    for TableCell { content, .. } in cells {
+                        visit_nodes(content, f);
+                    }
+                }
+            }
+            Node::Template { parameters, .. } => {
+                for Parameter { value, .. } in parameters {
+                    visit_nodes(value, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Normalizes page title the way MediaWiki does: underscores are replaced
+/// with spaces, whitespace is collapsed and first letter is capitalized.
+pub fn normalize_title(title: &str) -> String {
+    let title = title.replace('_', " ");
+    let mut words = title.split_whitespace();
+    let mut result = String::with_capacity(title.len());
+    if let Some(first) = words.next() {
+        let mut chars = first.chars();
+        if let Some(c) = chars.next() {
+            result.extend(c.to_uppercase());
+        }
+        result.push_str(chars.as_str());
+    }
+    for word in words {
+        result.push(' ');
+        result.push_str(word);
+    }
+    result
+}
+
+/// Returns normalized (trimmed, lowercase, spaces instead of underscores)
+/// template name.
+pub fn template_name(name: &[Node<'_>]) -> String {
+    let mut result = String::with_capacity(16);
+    for node in name {
+        if let Node::Text { value, .. } = node {
+            result.push_str(value);
+        }
+    }
+    result.trim().replace('_', " ").to_lowercase()
+}
+
+/// Lowercase names of templates that mark disambiguation pages.
+const DISAMBIGUATION_TEMPLATES: &[&str] = &[
+    "disambiguation",
+    "disambig",
+    "disamb",
+    "dab",
+    "geodis",
+    "hndis",
+    "numberdis",
+];
+
+/// Checks whether parsed page is a disambiguation page.
+///
+/// Pages are detected by `__DISAMBIG__` magic word or any of the
+/// [disambiguation templates](DISAMBIGUATION_TEMPLATES), anywhere in the
+/// node tree.
+pub fn is_disambiguation(raw: &str, nodes: &[Node<'_>]) -> bool {
+    let mut result = false;
+    visit_nodes(nodes, &mut |node| {
+        result |= match node {
+            Node::MagicWord { start, end } => raw
+                .get(*start..*end)
+                .map(|it| it.trim_matches('_').eq_ignore_ascii_case("DISAMBIG"))
+                .unwrap_or_default(),
+            Node::Template { name, .. } => {
+                DISAMBIGUATION_TEMPLATES.contains(&template_name(name).as_str())
+            }
+            _ => false,
+        };
+    });
+    result
+}
+
+/// Lowercase names of magic words which set the default category sort key.
+const DEFAULTSORT_NAMES: &[&str] = &["defaultsort", "defaultsortkey", "defaultcategorysort"];
+
+/// Returns the sort key if template `name` is a `{{DEFAULTSORT:key}}` magic
+/// word.
+fn default_sort_key(name: &[Node<'_>]) -> Option<String> {
+    let name: String = name
+        .iter()
+        .filter_map(|node| match node {
+            Node::Text { value, .. } => Some(*value),
+            _ => None,
+        })
+        .collect();
+    let (word, key) = name.split_once(':')?;
+    DEFAULTSORT_NAMES
+        .contains(&word.trim().to_lowercase().as_str())
+        .then(|| key.trim().to_string())
+}
+
+/// Returns the default category sort key of a page.
+///
+/// If `DEFAULTSORT` is used more than once, the last one applies.
+pub fn default_sort(nodes: &[Node<'_>]) -> Option<String> {
+    let mut result = None;
+    visit_nodes(nodes, &mut |node| {
+        if let Node::Template { name, .. } = node {
+            if let Some(key) = default_sort_key(name) {
+                result = Some(key);
+            }
+        }
+    });
+    result
+}
+
+/// Collects normalized names of categories a page belongs to, without
+/// namespace prefix and sort keys.
+pub fn categories(nodes: &[Node<'_>]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    visit_nodes(nodes, &mut |node| {
+        if let Node::Category { target, .. } = node {
+            let name = target.split_once(':').map(|(_, it)| it).unwrap_or(target);
+            let name = name.split(['|', '#']).next().unwrap_or_default();
+            let name = normalize_title(name);
+            if !name.is_empty() && !result.contains(&name) {
+                result.push(name);
+            }
+        }
+    });
+    result
+}
+
+/// Collects targets of all internal links in the node tree.
+pub fn link_targets<'a>(nodes: &[Node<'a>]) -> Vec<&'a str> {
+    let mut result = Vec::new();
+    visit_nodes(nodes, &mut |node| {
+        if let Node::Link { target, .. } = node {
+            result.push(*target);
+        }
+    });
+    result
+}
+
+/// Returns lowercase plain text of a heading, without formatting.
+fn heading_title(nodes: &[Node<'_>]) -> String {
+    let mut result = String::with_capacity(16);
+    visit_nodes(nodes, &mut |node| {
+        if let Node::Text { value, .. } = node {
+            result.push_str(value);
+        }
+    });
+    result.trim().to_lowercase()
+}
+
+/// Collects targets of internal links, excluding links in
+/// [skipped sections](SKIP_SECTIONS) unless `include_skipped` is set.
+pub fn text_link_targets<'a>(nodes: &[Node<'a>], include_skipped: bool) -> Vec<&'a str> {
+    if include_skipped {
+        return link_targets(nodes);
+    }
+
+    let mut result = Vec::new();
+    let mut skip_section = None;
+    for node in nodes {
+        if let Node::Heading { level, nodes, .. } = node {
+            if skip_section.is_some_and(|it| *level <= it) {
+                skip_section = None;
+            }
+            if skip_section.is_none() && SKIP_SECTIONS.contains(&heading_title(nodes).as_str()) {
+                skip_section = Some(*level);
+            }
+        }
+        if skip_section.is_none() {
+            result.extend(link_targets(std::slice::from_ref(node)));
+        }
+    }
+    result
 }
 
 /// List of lowercase Wikipedia section titles to skip.
@@ -277,9 +940,76 @@ static MAX_SKIP_LEN: LazyLock<usize> = LazyLock::new(|| {
 });
 
 pub fn nodes_to_text<'a>(nodes: impl AsRef<[Node<'a>]>, options: &TextOptions) -> String {
+    let text = if options.first_paragraph_only {
+        // stop at the end of the first paragraph with any text
+        render_text(nodes.as_ref(), options, |text| {
+            match text.trim().is_empty() {
+                true => ControlFlow::Continue(()),
+                false => ControlFlow::Break(()),
+            }
+        })
+    } else {
+        render_text(nodes.as_ref(), options, |_| ControlFlow::Continue(()))
+    };
+    finish_text(text, options)
+}
+
+/// Splits page text into paragraphs.
+///
+/// Paragraphs are text between [`Node::ParagraphBreak`]s; headings also end a
+/// paragraph. Skipped sections are left out just like in [`nodes_to_text`],
+/// and empty paragraphs are dropped.
+pub fn nodes_to_paragraphs<'a>(
+    nodes: impl AsRef<[Node<'a>]>,
+    options: &TextOptions,
+) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut push = |text: String| {
+        let text = finish_text(text, options);
+        if !text.trim().is_empty() {
+            paragraphs.push(text.trim().to_string());
+        }
+    };
+    let rest = render_text(nodes.as_ref(), options, |text| {
+        push(std::mem::take(text));
+        ControlFlow::Continue(())
+    });
+    push(rest);
+    paragraphs
+}
+
+fn finish_text(mut text: String, options: &TextOptions) -> String {
+    if options.strip_html {
+        text = StripHtmlTags::process(text);
+    }
+    NormalizeUnicode::apply(CollapseWhitespace::process(text), options.normalize)
+}
+
+/// Renders text of nodes outside of skipped sections.
+///
+/// `paragraph_end` is called with text rendered so far whenever a paragraph
+/// break or a heading is reached. Rendering stops if it returns
+/// [`ControlFlow::Break`].
+fn render_text(
+    nodes: &[Node<'_>],
+    options: &TextOptions,
+    mut paragraph_end: impl FnMut(&mut String) -> ControlFlow<()>,
+) -> String {
     let mut text = String::with_capacity(2048);
     let mut skip_section = None;
-    for node in nodes.as_ref() {
+    // headings held back by `drop_empty_sections` until text follows them
+    let mut pending_headings: Vec<(u8, String)> = Vec::new();
+    let mut emphasis = Emphasis::default();
+    let mut i = 0;
+    while i < nodes.len() {
+        let node = &nodes[i];
+        i += 1;
+        if let Node::Heading { level, .. } = node {
+            // sections of held back headings end without any text
+            while pending_headings.last().is_some_and(|(it, _)| it >= level) {
+                pending_headings.pop();
+            }
+        }
         if let Some(req_level) = skip_section {
             if let Node::Heading { level, .. } = node {
                 if level <= req_level {
@@ -292,7 +1022,35 @@ pub fn nodes_to_text<'a>(nodes: impl AsRef<[Node<'a>]>, options: &TextOptions) -
             }
         }
 
-        let content = node_to_string(&text, node, options);
+        let paragraph_ended = match node {
+            Node::ParagraphBreak { .. } | Node::Heading { .. } => {
+                // formatting doesn't carry over into the next paragraph
+                emphasis.close(&mut text);
+                paragraph_end(&mut text)
+            }
+            _ => ControlFlow::Continue(()),
+        };
+        if paragraph_ended.is_break() {
+            return text;
+        }
+        if let Node::ParagraphBreak { .. } = node {
+            continue;
+        }
+
+        let mut content = String::new();
+        let mut toggle = None;
+        if is_start_tag(node, "table") {
+            let (table, consumed) = html_table_to_string(&text, &nodes[(i - 1)..], options);
+            i += consumed - 1;
+            content = table;
+        } else {
+            if options.include_formatting {
+                toggle = emphasis.toggle(node, &mut content);
+            }
+            if toggle.is_none() {
+                content = node_to_string(&text, node, options);
+            }
+        }
         let trimmed = content.trim();
         if let Node::Heading { level, .. } = node {
             let trimmed = if options.include_formatting {
@@ -317,15 +1075,132 @@ pub fn nodes_to_text<'a>(nodes: impl AsRef<[Node<'a>]>, options: &TextOptions) -
             if !options.include_headings {
                 continue;
             }
+            if options.drop_empty_sections {
+                pending_headings.push((*level, content));
+                continue;
+            }
         }
-        if trimmed.is_empty() {
+        // entities such as `&nbsp;` and dividers are deliberate spacing
+        let is_spacing = matches!(
+            node,
+            Node::CharacterEntity { .. } | Node::HorizontalDivider { .. }
+        );
+        if trimmed.is_empty() && !is_spacing {
             continue;
         }
-        if text.as_bytes().last() == Some(&b'.') {
-            text.push(' ');
+        if !trimmed.is_empty() {
+            for (_, heading) in pending_headings.drain(..) {
+                push_content(&mut text, &heading, false);
+            }
         }
-        text.push_str(&content);
+        // closing markers end preceding text, so they aren't separated from it
+        if toggle == Some(true) {
+            text.push_str(&content);
+            continue;
+        }
+        push_content(
+            &mut text,
+            &content,
+            matches!(node, Node::HorizontalDivider { .. }),
+        );
+    }
+    emphasis.close(&mut text);
+    text
+}
+
+/// Appends rendered node content, separating it from a preceding sentence.
+fn push_content(text: &mut String, content: &str, is_divider: bool) {
+    // a sentence may end inside of closed emphasis (e.g. `**Bold.**`)
+    if text.trim_end_matches(['*', '_']).ends_with('.') && !is_divider {
+        text.push(' ');
+    }
+    text.push_str(content);
+}
+
+/// Page section with nested subsections.
+#[derive(Debug, Default, Serialize)]
+pub struct Section {
+    pub heading: String,
+    pub level: u8,
+    pub text: String,
+    pub children: Vec<Section>,
+}
+
+impl Section {
+    fn finish(&mut self, form: UnicodeForm) {
+        let text = CollapseWhitespace::process(std::mem::take(&mut self.text));
+        self.text = NormalizeUnicode::apply(text.trim(), form);
+        self.heading = NormalizeUnicode::apply(&self.heading, form);
+    }
+}
+
+/// Builds section tree of a page.
+///
+/// Wikitext headings don't enclose their content, so the hierarchy is
+/// reconstructed from heading levels. Returned root section has level 0, no
+/// heading and contains the lead text of the page. Like in text output,
+/// [skipped sections](SKIP_SECTIONS) are left out along with their
+/// subsections.
+pub fn nodes_to_sections(raw: &str, nodes: &[Node<'_>], options: &TextOptions) -> Section {
+    // stack of currently open sections; first one is the root
+    let mut open = vec![Section::default()];
+    let mut skip_section = None;
+
+    let close_last = |open: &mut Vec<Section>| {
+        let mut section = open.pop().expect("root section closed");
+        section.finish(options.normalize);
+        open.last_mut()
+            .expect("root section closed")
+            .children
+            .push(section);
+    };
+
+    let mut i = 0;
+    while i < nodes.len() {
+        let node = &nodes[i];
+        i += 1;
+
+        if let Node::Heading { level, nodes, .. } = node {
+            if skip_section.is_some_and(|it| *level <= it) {
+                skip_section = None;
+            }
+            if skip_section.is_none() && SKIP_SECTIONS.contains(&heading_title(nodes).as_str()) {
+                skip_section = Some(*level);
+            }
+            if skip_section.is_some() {
+                continue;
+            }
+            while open.len() > 1 && open.last().map(|it| it.level >= *level).unwrap_or_default() {
+                close_last(&mut open);
+            }
+            open.push(Section {
+                heading: nodes_to_string(raw, nodes, options).trim().to_string(),
+                level: *level,
+                ..Default::default()
+            });
+            continue;
+        }
+        if skip_section.is_some() {
+            continue;
+        }
+
+        let content = if is_start_tag(node, "table") {
+            let (content, consumed) = html_table_to_string(raw, &nodes[(i - 1)..], options);
+            i += consumed - 1;
+            content
+        } else {
+            node_to_string(raw, node, options)
+        };
+        open.last_mut()
+            .expect("root section closed")
+            .text
+            .push_str(&content);
     }
-    
-    CollapseWhitespace::process(text)
-}
\ No newline at end of file
+
+    while open.len() > 1 {
+        close_last(&mut open);
+    }
+    let mut root = open.pop().expect("root section closed");
+    root.finish(options.normalize);
+    root
+}
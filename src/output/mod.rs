@@ -1,6 +1,15 @@
+pub mod encoding;
 pub mod generator;
+pub mod json_container;
+pub mod manifest;
 pub mod mediawiki;
 pub mod options;
 pub mod processing;
+pub mod redirects;
+pub mod sink;
+pub mod split;
+pub mod templates;
+pub mod writer;
 
 pub use generator::DataGenerator;
+pub use sink::PageSink;
@@ -1,47 +1,574 @@
-use clap::Parser;
+use std::path::PathBuf;
 
-#[derive(Debug, Parser)]
+use clap::{ArgAction, Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use super::{encoding::OutputEncoding, processing::UnicodeForm, templates::TemplateRules};
+
+#[derive(Debug, Parser, Serialize, Deserialize)]
 pub struct GeneratorOptions {
     /// Collect redirection articles in a file.
     #[arg(short = 'R', long = "collect-redirects", default_value_t = false)]
     pub redirects: bool,
+    /// Also write redirects with chains resolved to their final target.
+    ///
+    /// Written into `redirects_resolved.json` once extraction finishes.
+    #[arg(
+        long = "follow-redirects",
+        default_value_t = false,
+        requires = "redirects"
+    )]
+    pub follow_redirects: bool,
     /// Collect article metadata.
     #[arg(short = 'M', long = "collect-metadata", default_value_t = false)]
     pub metadata: bool,
     /// Collect all words into a dictionary.
     #[arg(short = 'D', long = "build-dictionary", default_value_t = false)]
     pub dictionary: bool,
+    /// Count word occurrences and write them as `word<TAB>count` lines.
+    #[arg(
+        long = "dictionary-counts",
+        default_value_t = false,
+        requires = "dictionary"
+    )]
+    pub dictionary_counts: bool,
+    /// Order of words in a dictionary with counts.
+    #[arg(
+        long = "dictionary-sort",
+        value_enum,
+        default_value_t = DictionarySort::Frequency,
+        requires = "dictionary_counts"
+    )]
+    pub dictionary_sort: DictionarySort,
+    /// Maximum number of pages counted into the dictionary in parallel.
+    ///
+    /// Counting runs alongside page processing, which waits only once this
+    /// many pages are still being counted.
+    #[arg(long = "dictionary-jobs", value_name = "N", default_value_t = 4)]
+    pub dictionary_jobs: usize,
     /// Collect text content into a dump file.
     #[arg(short = 'T', long = "collect-text", default_value_t = false)]
     pub text: bool,
+    /// Additional format to write text content in.
+    ///
+    /// `conll` writes one token per line, with an empty line between sentences,
+    /// into `wiki_tokens.conll`. Tokenization is lossy: sentence splitting and
+    /// punctuation handling are heuristic, and numbers and symbols are dropped.
+    ///
+    /// `markdown-files` writes each article into a separate Markdown file with
+    /// YAML frontmatter in `markdown` directory. Implies `--markdown` for all
+    /// text outputs.
+    ///
+    /// `paragraphs` writes each paragraph of an article as a separate record
+    /// into `wiki_paragraphs.jsonl`.
+    #[arg(
+        long = "format",
+        alias = "output-format",
+        value_enum,
+        default_value_t = TextFormat::Plain
+    )]
+    pub format: TextFormat,
+    /// Write text content of each article into a separate file.
+    ///
+    /// Files are placed in `articles` directory of the output path.
+    #[arg(long = "split-output", default_value_t = false)]
+    pub split_output: bool,
+    /// Collect revision edit summaries into a JSONL file.
+    #[arg(short = 'C', long = "collect-comments", default_value_t = false)]
+    pub comments: bool,
+    /// Collect disambiguation pages and their link targets into a JSONL file.
+    #[arg(long = "collect-disambiguation", default_value_t = false)]
+    pub disambiguation: bool,
+    /// Collect section hierarchy of pages into a JSONL file.
+    #[arg(long = "sections-json", default_value_t = false)]
+    pub sections: bool,
+    /// Collect id, title and text of each article into a JSONL file.
+    #[arg(long = "pages-jsonl", default_value_t = false)]
+    pub pages: bool,
+    /// Split JSONL page output into N files.
+    ///
+    /// Pages are assigned to shards by a hash of their id, so assignment is
+    /// stable across runs.
+    #[arg(
+        long = "shards",
+        value_name = "N",
+        default_value_t = 1,
+        requires = "pages",
+        value_parser = parse_shards
+    )]
+    pub shards: usize,
+    /// Also write redirect pages into JSONL page output, with empty text and
+    /// `redirect_to` set to the redirect target.
+    ///
+    /// Independent of `--collect-redirects`, which still writes the redirects
+    /// file if enabled. With `--process-redirect-bodies`, redirect pages are
+    /// written like any other page instead, keeping `redirect_to`.
+    #[arg(
+        long = "emit-redirects-as-pages",
+        default_value_t = false,
+        requires = "pages"
+    )]
+    pub redirects_as_pages: bool,
+    /// Also process wikitext of redirect pages into text, links and other page
+    /// outputs.
+    ///
+    /// Redirect pages are otherwise only written into the redirects file (and
+    /// JSONL pages with `--emit-redirects-as-pages`). Independent of
+    /// `--collect-redirects`.
+    #[arg(long = "process-redirect-bodies", default_value_t = false)]
+    pub process_redirect_bodies: bool,
+    /// Collect categories and default sort key of pages into a JSONL file.
+    #[arg(long = "collect-categories", default_value_t = false)]
+    pub categories: bool,
+    /// Collect internal links between articles into a JSONL file.
+    #[arg(long = "collect-links", default_value_t = false)]
+    pub links: bool,
+    /// Also collect links from sections which are skipped in text, such as
+    /// "See also".
+    #[arg(
+        long = "include-see-also-links",
+        default_value_t = false,
+        requires = "links"
+    )]
+    pub include_see_also_links: bool,
+    /// Collect every page revision into a JSONL file.
+    ///
+    /// Meant for `pages-meta-history` dumps; text is still extracted only from
+    /// the latest revision.
+    #[arg(long = "history", default_value_t = false)]
+    pub history: bool,
+    /// Include revision wikitext in history output instead of only its
+    /// length.
+    #[arg(long = "history-text", default_value_t = false, requires = "history")]
+    pub history_text: bool,
+    /// Only process pages from listed namespaces (comma separated keys or
+    /// names).
+    ///
+    /// Namespaces are validated against the ones declared by the dump. Keys
+    /// can be negative (e.g. `-1` for `Special`).
+    #[arg(
+        long = "namespaces",
+        value_name = "NAMESPACES",
+        value_delimiter = ',',
+        allow_hyphen_values = true
+    )]
+    pub namespaces: Vec<String>,
+    /// Skip pages from listed namespaces (comma separated keys or names) and
+    /// process all others.
+    ///
+    /// Can't be combined with `--namespaces`.
+    #[arg(
+        long = "exclude-namespaces",
+        value_name = "NAMESPACES",
+        value_delimiter = ',',
+        allow_hyphen_values = true
+    )]
+    pub exclude_namespaces: Vec<String>,
+    /// Process only a random fraction (0.0 - 1.0) of pages.
+    ///
+    /// Sampling is deterministic for a given `--seed`.
+    #[arg(long = "sample", value_name = "RATE", default_value_t = 1.0, value_parser = parse_rate)]
+    pub sample: f64,
+    /// Seed used for page sampling.
+    #[arg(long = "seed", default_value_t = 0)]
+    pub seed: u64,
+    /// Only process pages with titles listed in a file (one per line).
+    #[arg(long = "title-list", value_name = "PATH")]
+    pub title_list: Option<PathBuf>,
+    /// Only process pages with given content model (e.g. `Scribunto` or
+    /// `json`).
+    ///
+    /// Content of models other than `wikitext` isn't parsed and is written to
+    /// text outputs as is.
+    #[arg(
+        long = "content-model",
+        value_name = "MODEL",
+        default_value = "wikitext"
+    )]
+    pub content_model: String,
+    /// Time budget for parsing a single page, in seconds.
+    ///
+    /// Pages which take longer (usually due to malformed wikitext) are skipped
+    /// with a warning. Set to 0 to disable the limit.
+    #[arg(long = "page-timeout", value_name = "SECONDS", default_value_t = 30)]
+    pub page_timeout: u64,
+    /// Drop pages whose text is detected to be in another language, given as
+    /// ISO 639-3 code (e.g. `eng`).
+    ///
+    /// Detection runs on generated text, so pages are still parsed. It's
+    /// unreliable on short text, so pages are only dropped if detection is at
+    /// least `--language-confidence` confident.
+    #[arg(long = "detect-language", value_name = "CODE", value_parser = parse_language)]
+    pub detect_language: Option<String>,
+    /// Confidence (0.0 - 1.0) of language detection required to drop a page.
+    #[arg(
+        long = "language-confidence",
+        value_name = "RATE",
+        default_value_t = 0.5,
+        value_parser = parse_rate,
+        requires = "detect_language"
+    )]
+    pub language_confidence: f64,
+    /// Skip pages with text of fewer sentences.
+    ///
+    /// Only sentences ending with `.`, `?` or `!` are counted; abbreviations
+    /// are guessed like when splitting sentences.
+    #[arg(long = "min-sentences", value_name = "COUNT", default_value_t = 0)]
+    pub min_sentences: usize,
+    /// Skip all pages until one with given title is reached.
+    ///
+    /// Skipped pages aren't parsed, but the dump still has to be read (and
+    /// downloaded) up to that page.
+    #[arg(long = "resume-from-title", value_name = "TITLE")]
+    pub resume_from_title: Option<String>,
+    /// Skip pages with a title that was already processed.
+    ///
+    /// Useful for concatenated split files or history dumps which can contain
+    /// the same page more than once.
+    #[arg(long = "dedup-titles", default_value_t = false)]
+    pub dedup_titles: bool,
+    /// Only parse pages and report failures, without writing any output.
+    ///
+    /// Exits with a non-zero status if any page fails to parse.
+    #[arg(long = "validate-only", default_value_t = false)]
+    pub validate_only: bool,
+    /// Record pages that couldn't be processed into a JSONL file.
+    ///
+    /// Each line contains page `id`, `title`, error `kind` and `message`.
+    #[arg(long = "errors-file", value_name = "PATH")]
+    pub errors_file: Option<PathBuf>,
+    /// Keep JSON array/object outputs valid at all times.
+    ///
+    /// Closing bracket is written after every entry and overwritten by the
+    /// next one, so files remain valid even if the process is killed.
+    #[arg(long = "crash-safe-json", default_value_t = false)]
+    pub crash_safe_json: bool,
+    /// Flush output files every N processed pages.
+    ///
+    /// Allows following produced files (e.g. with `tail -f`) during long runs.
+    /// Set to 0 to only flush when extraction finishes.
+    #[arg(long = "flush-interval", default_value_t = 1000)]
+    pub flush_interval: usize,
+    /// Number of processed pages queued for writing.
+    ///
+    /// When writing lags behind, page processing blocks once the queue is
+    /// full, which in turn pauses reading of the dump, so memory stays bounded
+    /// by roughly this many pages of extracted text.
+    #[arg(long = "page-buffer", value_name = "N", default_value_t = 1024)]
+    pub page_buffer: usize,
+}
+
+/// Order of words in a dictionary with counts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DictionarySort {
+    /// Most frequent words first.
+    #[default]
+    Frequency,
+    /// Alphabetical order.
+    Lexical,
+}
+
+/// Additional format of extracted text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextFormat {
+    /// Only text outputs enabled by other options.
+    #[default]
+    Plain,
+    /// Tokenized text in CoNLL style.
+    Conll,
+    /// Markdown file per article.
+    MarkdownFiles,
+    /// JSONL record per paragraph.
+    Paragraphs,
+}
+
+/// Description of an output generator.
+pub struct GeneratorInfo {
+    pub name: &'static str,
+    /// Argument that enables the generator.
+    pub argument: &'static str,
+    /// File or directory produced in the output path.
+    pub output: &'static str,
+    /// Checks whether the generator is enabled by options.
+    pub enabled: fn(&GeneratorOptions) -> bool,
+    /// Disables the generator in options.
+    pub disable: fn(&mut GeneratorOptions),
+}
+
+/// All available generators.
+pub const GENERATORS: [GeneratorInfo; 16] = [
+    GeneratorInfo {
+        name: "redirects",
+        argument: "--collect-redirects",
+        output: "redirects.json",
+        enabled: |it| it.redirects,
+        disable: |it| it.redirects = false,
+    },
+    GeneratorInfo {
+        name: "resolved_redirects",
+        argument: "--follow-redirects",
+        output: "redirects_resolved.json",
+        enabled: |it| it.follow_redirects,
+        disable: |it| it.follow_redirects = false,
+    },
+    GeneratorInfo {
+        name: "metadata",
+        argument: "--collect-metadata",
+        output: "wiki_page_info.json",
+        enabled: |it| it.metadata,
+        disable: |it| it.metadata = false,
+    },
+    GeneratorInfo {
+        name: "dictionary",
+        argument: "--build-dictionary",
+        output: "dictionary.txt",
+        enabled: |it| it.dictionary,
+        disable: |it| it.dictionary = false,
+    },
+    GeneratorInfo {
+        name: "text",
+        argument: "--collect-text",
+        output: "wiki_sentences.txt",
+        enabled: |it| it.text,
+        disable: |it| it.text = false,
+    },
+    GeneratorInfo {
+        name: "conll",
+        argument: "--format conll",
+        output: "wiki_tokens.conll",
+        enabled: |it| it.format == TextFormat::Conll,
+        disable: |it| it.format = TextFormat::Plain,
+    },
+    GeneratorInfo {
+        name: "split_output",
+        argument: "--split-output",
+        output: "articles/",
+        enabled: |it| it.split_output,
+        disable: |it| it.split_output = false,
+    },
+    GeneratorInfo {
+        name: "markdown_files",
+        argument: "--format markdown-files",
+        output: "markdown/",
+        enabled: |it| it.format == TextFormat::MarkdownFiles,
+        disable: |it| it.format = TextFormat::Plain,
+    },
+    GeneratorInfo {
+        name: "paragraphs",
+        argument: "--format paragraphs",
+        output: "wiki_paragraphs.jsonl",
+        enabled: |it| it.format == TextFormat::Paragraphs,
+        disable: |it| it.format = TextFormat::Plain,
+    },
+    GeneratorInfo {
+        name: "comments",
+        argument: "--collect-comments",
+        output: "comments.jsonl",
+        enabled: |it| it.comments,
+        disable: |it| it.comments = false,
+    },
+    GeneratorInfo {
+        name: "disambiguation",
+        argument: "--collect-disambiguation",
+        output: "disambiguation.jsonl",
+        enabled: |it| it.disambiguation,
+        disable: |it| it.disambiguation = false,
+    },
+    GeneratorInfo {
+        name: "sections",
+        argument: "--sections-json",
+        output: "sections.jsonl",
+        enabled: |it| it.sections,
+        disable: |it| it.sections = false,
+    },
+    GeneratorInfo {
+        name: "pages",
+        argument: "--pages-jsonl",
+        output: "wiki_pages.jsonl",
+        enabled: |it| it.pages,
+        disable: |it| it.pages = false,
+    },
+    GeneratorInfo {
+        name: "categories",
+        argument: "--collect-categories",
+        output: "categories.jsonl",
+        enabled: |it| it.categories,
+        disable: |it| it.categories = false,
+    },
+    GeneratorInfo {
+        name: "links",
+        argument: "--collect-links",
+        output: "links.jsonl",
+        enabled: |it| it.links,
+        disable: |it| it.links = false,
+    },
+    GeneratorInfo {
+        name: "history",
+        argument: "--history",
+        output: "revisions.jsonl",
+        enabled: |it| it.history,
+        disable: |it| it.history = false,
+    },
+];
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.parse().map_err(|err| format!("{}", err))?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err("rate must be between 0.0 and 1.0".to_string())
+    }
+}
+
+fn parse_language(value: &str) -> Result<String, String> {
+    match whatlang::Lang::from_code(value.to_ascii_lowercase()) {
+        Some(lang) => Ok(lang.code().to_string()),
+        None => Err(format!("unknown ISO 639-3 language code: {}", value)),
+    }
+}
+
+fn parse_shards(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("at least one shard is required".to_string()),
+        Ok(it) => Ok(it),
+        Err(err) => Err(format!("{}", err)),
+    }
 }
 
 impl GeneratorOptions {
     pub fn any(&self) -> bool {
-        [self.redirects, self.metadata, self.dictionary, self.text]
-            .into_iter()
-            .any(|it| it)
+        GENERATORS.iter().any(|it| (it.enabled)(self))
+    }
+
+    /// Disables all generators.
+    pub fn disable_outputs(&mut self) {
+        for generator in &GENERATORS {
+            (generator.disable)(self);
+        }
+    }
+
+    /// Names of enabled generators.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        GENERATORS
+            .iter()
+            .filter(|it| (it.enabled)(self))
+            .map(|it| it.name)
+            .collect()
     }
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Parser, Serialize, Deserialize)]
 pub struct TextOptions {
     /// Include headings in dump output.
     #[arg(short = 'H', long = "include-headings", default_value_t = false)]
     pub include_headings: bool,
+    /// Omit headings of sections without any text, including their
+    /// subsections.
+    #[arg(
+        long = "drop-empty-sections",
+        default_value_t = false,
+        requires = "include_headings"
+    )]
+    pub drop_empty_sections: bool,
     /// Include preformatted text in dump output.
     #[arg(short = 'P', long = "include-preformatted", default_value_t = false)]
     pub include_preformatted: bool,
+    /// Include image captions in dump output.
+    #[arg(
+        long = "include-image-captions",
+        alias = "include-thumbnails",
+        default_value_t = false
+    )]
+    pub include_image_captions: bool,
+    /// Include content of `<poem>` tags in dump output.
+    ///
+    /// Line and stanza breaks of verses are preserved.
+    #[arg(long = "include-poems", default_value_t = false)]
+    pub include_poems: bool,
     /// Exclude table content in dump output.
-    #[arg(long = "no-tables", default_value_t = true)]
+    #[arg(long = "no-tables", default_value_t = true, action = ArgAction::SetFalse)]
     pub include_tables: bool,
+    /// Render list items on a single line, joined with `; `.
+    ///
+    /// Lists are then read as a single sentence-like clause.
+    #[arg(long = "flatten-lists", default_value_t = false)]
+    pub flatten_lists: bool,
     /// Produce Markdown instead of raw text dump.
     #[arg(long = "markdown", default_value_t = false)]
     pub include_formatting: bool,
+    /// Only keep the first paragraph of each article with any text.
+    ///
+    /// Affects text based outputs (text dump, dictionary, pages, ...) but not
+    /// sections and paragraphs.
+    #[arg(long = "first-paragraph-only", default_value_t = false)]
+    pub first_paragraph_only: bool,
     /// Make produced output contain only sentences when possible
     ///
     /// Not all edge cases are handled, but it will (for instance) exclude table
     /// cells and list items with text that doesn't end in punctuation.
     #[arg(short = 'S', long = "only-sentences", default_value_t = true)]
     pub only_sentences: bool,
+    /// Exclude disambiguation pages from text dump output.
+    ///
+    /// Disambiguation pages are mostly lists of links and not prose.
+    #[arg(
+        long = "exclude-disambiguation",
+        default_value_t = true,
+        action = ArgAction::Set
+    )]
+    pub exclude_disambiguation: bool,
+    /// Character encoding of the text dump.
+    ///
+    /// JSON outputs are always UTF-8.
+    #[arg(long = "output-encoding", value_enum, default_value_t = OutputEncoding::Utf8)]
+    pub output_encoding: OutputEncoding,
+    /// Substitute for characters that can't be represented in output
+    /// encoding.
+    #[arg(long = "unmappable-char", default_value_t = '?')]
+    pub unmappable_char: char,
+    /// Remove inline HTML tags (e.g. `<small>`, `<span>`) left in extracted
+    /// text, keeping their content.
+    ///
+    /// Only common inline tags are removed; other uses of `<` are kept.
+    #[arg(long = "strip-html", default_value_t = false)]
+    pub strip_html: bool,
+    /// Unicode normalization applied to extracted text.
+    #[arg(
+        long = "normalize",
+        alias = "replace-unicode",
+        value_enum,
+        default_value_t = UnicodeForm::None
+    )]
+    pub normalize: UnicodeForm,
+    /// Additional extension tag name used by the wiki (repeatable).
+    ///
+    /// Content of extension tags isn't treated as article text.
+    #[arg(long = "extension-tag", value_name = "NAME", action = ArgAction::Append)]
+    pub extension_tags: Vec<String>,
+    /// Keep templates as `{{name|params}}` markup instead of rendering them.
+    ///
+    /// Markup is reconstructed from parsed parameters, so formatting within
+    /// them is converted like the rest of the text.
+    #[arg(long = "keep-raw-templates", default_value_t = false)]
+    pub keep_raw_templates: bool,
+    /// TOML file with template rendering rules, overriding bundled ones.
+    ///
+    /// Templates are mapped to one of `drop`, `first-param`, `passthrough`,
+    /// `{ param = "name" }` or `{ text = "..." }` under a `[templates]` table.
+    #[arg(long = "template-rules", value_name = "PATH")]
+    pub template_rules_path: Option<PathBuf>,
+    /// Only render listed templates (repeatable), as their first parameter,
+    /// and drop all others.
+    ///
+    /// Lighter alternative to `--template-rules` for a short list of relevant
+    /// templates; bundled rules aren't used.
+    #[arg(long = "passthrough-template", value_name = "NAME", action = ArgAction::Append)]
+    pub passthrough_templates: Vec<String>,
+    /// Template rules in effect; bundled ones merged with
+    /// `template_rules_path`.
+    #[arg(skip)]
+    #[serde(skip)]
+    pub template_rules: TemplateRules,
 }
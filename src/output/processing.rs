@@ -3,11 +3,19 @@
 use std::sync::LazyLock;
 
 use aho_corasick::AhoCorasick;
+use clap::ValueEnum;
+use itertools::Itertools as _;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization as _;
 
 pub trait ProcessingPass {
     fn process(chunk: impl AsRef<str>) -> String;
 }
 
+/// Replaces predefined XML entities with characters they stand for.
+///
+/// HTML entities such as `&nbsp;` arrive escaped (`&amp;nbsp;`) in dumps, so
+/// they're left for the wikitext parser to decode.
 pub struct MapXMLEntities;
 impl ProcessingPass for MapXMLEntities {
     fn process(chunk: impl AsRef<str>) -> String {
@@ -19,6 +27,24 @@ impl ProcessingPass for MapXMLEntities {
     }
 }
 
+/// Checks whether character is a horizontal space.
+///
+/// Besides ASCII space this includes no-break space and typographic spaces
+/// (en/em/thin spaces, etc.), which are all treated as a regular space in
+/// output.
+fn is_space(c: char) -> bool {
+    matches!(
+        c,
+        ' ' | '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+    )
+}
+
+/// Collapses consecutive spaces and newlines.
+///
+/// All spaces matched by [`is_space`] are replaced with a single ASCII space.
+///
+/// Content of fenced (` ``` `) code blocks is left untouched so their
+/// indentation is preserved.
 pub struct CollapseWhitespace;
 impl ProcessingPass for CollapseWhitespace {
     fn process(chunk: impl AsRef<str>) -> String {
@@ -26,51 +52,158 @@ impl ProcessingPass for CollapseWhitespace {
 
         let mut newline_count = 0;
         let mut space_count = 0;
-        for c in chunk.as_ref().chars() {
-            match c {
-                '\n' => {
-                    newline_count += 1;
-                    space_count = 0;
-                    if newline_count > 2 {
-                        continue;
+        let mut in_fence = false;
+        for line in chunk.as_ref().split_inclusive('\n') {
+            let is_fence = line.trim_start_matches(is_space).starts_with("```");
+            if in_fence && !is_fence {
+                result.push_str(line);
+                newline_count = line.ends_with('\n') as usize;
+                space_count = 0;
+                continue;
+            }
+            if is_fence {
+                in_fence = !in_fence;
+            }
+
+            for c in line.chars() {
+                match c {
+                    '\n' => {
+                        newline_count += 1;
+                        space_count = 0;
+                        if newline_count > 2 {
+                            continue;
+                        }
                     }
-                }
-                ' ' | '\u{00A0}' => {
-                    if newline_count > 0 {
-                        // if on new line pretend we've seen space before so
-                        // starting spaces don't get printed.
+                    c if is_space(c) => {
+                        if newline_count > 0 {
+                            // if on new line pretend we've seen space before so
+                            // starting spaces don't get printed.
+                            space_count += 1;
+                        }
+                        newline_count = 0;
                         space_count += 1;
+                        if space_count == 1 {
+                            result.push(' ');
+                        }
+                        continue;
+                    }
+                    _ => {
+                        newline_count = 0;
+                        space_count = 0;
                     }
-                    newline_count = 0;
-                    space_count += 1;
-                    if space_count == 1 {
+                }
+                result.push(c);
+            }
+        }
+
+        result
+    }
+}
+
+/// Names of HTML tags removed by [`StripHtmlTags`].
+const HTML_TAGS: &[&str] = &[
+    "abbr", "b", "bdi", "big", "br", "center", "cite", "code", "del", "dfn", "div", "em", "font",
+    "i", "ins", "kbd", "mark", "p", "q", "rp", "rt", "ruby", "s", "samp", "small", "span",
+    "strike", "strong", "sub", "sup", "time", "tt", "u", "var", "wbr",
+];
+
+/// Returns length of an HTML tag with a [known](HTML_TAGS) name at the start
+/// of `text`, if there is one.
+fn html_tag_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('<')?;
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if !HTML_TAGS.iter().any(|it| it.eq_ignore_ascii_case(name)) {
+        return None;
+    }
+    let rest = &rest[name_len..];
+    if !rest.starts_with(['>', '/', ' ', '\t']) {
+        return None;
+    }
+    let end = rest.find(['>', '<', '\n'])?;
+    if rest.as_bytes()[end] != b'>' {
+        return None;
+    }
+    Some(text.len() - rest.len() + end + 1)
+}
+
+/// Removes inline HTML tags which remained in text, keeping their content.
+///
+/// Only tags with known names are removed, so `<` used in text (e.g. `a < b`)
+/// is left as is. Line breaks (`<br>`) are replaced with a space.
+pub struct StripHtmlTags;
+impl ProcessingPass for StripHtmlTags {
+    fn process(chunk: impl AsRef<str>) -> String {
+        let chunk = chunk.as_ref();
+        let mut result = String::with_capacity(chunk.len());
+
+        let mut rest = chunk;
+        while let Some(start) = rest.find('<') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match html_tag_len(rest) {
+                Some(len) => {
+                    let name = rest[1..].trim_start_matches('/');
+                    if name
+                        .get(..2)
+                        .is_some_and(|it| it.eq_ignore_ascii_case("br"))
+                    {
                         result.push(' ');
                     }
-                    continue;
+                    rest = &rest[len..];
                 }
-                _ => {
-                    newline_count = 0;
-                    space_count = 0;
+                None => {
+                    result.push('<');
+                    rest = &rest[1..];
                 }
             }
-            result.push(c);
         }
+        result.push_str(rest);
 
         result
     }
 }
 
+/// Checks whether character is a combining diacritical mark.
+///
+/// Only commonly used combining blocks are covered.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Keeps only words, separated by single spaces.
+///
+/// Apostrophes within words (contractions) and combining marks attached to
+/// letters are preserved. Hyphens are normalized to `-`.
 pub struct StripWords;
 impl ProcessingPass for StripWords {
     fn process(chunk: impl AsRef<str>) -> String {
         let mut result = String::with_capacity(chunk.as_ref().len());
 
         let mut delimited = true;
-        for c in chunk.as_ref().chars() {
+        let mut after_letter = false;
+        let mut chars = chunk.as_ref().chars().peekable();
+        while let Some(c) = chars.next() {
             match c {
                 c if c.is_alphabetic() => {
                     result.push(c);
                     delimited = false;
+                    after_letter = true;
+                    continue;
+                }
+                c if is_combining_mark(c) && after_letter => {
+                    result.push(c);
+                    continue;
                 }
                 c if c.is_whitespace() => {
                     if delimited {
@@ -79,6 +212,11 @@ impl ProcessingPass for StripWords {
                     result.push(' ');
                     delimited = true;
                 }
+                '\'' | '\u{2019}'
+                    if after_letter && chars.peek().is_some_and(|it| it.is_alphabetic()) =>
+                {
+                    result.push('\'');
+                }
                 '\u{002D}'
                 | '\u{058A}'
                 | '\u{1806}'
@@ -88,8 +226,145 @@ impl ProcessingPass for StripWords {
                 | '\u{FF0D}' => result.push('-'),
                 _ => {}
             }
+            after_letter = false;
         }
 
         result
     }
 }
+
+/// Splits text into space separated words and marks ones that end a
+/// sentence.
+///
+/// Sentence ends are guessed from common grammar rules: a word ending with
+/// `.` is considered last in a sentence if it's followed by an uppercase word,
+/// a newline or end of text. Its `.` is then stripped, while abbreviations
+/// keep theirs.
+///
+/// Examples of input that will be handled incorrectly:
+/// - `I was there with Dr. Abigail to see the show.` is treated as two
+///   sentences and `Dr.` will be stripped of punctuation.
+pub fn sentence_words(text: &str) -> impl Iterator<Item = (&str, bool)> {
+    // iterate over words with forward context
+    let words = text
+        .split(' ')
+        .map(|word| {
+            let is_uppercase = word
+                .chars()
+                .next()
+                .map(|it| it.is_uppercase())
+                .unwrap_or_default();
+            (Some(word.trim()), is_uppercase)
+        })
+        .chain(std::iter::once((None, true)));
+    words
+        .tuple_windows()
+        .map(|((word, _is_uppercase), (next_word, is_next_uppercase))| {
+            let word = unsafe {
+                // SAFETY: None is inserted only as next_word of last window.
+                word.unwrap_unchecked()
+            };
+            match word.strip_suffix('.') {
+                // name abbr.
+                Some(_) if word.len() == 2 => (word, false),
+                Some(stripped)
+                    if next_word
+                        .map(|it| it.starts_with('\n') || is_next_uppercase)
+                        .unwrap_or(true) =>
+                {
+                    // end of sentence
+                    (stripped, true)
+                }
+                _ => (word, false),
+            }
+        })
+}
+
+/// Counts sentences in text ending with `.`, `?` or `!`.
+///
+/// Ends of sentences with `.` are guessed by [`sentence_words`], so
+/// abbreviations aren't counted. Trailing text without punctuation isn't
+/// counted either.
+pub fn sentence_count(text: &str) -> usize {
+    text.lines()
+        .flat_map(sentence_words)
+        .filter(|(word, sentence_end)| *sentence_end || word.ends_with(['?', '!']))
+        .count()
+}
+
+/// Formats text as CoNLL-style tokens, one per line, with an empty line after
+/// every sentence.
+///
+/// Conversion is lossy: sentences are split by [`sentence_words`] and tokens
+/// are words kept by [`StripWords`], so punctuation, numbers and other
+/// symbols are dropped.
+pub struct ConllTokens;
+impl ProcessingPass for ConllTokens {
+    fn process(chunk: impl AsRef<str>) -> String {
+        let mut result = String::with_capacity(chunk.as_ref().len());
+
+        for line in chunk.as_ref().lines() {
+            let mut in_sentence = false;
+            for (word, sentence_end) in sentence_words(line) {
+                let words = StripWords::process(word);
+                for token in words
+                    .split(' ')
+                    .filter(|it| it.chars().any(char::is_alphabetic))
+                {
+                    result.push_str(token);
+                    result.push('\n');
+                    in_sentence = true;
+                }
+                if sentence_end && in_sentence {
+                    result.push('\n');
+                    in_sentence = false;
+                }
+            }
+            if in_sentence {
+                result.push('\n');
+            }
+        }
+
+        result
+    }
+}
+
+/// Unicode normalization form.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnicodeForm {
+    /// Leave text as is.
+    #[default]
+    None,
+    /// Canonical composition.
+    Nfc,
+    /// Compatibility composition; e.g. folds fullwidth Latin and ligatures.
+    Nfkc,
+    /// Canonical decomposition.
+    Nfd,
+    /// Compatibility decomposition.
+    Nfkd,
+}
+
+/// Applies Unicode normalization.
+///
+/// As a [`ProcessingPass`] text is normalized to NFC; other forms are applied
+/// with [`NormalizeUnicode::apply`].
+pub struct NormalizeUnicode;
+impl NormalizeUnicode {
+    pub fn apply(chunk: impl AsRef<str>, form: UnicodeForm) -> String {
+        let chunk = chunk.as_ref();
+        match form {
+            UnicodeForm::None => chunk.to_string(),
+            UnicodeForm::Nfc => chunk.nfc().collect(),
+            UnicodeForm::Nfkc => chunk.nfkc().collect(),
+            UnicodeForm::Nfd => chunk.nfd().collect(),
+            UnicodeForm::Nfkd => chunk.nfkd().collect(),
+        }
+    }
+}
+impl ProcessingPass for NormalizeUnicode {
+    fn process(chunk: impl AsRef<str>) -> String {
+        Self::apply(chunk, UnicodeForm::Nfc)
+    }
+}
@@ -0,0 +1,64 @@
+//! Post-processing of collected redirects.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io::{BufReader, BufWriter, Write as _},
+    path::Path,
+};
+
+use super::mediawiki::normalize_title;
+
+/// Maps every redirect title to the final target of its redirect chain.
+///
+/// Targets are matched against redirect titles after
+/// [normalization](normalize_title), ignoring section fragments; fragment of
+/// the last redirect in a chain is kept. Redirects that end up in a cycle are
+/// left out.
+///
+/// Returns resolved redirects and number of redirects left out due to cycles.
+pub fn resolve_chains(redirects: &HashMap<String, String>) -> (BTreeMap<String, String>, usize) {
+    let by_title: HashMap<String, &str> = redirects
+        .iter()
+        .map(|(title, target)| (normalize_title(title), target.as_str()))
+        .collect();
+
+    let mut result = BTreeMap::new();
+    let mut cycles = 0;
+    'redirects: for (title, target) in redirects {
+        let mut target = target.as_str();
+        let mut visited = HashSet::from([normalize_title(title)]);
+        loop {
+            let page = target.split('#').next().unwrap_or_default();
+            let key = normalize_title(page);
+            let next = match by_title.get(&key) {
+                Some(it) => *it,
+                None => break,
+            };
+            if !visited.insert(key) {
+                cycles += 1;
+                continue 'redirects;
+            }
+            target = next;
+        }
+        result.insert(title.clone(), target.to_string());
+    }
+
+    (result, cycles)
+}
+
+/// Reads a redirects JSON object from `source` and writes it with chains
+/// [resolved](resolve_chains) into `target`.
+pub fn write_resolved(source: impl AsRef<Path>, target: impl AsRef<Path>) -> std::io::Result<()> {
+    let redirects: HashMap<String, String> =
+        serde_json::from_reader(BufReader::new(File::open(source)?))?;
+    let (resolved, cycles) = resolve_chains(&redirects);
+    if cycles > 0 {
+        log::warn!("left out {} redirects that are part of a cycle", cycles);
+    }
+
+    let mut file = BufWriter::new(File::create(target)?);
+    serde_json::to_writer_pretty(&mut file, &resolved)?;
+    file.write_all(b"\n")?;
+    file.flush()
+}
@@ -0,0 +1,39 @@
+//! Extension point for custom page processing.
+
+use crate::dump_data::WikiPage;
+
+/// Custom consumer of pages, invoked by [`DataGenerator`] alongside built-in
+/// outputs.
+///
+/// Pages are passed after sampling, namespace and title filters are applied,
+/// before their content is parsed. Revisions of the page are included, unless
+/// older ones were already consumed while streaming page history.
+///
+/// Closures accepting a `&WikiPage` implement this trait, so simple hooks
+/// can be registered directly:
+///
+/// ```no_run
+/// # use wiki_extractor::output::DataGenerator;
+/// # fn register(generator: DataGenerator) -> DataGenerator {
+/// generator.with_page_sink(|page: &wiki_extractor::dump_data::WikiPage| {
+///     println!("{:?}", page.title.value());
+/// })
+/// # }
+/// ```
+///
+/// [`DataGenerator`]: super::DataGenerator
+pub trait PageSink {
+    fn process_page(&mut self, page: &WikiPage) -> std::io::Result<()>;
+
+    /// Called once all pages were processed.
+    fn finalize(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&WikiPage)> PageSink for F {
+    fn process_page(&mut self, page: &WikiPage) -> std::io::Result<()> {
+        self(page);
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Per-article text output.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use super::encoding::{OutputEncoding, TextWriter};
+
+/// Maximum length of generated file names in bytes.
+///
+/// Most filesystems limit names to 255 bytes; some room is left for the page
+/// id suffix and extension.
+const MAX_NAME_LEN: usize = 200;
+
+/// Names that can't be used as file names on Windows regardless of extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Converts page title into a file name that's valid on common filesystems.
+pub fn sanitize_file_name(title: &str) -> String {
+    let mut result: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if result.len() > MAX_NAME_LEN {
+        let mut end = MAX_NAME_LEN;
+        while !result.is_char_boundary(end) {
+            end -= 1;
+        }
+        result.truncate(end);
+    }
+
+    // Windows strips trailing dots and spaces
+    let trimmed_len = result.trim_end_matches(['.', ' ']).len();
+    result.truncate(trimmed_len);
+
+    if result.is_empty() {
+        result.push('_');
+    }
+    if RESERVED_NAMES
+        .iter()
+        .any(|it| it.eq_ignore_ascii_case(&result))
+    {
+        result.push('_');
+    }
+
+    result
+}
+
+/// FNV-1a hash; stable across builds unlike std hashers.
+fn fnv1a(value: &str) -> u32 {
+    value.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Writes text of each article into a separate file.
+///
+/// Files are spread over two levels of subdirectories (`ab/cd/Title.txt`)
+/// derived from the title hash, so that no directory ends up containing
+/// millions of files.
+pub struct SplitWriter {
+    root: PathBuf,
+    /// Extension of written files, without the leading dot.
+    extension: &'static str,
+    encoding: OutputEncoding,
+    substitute: char,
+    writer: Option<TextWriter<File>>,
+    /// Lowercase relative paths of written files; some filesystems are case
+    /// insensitive.
+    written: HashSet<String>,
+}
+
+impl SplitWriter {
+    pub fn new(root: impl AsRef<Path>, encoding: OutputEncoding, substitute: char) -> Self {
+        SplitWriter {
+            root: root.as_ref().to_path_buf(),
+            extension: "txt",
+            encoding,
+            substitute,
+            writer: None,
+            written: HashSet::new(),
+        }
+    }
+
+    /// Sets extension of written files (`txt` by default).
+    pub fn with_extension(mut self, extension: &'static str) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    fn file_path(&mut self, title: &str, id: Option<usize>) -> PathBuf {
+        let name = sanitize_file_name(title);
+        let hash = fnv1a(&name.to_lowercase());
+        let dir = format!("{:02x}/{:02x}", hash >> 24, (hash >> 16) & 0xff);
+
+        let ext = self.extension;
+        let mut file_name = format!("{}.{}", name, ext);
+        if !self
+            .written
+            .insert(format!("{}/{}", dir, file_name.to_lowercase()))
+        {
+            file_name = match id {
+                Some(id) => format!("{} ({}).{}", name, id, ext),
+                None => format!("{} ({}).{}", name, self.written.len(), ext),
+            };
+            self.written
+                .insert(format!("{}/{}", dir, file_name.to_lowercase()));
+        }
+
+        self.root.join(dir).join(file_name)
+    }
+
+    pub fn write(&mut self, title: &str, id: Option<usize>, text: &str) -> std::io::Result<()> {
+        let path = self.file_path(title, id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path)?;
+        match &mut self.writer {
+            Some(writer) => {
+                writer.replace_inner(file);
+            }
+            None => self.writer = Some(TextWriter::new(file, self.encoding, self.substitute)),
+        }
+        let writer = self.writer.as_mut().expect("writer not initialized");
+        writer.write_text(text)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,107 @@
+//! Template rendering rules.
+
+use std::{collections::HashMap, path::Path};
+
+use serde::Deserialize;
+
+/// Rules bundled with the program; see the file for format description.
+const BUNDLED_RULES: &str = include_str!("templates.toml");
+
+/// How a template is rendered into text.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemplateRule {
+    /// Template produces no text.
+    Drop,
+    /// First positional parameter.
+    FirstParam,
+    /// All positional parameters, separated by spaces.
+    Passthrough,
+    /// Named parameter, or positional one if name is a number (1-based).
+    Param(String),
+    /// Fixed text.
+    Text(String),
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    templates: HashMap<String, TemplateRule>,
+}
+
+/// Mapping of template names to their [`TemplateRule`]s.
+#[derive(Debug, Clone)]
+pub struct TemplateRules {
+    exact: HashMap<String, TemplateRule>,
+    /// Rules for names ending with `*`, longest prefix first.
+    prefixes: Vec<(String, TemplateRule)>,
+}
+
+impl Default for TemplateRules {
+    fn default() -> Self {
+        let mut rules = TemplateRules {
+            exact: HashMap::new(),
+            prefixes: Vec::new(),
+        };
+        rules
+            .extend_from_str(BUNDLED_RULES)
+            .expect("invalid bundled template rules");
+        rules
+    }
+}
+
+impl TemplateRules {
+    /// Rules rendering only listed templates (as their first positional
+    /// parameter) and dropping all others.
+    pub fn first_param_only(names: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        TemplateRules {
+            exact: names
+                .into_iter()
+                .map(|name| {
+                    let name = name.as_ref().trim().replace('_', " ").to_lowercase();
+                    (name, TemplateRule::FirstParam)
+                })
+                .collect(),
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Adds rules from TOML source, replacing existing rules with the same
+    /// name.
+    pub fn extend_from_str(&mut self, source: &str) -> Result<(), toml::de::Error> {
+        let file: RulesFile = toml::from_str(source)?;
+        for (name, rule) in file.templates {
+            let name = name.trim().replace('_', " ").to_lowercase();
+            match name.strip_suffix('*') {
+                Some(prefix) => {
+                    self.prefixes.retain(|(it, _)| it != prefix);
+                    self.prefixes.push((prefix.to_string(), rule));
+                }
+                None => {
+                    self.exact.insert(name, rule);
+                }
+            }
+        }
+        self.prefixes
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(())
+    }
+
+    /// Adds rules from a TOML file, overriding bundled ones.
+    pub fn extend_from_file(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        self.extend_from_str(&source)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+    }
+
+    /// Returns rule for a normalized (lowercase) template name.
+    pub fn get(&self, name: &str) -> &TemplateRule {
+        if let Some(rule) = self.exact.get(name) {
+            return rule;
+        }
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix.as_str()))
+            .map(|(_, rule)| rule)
+            .unwrap_or(&TemplateRule::Drop)
+    }
+}
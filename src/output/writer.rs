@@ -0,0 +1,454 @@
+//! Output files written from a dedicated thread.
+//!
+//! Page processing produces [`Record`]s which are sent over a channel in
+//! per-page batches tagged with a sequence number. The writer thread applies
+//! batches strictly in sequence order, so content of every output file is
+//! deterministic even if pages are processed out of order.
+//!
+//! At most [`page_buffer`](GeneratorOptions::page_buffer) batches are queued;
+//! further sends block until the writer catches up.
+//!
+//! Streamed outputs (text dumps and JSONL files) are opened through an
+//! [`OutputTarget`], so they can be written somewhere other than files in the
+//! output directory.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use super::{
+    encoding::TextWriter,
+    json_container::JsonContainer,
+    options::{GeneratorOptions, TextFormat, TextOptions},
+    split::SplitWriter,
+};
+
+/// Destination of a streamed output.
+pub type OutputStream = Box<dyn Write + Send>;
+
+/// Opens streams for outputs, by their file name (e.g. `wiki_sentences.txt`).
+///
+/// Only outputs that are written sequentially are opened through the target.
+/// JSON containers, per-article files, dictionary and manifest are still
+/// written to the output directory.
+///
+/// Closures returning an [`OutputStream`] for a name implement this trait.
+pub trait OutputTarget: Send {
+    fn open(&mut self, name: &str) -> std::io::Result<OutputStream>;
+}
+
+impl<F: FnMut(&str) -> std::io::Result<OutputStream> + Send> OutputTarget for F {
+    fn open(&mut self, name: &str) -> std::io::Result<OutputStream> {
+        self(name)
+    }
+}
+
+/// Creates outputs as files in a directory.
+#[derive(Debug, Clone)]
+pub struct DirectoryTarget(pub PathBuf);
+
+impl OutputTarget for DirectoryTarget {
+    fn open(&mut self, name: &str) -> std::io::Result<OutputStream> {
+        Ok(Box::new(File::create(self.0.join(name))?))
+    }
+}
+
+/// JSONL output file.
+#[derive(Debug, Clone, Copy)]
+pub enum JsonlFile {
+    Comments,
+    Disambiguation,
+    Sections,
+    /// Page text; shard index is ignored if output isn't sharded.
+    Pages(usize),
+    Paragraphs,
+    Categories,
+    Links,
+    History,
+    /// Pages that failed processing.
+    Errors,
+}
+
+/// Single write to an output file.
+#[derive(Debug)]
+pub enum Record {
+    /// Serialized `"title": "target"` redirect entry.
+    Redirect(Vec<u8>),
+    /// Serialized JSON line, including the trailing newline.
+    Jsonl(JsonlFile, Vec<u8>),
+    /// Text appended to the text dump.
+    Text(Arc<String>),
+    /// Tokens appended to the CoNLL token dump.
+    Tokens(String),
+    /// Text of a single article for split output.
+    Article {
+        title: String,
+        id: Option<usize>,
+        text: Arc<String>,
+    },
+    /// Markdown document of a single article.
+    MarkdownFile {
+        title: String,
+        id: Option<usize>,
+        document: String,
+    },
+    /// Flush all output files.
+    Flush,
+}
+
+/// Output files, owned by the writer thread.
+struct OutputFiles {
+    metadata: Option<JsonContainer>,
+    text_dump: Option<TextWriter<OutputStream>>,
+    conll: Option<TextWriter<OutputStream>>,
+    split_output: Option<SplitWriter>,
+    markdown_files: Option<SplitWriter>,
+    redirects: Option<JsonContainer>,
+    comments: Option<OutputStream>,
+    disambiguation: Option<OutputStream>,
+    sections: Option<OutputStream>,
+    /// Page output shards; empty if disabled.
+    pages: Vec<OutputStream>,
+    paragraphs: Option<OutputStream>,
+    categories: Option<OutputStream>,
+    links: Option<OutputStream>,
+    history: Option<OutputStream>,
+    errors: Option<OutputStream>,
+}
+
+impl OutputFiles {
+    fn create(
+        output_path: &Path,
+        target: &mut dyn OutputTarget,
+        generator_options: &GeneratorOptions,
+        text_options: &TextOptions,
+    ) -> std::io::Result<Self> {
+        let crash_safe = generator_options.crash_safe_json;
+
+        let metadata = if generator_options.metadata {
+            let metadata = output_path.join("wiki_page_info.json");
+            Some(JsonContainer::array(metadata, crash_safe)?)
+        } else {
+            None
+        };
+
+        let text_dump = if generator_options.text {
+            Some(TextWriter::new(
+                target.open("wiki_sentences.txt")?,
+                text_options.output_encoding,
+                text_options.unmappable_char,
+            ))
+        } else {
+            None
+        };
+
+        let conll = if generator_options.format == TextFormat::Conll {
+            Some(TextWriter::new(
+                target.open("wiki_tokens.conll")?,
+                text_options.output_encoding,
+                text_options.unmappable_char,
+            ))
+        } else {
+            None
+        };
+
+        let split_output = if generator_options.split_output {
+            Some(SplitWriter::new(
+                output_path.join("articles"),
+                text_options.output_encoding,
+                text_options.unmappable_char,
+            ))
+        } else {
+            None
+        };
+
+        let markdown_files = if generator_options.format == TextFormat::MarkdownFiles {
+            let writer = SplitWriter::new(
+                output_path.join("markdown"),
+                text_options.output_encoding,
+                text_options.unmappable_char,
+            );
+            Some(writer.with_extension("md"))
+        } else {
+            None
+        };
+
+        let pages = match (generator_options.pages, generator_options.shards) {
+            (false, _) => Vec::new(),
+            (true, 0 | 1) => vec![target.open("wiki_pages.jsonl")?],
+            (true, shards) => (0..shards)
+                .map(|i| target.open(&format!("wiki_pages_{:05}.jsonl", i)))
+                .collect::<std::io::Result<_>>()?,
+        };
+
+        let redirects = if generator_options.redirects {
+            let redirects = output_path.join("redirects.json");
+            Some(JsonContainer::object(redirects, crash_safe)?)
+        } else {
+            None
+        };
+
+        let mut jsonl = |enabled: bool, name: &str| -> std::io::Result<Option<OutputStream>> {
+            match enabled {
+                true => Ok(Some(target.open(name)?)),
+                false => Ok(None),
+            }
+        };
+
+        Ok(OutputFiles {
+            metadata,
+            text_dump,
+            conll,
+            split_output,
+            markdown_files,
+            redirects,
+            comments: jsonl(generator_options.comments, "comments.jsonl")?,
+            disambiguation: jsonl(generator_options.disambiguation, "disambiguation.jsonl")?,
+            sections: jsonl(generator_options.sections, "sections.jsonl")?,
+            pages,
+            paragraphs: jsonl(
+                generator_options.format == TextFormat::Paragraphs,
+                "wiki_paragraphs.jsonl",
+            )?,
+            categories: jsonl(generator_options.categories, "categories.jsonl")?,
+            links: jsonl(generator_options.links, "links.jsonl")?,
+            history: jsonl(generator_options.history, "revisions.jsonl")?,
+            errors: match &generator_options.errors_file {
+                Some(path) => Some(Box::new(File::create(path)?)),
+                None => None,
+            },
+        })
+    }
+
+    fn apply(&mut self, record: Record) -> std::io::Result<()> {
+        match record {
+            Record::Redirect(entry) => {
+                if let Some(redirects) = &mut self.redirects {
+                    redirects.write_entry(&entry)?;
+                }
+            }
+            Record::Jsonl(target, line) => {
+                let file = match target {
+                    JsonlFile::Comments => self.comments.as_mut(),
+                    JsonlFile::Disambiguation => self.disambiguation.as_mut(),
+                    JsonlFile::Sections => self.sections.as_mut(),
+                    JsonlFile::Pages(shard) => {
+                        let shards = self.pages.len().max(1);
+                        self.pages.get_mut(shard % shards)
+                    }
+                    JsonlFile::Paragraphs => self.paragraphs.as_mut(),
+                    JsonlFile::Categories => self.categories.as_mut(),
+                    JsonlFile::Links => self.links.as_mut(),
+                    JsonlFile::History => self.history.as_mut(),
+                    JsonlFile::Errors => self.errors.as_mut(),
+                };
+                if let Some(file) = file {
+                    file.write_all(&line)?;
+                }
+            }
+            Record::Text(text) => {
+                if let Some(text_dump) = &mut self.text_dump {
+                    text_dump.write_text(&text)?;
+                }
+            }
+            Record::Tokens(tokens) => {
+                if let Some(conll) = &mut self.conll {
+                    conll.write_text(&tokens)?;
+                }
+            }
+            Record::Article { title, id, text } => {
+                if let Some(split_output) = &mut self.split_output {
+                    split_output.write(&title, id, &text)?;
+                }
+            }
+            Record::MarkdownFile {
+                title,
+                id,
+                document,
+            } => {
+                if let Some(markdown_files) = &mut self.markdown_files {
+                    markdown_files.write(&title, id, &document)?;
+                }
+            }
+            Record::Flush => self.flush()?,
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(text_dump) = &mut self.text_dump {
+            text_dump.flush()?;
+        }
+        if let Some(conll) = &mut self.conll {
+            conll.flush()?;
+        }
+        if let Some(redirects) = &mut self.redirects {
+            redirects.flush()?;
+        }
+        for file in [
+            &mut self.comments,
+            &mut self.disambiguation,
+            &mut self.sections,
+            &mut self.paragraphs,
+            &mut self.categories,
+            &mut self.links,
+            &mut self.history,
+            &mut self.errors,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(&mut self.pages)
+        {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<()> {
+        self.flush()?;
+        if let Some(redirects) = self.redirects {
+            redirects.finish()?;
+        }
+        if let Some(metadata) = self.metadata {
+            metadata.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Records produced by processing of a single page.
+struct Batch {
+    sequence: u64,
+    records: Vec<Record>,
+}
+
+/// Applies received batches in sequence order until all senders are dropped.
+fn write_loop(mut files: OutputFiles, receiver: Receiver<Batch>) -> std::io::Result<()> {
+    let mut next = 0;
+    let mut pending = BTreeMap::new();
+
+    for batch in receiver {
+        pending.insert(batch.sequence, batch.records);
+        while let Some(records) = pending.remove(&next) {
+            for record in records {
+                if let Err(err) = files.apply(record) {
+                    // close what was written so far, so that it stays valid
+                    if let Err(err) = files.finish() {
+                        log::error!("unable to close output files: {}", err);
+                    }
+                    return Err(err);
+                }
+            }
+            next += 1;
+        }
+    }
+
+    if let Some(sequence) = pending.keys().next() {
+        let message = format!("batch {} never arrived, output is incomplete", next);
+        log::error!("{}; next available is {}", message, sequence);
+        return Err(std::io::Error::new(ErrorKind::UnexpectedEof, message));
+    }
+
+    files.finish()
+}
+
+/// Handle to the writer thread.
+///
+/// Every sequence number returned by [`next_sequence`](Self::next_sequence)
+/// must be [`send`](Self::send) exactly once (even with no records), as later
+/// batches are held back until all earlier ones are written.
+pub struct OutputWriter {
+    sender: Option<SyncSender<Batch>>,
+    thread: Option<JoinHandle<std::io::Result<()>>>,
+    sequence: u64,
+}
+
+impl OutputWriter {
+    /// Creates output files and starts the writer thread.
+    pub fn spawn(
+        output_path: impl AsRef<Path>,
+        generator_options: &GeneratorOptions,
+        text_options: &TextOptions,
+    ) -> std::io::Result<Self> {
+        let target = DirectoryTarget(output_path.as_ref().to_path_buf());
+        Self::spawn_with_target(output_path, target, generator_options, text_options)
+    }
+
+    /// Creates outputs and starts the writer thread.
+    ///
+    /// Streamed outputs are opened with `target`, others are created in
+    /// `output_path`.
+    pub fn spawn_with_target(
+        output_path: impl AsRef<Path>,
+        mut target: impl OutputTarget,
+        generator_options: &GeneratorOptions,
+        text_options: &TextOptions,
+    ) -> std::io::Result<Self> {
+        let files = OutputFiles::create(
+            output_path.as_ref(),
+            &mut target,
+            generator_options,
+            text_options,
+        )?;
+        // bounded, so that processing and reading of input are throttled if
+        // writing can't keep up
+        let (sender, receiver) = mpsc::sync_channel(generator_options.page_buffer);
+        let thread = std::thread::Builder::new()
+            .name("output-writer".to_string())
+            .spawn(move || write_loop(files, receiver))?;
+
+        Ok(OutputWriter {
+            sender: Some(sender),
+            thread: Some(thread),
+            sequence: 0,
+        })
+    }
+
+    /// Reserves sequence number for the next batch.
+    pub fn next_sequence(&mut self) -> u64 {
+        let result = self.sequence;
+        self.sequence += 1;
+        result
+    }
+
+    /// Queues records to be written after all batches with lower sequence
+    /// numbers.
+    pub fn send(&mut self, sequence: u64, records: Vec<Record>) -> std::io::Result<()> {
+        let sent = match &self.sender {
+            Some(sender) => sender.send(Batch { sequence, records }).is_ok(),
+            None => false,
+        };
+        if sent {
+            return Ok(());
+        }
+        // receiver is only dropped if writing failed
+        self.join()?;
+        Err(std::io::Error::new(
+            ErrorKind::BrokenPipe,
+            "output writer stopped",
+        ))
+    }
+
+    fn join(&mut self) -> std::io::Result<()> {
+        self.sender = None;
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("output writer panicked"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes remaining batches and closes output files.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.join()
+    }
+}
@@ -6,11 +6,18 @@ use std::{
 
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::input::data::{FileDescriptor, FileName};
 
 /// A global pointer address of the download tracker.
 static TRACKER: AtomicUsize = AtomicUsize::new(0);
+/// Sets the global download tracker used by the progress bar.
+///
+/// # Safety
+///
+/// Provided `tracker` must not be moved or dropped while it's in use by the
+/// logger, i.e. until the end of the program.
 pub unsafe fn set_tracker_global(tracker: &DownloadTracker) {
     TRACKER
         .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |it| {
@@ -21,6 +28,12 @@ pub unsafe fn set_tracker_global(tracker: &DownloadTracker) {
         })
         .expect("can't set download tracker global");
 }
+/// Returns the global download tracker, if one was set.
+///
+/// # Safety
+///
+/// Returned reference aliases the tracker passed to [`set_tracker_global`],
+/// which may be concurrently modified by its owner.
 pub unsafe fn get_tracker_global() -> Option<&'static DownloadTracker> {
     let addr = TRACKER.load(Ordering::SeqCst);
     if addr == 0 {
@@ -29,6 +42,19 @@ pub unsafe fn get_tracker_global() -> Option<&'static DownloadTracker> {
     Some((addr as *const DownloadTracker).as_ref().unwrap_unchecked())
 }
 
+/// Error produced when a tracker can't continue from a checkpoint.
+#[derive(Debug, Error)]
+pub enum ResumeError {
+    #[error("checkpoint was made for a different set of dump files")]
+    FilesMismatch,
+    #[error("offset {offset} is outside of file #{file_index} (size: {size})")]
+    InvalidPosition {
+        file_index: usize,
+        offset: usize,
+        size: usize,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadTracker {
     start_time: DateTime<Utc>,
@@ -38,6 +64,9 @@ pub struct DownloadTracker {
     current_file: usize,
     passive_offset: usize,
     current_offset: usize,
+    /// Bytes processed by an earlier run; excluded from ETA estimation.
+    #[serde(default)]
+    resumed_offset: usize,
 }
 
 impl DownloadTracker {
@@ -51,11 +80,60 @@ impl DownloadTracker {
             current_file: 0,
             passive_offset: 0,
             current_offset: 0,
+            resumed_offset: 0,
+        }
+    }
+
+    /// Creates a tracker continuing from a `checkpoint` saved by an earlier
+    /// run.
+    ///
+    /// Returns an error if the checkpoint was made for different files.
+    pub fn resume(
+        files: &BTreeMap<FileName, FileDescriptor>,
+        checkpoint: &DownloadTracker,
+    ) -> Result<DownloadTracker, ResumeError> {
+        let mut result = DownloadTracker::new(files);
+        if result.file_names != checkpoint.file_names || result.file_sizes != checkpoint.file_sizes
+        {
+            return Err(ResumeError::FilesMismatch);
+        }
+        result.fast_forward(checkpoint.current_file, checkpoint.current_offset)?;
+        Ok(result)
+    }
+
+    /// Moves to `offset` bytes into the file at `file_index`.
+    ///
+    /// Skipped bytes are counted as downloaded, but not towards the download
+    /// rate used for ETA.
+    pub fn fast_forward(&mut self, file_index: usize, offset: usize) -> Result<(), ResumeError> {
+        let size = self.file_sizes.get(file_index).copied().unwrap_or_default();
+        let past_end = file_index > self.file_sizes.len();
+        if past_end || offset > size {
+            return Err(ResumeError::InvalidPosition {
+                file_index,
+                offset,
+                size,
+            });
         }
+
+        self.current_file = file_index;
+        self.passive_offset = self.file_sizes[..file_index].iter().sum();
+        self.current_offset = offset;
+        self.resumed_offset = self.downloaded();
+        Ok(())
     }
 
-    pub fn set_current_position(&mut self, buffer_position: usize) {
-        self.current_offset = buffer_position;
+    /// Index of the current file and offset within it.
+    pub fn position(&self) -> (usize, usize) {
+        (self.current_file, self.current_offset)
+    }
+
+    /// Updates position in the current file.
+    ///
+    /// `source_position` is the number of (compressed) bytes consumed from the
+    /// source, which is what listed file sizes measure.
+    pub fn set_current_position(&mut self, source_position: usize) {
+        self.current_offset = source_position;
     }
 
     pub fn total_size(&self) -> usize {
@@ -67,6 +145,10 @@ impl DownloadTracker {
     }
 
     pub fn download_percent(&self) -> f32 {
+        if self.total_size == 0 {
+            // size isn't known in advance, e.g. for API input
+            return 0.;
+        }
         self.downloaded() as f32 / self.total_size as f32
     }
 
@@ -85,8 +167,9 @@ impl DownloadTracker {
     }
 
     pub fn eta(&self) -> usize {
-        (self.elapsed_time().num_seconds() as f64 / self.download_percent() as f64
-            * (1. - self.download_percent()) as f64
-            + 1.) as usize
+        let downloaded_now = self.downloaded() - self.resumed_offset.min(self.downloaded());
+        let remaining = self.total_size - self.downloaded().min(self.total_size);
+        (self.elapsed_time().num_seconds() as f64 / downloaded_now as f64 * remaining as f64 + 1.)
+            as usize
     }
 }
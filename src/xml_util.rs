@@ -1,7 +1,7 @@
 //! Utilities for handling partial/streaming XML data.
 
 use std::{
-    alloc::Layout,
+    borrow::Cow,
     collections::HashMap,
     fmt::Display,
     ops::{Deref, DerefMut},
@@ -17,7 +17,7 @@ pub mod error {
     use std::fmt::Display;
     use std::{convert::Infallible, str::Utf8Error};
 
-    use quick_xml::events::attributes::AttrError;
+    use quick_xml::{escape::EscapeError, events::attributes::AttrError};
     use thiserror::Error;
 
     #[derive(Debug)]
@@ -103,7 +103,19 @@ pub mod error {
         BadCloseableState(super::CloseableState),
         #[error("can't handle event: {reason}")]
         UnhandledEvent { reason: &'static str },
+        #[error("value tag '{key}' doesn't support nested tags")]
+        NestedValueTag { key: &'static str },
+        #[error("invalid character reference: {0}")]
+        BadEscape(
+            #[from]
+            #[source]
+            EscapeError,
+        ),
+        #[error("more than {limit} pages are waiting to be processed")]
+        PageLimit { limit: usize },
 
+        #[error("document is declared as '{encoding}' encoded, but only UTF-8 is supported")]
+        UnsupportedEncoding { encoding: String },
         #[error("invalid stream character/encoding: {0}")]
         EncodingError(
             #[from]
@@ -289,34 +301,13 @@ impl<C: Closeable + FromAttributes> CloseableConstructor for C {
 pub enum Handle<D: HandleEvent + FromAttributes, const KEY: &'static str> {
     #[default]
     Unopened,
-    Open(*mut D),
+    Open(Box<D>),
     Closed(D),
 }
 
-impl<D: HandleEvent + FromAttributes, const KEY: &'static str> Drop for Handle<D, KEY> {
-    fn drop(&mut self) {
-        if let Handle::Open(value) = self {
-            if !value.is_null() {
-                unsafe {
-                    // SAFETY: value is uniquely owned by NestedHandle.
-                    let mut ptr = std::ptr::null_mut();
-                    std::mem::swap(value, &mut ptr);
-                    std::alloc::dealloc(ptr as *mut u8, Layout::new::<D>());
-                }
-            }
-        }
-    }
-}
-
 impl<D: HandleEvent + FromAttributes, const KEY: &'static str> Handle<D, KEY> {
-    pub fn new_open(mut data: D) -> Self {
-        Handle::Open({
-            let addr = std::ptr::addr_of_mut!(data);
-            // Not a leak because we'll destroy pointee in Drop or make it
-            // owned with NestedHandle::finish.
-            std::mem::forget(data);
-            addr
-        })
+    pub fn new_open(data: D) -> Self {
+        Handle::Open(Box::new(data))
     }
 
     pub fn value(&self) -> Option<&D> {
@@ -328,10 +319,7 @@ impl<D: HandleEvent + FromAttributes, const KEY: &'static str> Handle<D, KEY> {
 
     pub fn partial_value(&self) -> Option<&D> {
         match self {
-            Handle::Open(value) => unsafe {
-                // SAFETY: HandleOpen always returns an initialized type
-                Some(value.as_ref().expect("invalid Closeable state"))
-            },
+            Handle::Open(value) => Some(value),
             Handle::Closed(value) => Some(value),
             _ => None,
         }
@@ -351,20 +339,16 @@ impl<D: HandleEvent + FromAttributes, const KEY: &'static str> Closeable for Han
     }
 
     fn close(&mut self) -> ParseResult<()> {
-        match self {
+        match std::mem::take(self) {
             Handle::Open(data) => {
-                *self = Handle::Closed(unsafe {
-                    // SAFETY: This function just changes the enum variant.
-                    // Basically we're doing a swap of MaybeUninit value,
-                    // only without Copy bound.
-                    let mut value = std::ptr::null_mut();
-                    std::mem::swap(data, &mut value);
-                    value.read()
-                })
+                *self = Handle::Closed(*data);
+                Ok(())
+            }
+            other => {
+                *self = other;
+                Err(ParseError::BadCloseableState(self.close_state()))
             }
-            _ => return Err(ParseError::BadCloseableState(self.close_state())),
         }
-        Ok(())
     }
 }
 
@@ -380,11 +364,7 @@ impl<D: HandleEvent + FromAttributes, const KEY: &'static str> HandleEvent for H
             (handle, XMLEvent::End(end)) if handle.is_open() && end.name().0 == KEY.as_bytes() => {
                 handle.close()
             }
-            (Handle::Open(data), other) => unsafe {
-                data.as_mut()
-                    .expect("invalid Closeable variant value")
-                    .handle_event(other)
-            },
+            (Handle::Open(data), other) => data.handle_event(other),
             (other, _) => Err(ParseError::BadCloseableState(other.close_state())),
         }
     }
@@ -588,6 +568,19 @@ impl<D: ParseValue, const KEY: &'static str> ValueTag<D, KEY> {
     }
 }
 
+impl<const KEY: &'static str> ValueTag<String, KEY> {
+    /// Replaces character and entity references in the closed value with
+    /// characters they stand for.
+    pub fn unescape(&mut self) -> ParseResult<()> {
+        if let ValueTag::Closed { value, .. } = self {
+            if let Cow::Owned(unescaped) = quick_xml::escape::unescape(value)? {
+                *value = unescaped;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<D: ParseValue, const KEY: &'static str> FromAttributes for ValueTag<D, KEY> {
     fn from_attributes(attributes: AttributeMap<'_>) -> ParseResult<Self> {
         Ok(ValueTag::Open {
@@ -617,10 +610,10 @@ impl<D: ParseValue, const KEY: &'static str> HandleEvent for ValueTag<D, KEY> {
             },
             XMLEvent::Comment(_) => {}
             XMLEvent::Eof => return Err(ParseError::BadCloseableState(self.close_state())),
-            other => panic!(
-                "value tag '{}' doesn't support nested tag: {:?}",
-                KEY, other
-            ),
+            other => {
+                log::debug!("value tag '{}' got nested tag: {:?}", KEY, other);
+                return Err(ParseError::NestedValueTag { key: KEY });
+            }
         }
         Ok(())
     }
@@ -0,0 +1,72 @@
+use std::{
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use wiki_extractor::input::cache::CachePath;
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wx-cache-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Reader returning at most `chunk` bytes per read.
+struct Chunked<R: Read> {
+    inner: R,
+    chunk: usize,
+}
+
+impl<R: Read> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.chunk);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+/// Reads `body` through a cache writer for a file listed with `size`.
+fn download(dir: &Path, size: usize, body: &[u8], chunk: usize) -> Vec<u8> {
+    let cache = CachePath::new(dir.join("dump.xml"), size, None);
+    let (partial, offset) = cache.open_partial().unwrap();
+    let remote = Chunked {
+        inner: Cursor::new(body.to_vec()),
+        chunk,
+    };
+    let mut result = Vec::new();
+    cache
+        .writer(partial, offset, remote)
+        .unwrap()
+        .read_to_end(&mut result)
+        .unwrap();
+    result
+}
+
+#[test]
+fn complete_download_is_cached() {
+    let dir = cache_dir("complete");
+    assert_eq!(download(&dir, 8, b"<page/>\n", 3), b"<page/>\n");
+    assert_eq!(std::fs::read(dir.join("dump.xml")).unwrap(), b"<page/>\n");
+    assert!(!dir.join("dump.xml.part").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn longer_body_is_not_cached() {
+    let dir = cache_dir("longer");
+    let body = b"<page/>\n<page/>\n";
+    assert_eq!(download(&dir, 8, body, body.len()), body);
+    assert!(!dir.join("dump.xml").exists());
+    assert!(!dir.join("dump.xml.part").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bytes_after_listed_size_are_not_appended() {
+    let dir = cache_dir("appended");
+    let body = b"<page/>\n<page/>\n";
+    // listed size is reached exactly at the end of a read
+    assert_eq!(download(&dir, 8, body, 4), body);
+    assert_eq!(std::fs::read(dir.join("dump.xml")).unwrap(), b"<page/>\n");
+    assert!(!dir.join("dump.xml.part").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,39 @@
+//! Helpers shared by tests that run the extractor binary.
+
+use std::path::PathBuf;
+
+/// Path of the built binary.
+///
+/// Newer Cargo versions provide it only in test environment, not at compile
+/// time.
+pub fn binary() -> PathBuf {
+    option_env!("CARGO_BIN_EXE_wiki-extractor")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("CARGO_BIN_EXE_wiki-extractor").map(PathBuf::from))
+        .expect("binary path not provided by cargo")
+}
+
+/// Writes a dump with given pages of wikitext to a temporary file.
+#[allow(dead_code)]
+pub fn write_dump(name: &str, pages: &[(&str, &str)]) -> PathBuf {
+    let pages: String = pages
+        .iter()
+        .enumerate()
+        .map(|(index, (title, text))| {
+            let id = index + 1;
+            format!(
+                "<page><title>{title}</title><ns>0</ns><id>{id}</id><revision><id>{id}</id>\
+                 <model>wikitext</model><format>text/x-wiki</format>\
+                 <text xml:space=\"preserve\">{text}</text></revision></page>"
+            )
+        })
+        .collect();
+    let input = std::env::temp_dir().join(format!("wx-{}-{}.xml", name, std::process::id()));
+    let xmlns = "http://www.mediawiki.org/xml/export-0.11/";
+    std::fs::write(
+        &input,
+        format!("<mediawiki xmlns=\"{xmlns}\">{pages}</mediawiki>"),
+    )
+    .unwrap();
+    input
+}
@@ -0,0 +1,73 @@
+//! Runs of the extractor binary with options loaded from a config file.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+mod common;
+
+use common::{binary, write_dump};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("wx-config-{}-{}", name, std::process::id()))
+}
+
+/// Extracts text from `input` with options from `config` file content.
+fn extract(name: &str, config: &str, input: &Path) -> (Output, PathBuf) {
+    let config_path = temp_path(name).with_extension("toml");
+    std::fs::write(&config_path, config).unwrap();
+    let output = temp_path(name);
+    let result = Command::new(binary())
+        .args(["-T", "--config"])
+        .arg(&config_path)
+        .arg("-o")
+        .arg(&output)
+        .args(["local", input.to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .output()
+        .expect("unable to run wiki-extractor");
+    std::fs::remove_file(config_path).unwrap();
+    (result, output)
+}
+
+#[test]
+fn config_file_options() {
+    let input = write_dump("config-options", &[("Page", "Lead.\n== Heading ==\nText.")]);
+    for (name, config) in [
+        ("long", "include-headings = true"),
+        ("field", "include_headings = true"),
+        ("nested", "[text]\ninclude_headings = true"),
+    ] {
+        let (result, output) = extract(name, config, &input);
+        assert!(result.status.success(), "{}: {:?}", name, result);
+        let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+        assert!(text.contains("Heading"), "{}: {}", name, text);
+        std::fs::remove_dir_all(output).unwrap();
+    }
+    std::fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn config_file_errors() {
+    let input = write_dump("config-errors", &[("Page", "Text.")]);
+    for (name, config, message) in [
+        (
+            "unknown",
+            "include-headlines = true",
+            "unknown config option 'include-headlines'",
+        ),
+        (
+            "invalid",
+            "output-encoding = \"klingon\"",
+            "invalid value 'klingon' for '--output-encoding",
+        ),
+    ] {
+        let (result, output) = extract(name, config, &input);
+        assert!(!result.status.success(), "{}: {:?}", name, result);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains(message), "{}: {}", name, stderr);
+        let _ = std::fs::remove_dir_all(output);
+    }
+    std::fs::remove_file(input).unwrap();
+}
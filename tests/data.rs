@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use url::Url;
+use wiki_extractor::input::{
+    data::{FileName, RemoteParams, DEFAULT_URL_TEMPLATE},
+    io::DEFAULT_CHUNK_RETRIES,
+};
+
+fn file_name(name: &str) -> FileName {
+    FileName::try_from(Path::new(name)).unwrap()
+}
+
+#[test]
+fn plain_xml() {
+    let name = file_name("a.xml");
+    assert_eq!(name.extensions(), ["xml"]);
+    assert_eq!(name.ext(), Some("xml"));
+    assert_eq!(name.full_ext(), Some("xml"));
+}
+
+#[test]
+fn bzip2_xml() {
+    let name = file_name("a.xml.bz2");
+    assert_eq!(name.extensions(), ["xml", "bz2"]);
+    assert_eq!(name.ext(), Some("bz2"));
+    assert_eq!(name.full_ext(), Some("xml.bz2"));
+}
+
+#[test]
+fn gzip_xml() {
+    let name = file_name("a.xml.gz");
+    assert_eq!(name.extensions(), ["xml", "gz"]);
+    assert_eq!(name.ext(), Some("gz"));
+    assert_eq!(name.full_ext(), Some("xml.gz"));
+}
+
+#[test]
+fn no_extension() {
+    let name = file_name("noext");
+    assert!(name.extensions().is_empty());
+    assert_eq!(name.ext(), None);
+    assert_eq!(name.full_ext(), None);
+}
+
+#[test]
+fn url_query() {
+    let name = file_name("a.xml.bz2?download=1");
+    assert_eq!(name.extensions(), ["xml", "bz2"]);
+    assert_eq!(name.ext(), Some("bz2"));
+}
+
+#[test]
+fn remote_file_url_template() {
+    let mut params = RemoteParams {
+        base: Url::parse("https://mirror.example/dumps/").unwrap(),
+        mirrors: Vec::new(),
+        version: "20240101".to_string(),
+        language: "de".to_string(),
+        since_version: None,
+        url_template: DEFAULT_URL_TEMPLATE.to_string(),
+        max_retries: DEFAULT_CHUNK_RETRIES,
+        cache_dir: None,
+    };
+    assert_eq!(
+        params.file_url("dumpstatus.json"),
+        "https://mirror.example/dumps/dewiki/20240101/dumpstatus.json"
+    );
+    params.url_template = "{base}/{lang}/{version}/{file}".to_string();
+    assert_eq!(
+        params.file_url("dumpstatus.json"),
+        "https://mirror.example/dumps/de/20240101/dumpstatus.json"
+    );
+}
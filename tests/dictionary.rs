@@ -0,0 +1,44 @@
+use wiki_extractor::output::{generator::Dictionary, options::DictionarySort};
+
+fn write_dictionary(name: &str, counts: Option<DictionarySort>, texts: &[&str]) -> String {
+    let path = std::env::temp_dir().join(format!("wx-dictionary-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut dictionary = Dictionary::with_counts(&path, counts);
+    futures::executor::block_on(async {
+        for text in texts {
+            dictionary.push(text).await;
+        }
+    });
+    dictionary.write().unwrap();
+    let result = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    result
+}
+
+#[test]
+fn counts_by_frequency() {
+    let result = write_dictionary(
+        "frequency",
+        Some(DictionarySort::Frequency),
+        &["the cat saw the dog", "the dog"],
+    );
+    assert_eq!(result, "the\t3\ndog\t2\ncat\t1\nsaw\t1\n");
+}
+
+#[test]
+fn counts_lexical() {
+    let result = write_dictionary(
+        "lexical",
+        Some(DictionarySort::Lexical),
+        &["the cat saw the dog", "the dog"],
+    );
+    assert_eq!(result, "cat\t1\ndog\t2\nsaw\t1\nthe\t3\n");
+}
+
+#[test]
+fn plain_words() {
+    let result = write_dictionary("plain", None, &["the cat saw the dog"]);
+    let mut words: Vec<&str> = result.lines().collect();
+    words.sort();
+    assert_eq!(words, ["cat", "dog", "saw", "the"]);
+}
@@ -0,0 +1,350 @@
+use quick_xml::{events::Event as XMLEvent, Reader as XMLReader};
+use wiki_extractor::{
+    dump_data::{ContributorInfo, DocumentContext},
+    xml_util::{HandleEvent, ParseError, ParseResult},
+};
+
+fn parse(data: &[u8]) -> ParseResult<DocumentContext> {
+    parse_into(data, DocumentContext::with_file_name("test.xml"))
+}
+
+fn parse_into(data: &[u8], mut document: DocumentContext) -> ParseResult<DocumentContext> {
+    let mut xml_reader = XMLReader::from_reader(data);
+    let mut stream_buffer = Vec::new();
+
+    loop {
+        let event = xml_reader
+            .read_event_into(&mut stream_buffer)
+            .expect("malformed test XML");
+        let is_eof = matches!(event, XMLEvent::Eof);
+        document.handle_event(event)?;
+        if is_eof {
+            break;
+        }
+        stream_buffer.clear();
+    }
+
+    Ok(document)
+}
+
+#[test]
+fn revision_contributors() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>Anarchism</title>
+    <id>12</id>
+    <revision>
+      <id>1</id>
+      <contributor>
+        <username>Alice</username>
+        <id>42</id>
+      </contributor>
+    </revision>
+    <revision>
+      <id>2</id>
+      <contributor deleted="deleted" />
+    </revision>
+    <revision>
+      <id>3</id>
+      <contributor>
+        <ip>192.0.2.1</ip>
+      </contributor>
+    </revision>
+    <revision>
+      <id>4</id>
+    </revision>
+  </page>
+</mediawiki>"#;
+    let document = parse(data).expect("contributors not handled");
+    let page = &document.pages[0];
+    assert_eq!(page.id.value(), Some(&12));
+    assert_eq!(page.revisions.len(), 4);
+
+    let known = &page.revisions[0].contributor;
+    assert!(known.is_known());
+    assert_eq!(known.username.value().map(String::as_str), Some("Alice"));
+    assert_eq!(known.id.value(), Some(&42));
+    assert_eq!(
+        known.info(),
+        ContributorInfo::User {
+            name: "Alice",
+            id: Some(42)
+        }
+    );
+
+    let deleted = &page.revisions[1].contributor;
+    assert!(deleted.deleted);
+    assert!(!deleted.is_known());
+    assert_eq!(deleted.username.value(), None);
+    assert_eq!(deleted.info(), ContributorInfo::Suppressed);
+
+    assert_eq!(
+        page.revisions[2].contributor.info(),
+        ContributorInfo::Ip {
+            address: "192.0.2.1"
+        }
+    );
+    assert_eq!(
+        page.revisions[3].contributor.info(),
+        ContributorInfo::Absent
+    );
+}
+
+#[test]
+fn revision_comment_entities() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>Anarchism</title>
+    <id>12</id>
+    <revision>
+      <id>1</id>
+      <comment>/* History */ fixed &quot;A &amp; B&quot; &lt;ref&gt;</comment>
+    </revision>
+  </page>
+</mediawiki>"#;
+    let document = parse(data).expect("comment not handled");
+    // references are resolved when comments are written out
+    assert_eq!(
+        document.pages[0].revisions[0]
+            .comment
+            .value()
+            .map(String::as_str),
+        Some("/* History */ fixed &quot;A &amp; B&quot; &lt;ref&gt;")
+    );
+}
+
+#[test]
+fn leading_bom_and_prolog() {
+    let document = parse(include_bytes!("fixtures/bom.xml")).expect("BOM not handled");
+    assert_eq!(document.pages.len(), 1);
+    assert_eq!(
+        document.pages[0].title.value().map(String::as_str),
+        Some("Anarchism")
+    );
+}
+
+#[test]
+fn wrong_root_element() {
+    let data = b"<?xml version=\"1.0\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\"></feed>";
+    assert!(parse(data).is_err());
+}
+
+#[test]
+fn non_utf8_declaration() {
+    let data = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n\
+                 <mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\"></mediawiki>";
+    match parse(data) {
+        Err(ParseError::UnsupportedEncoding { encoding }) => assert_eq!(encoding, "ISO-8859-1"),
+        other => panic!("expected unsupported encoding error, got {:?}", other.err()),
+    }
+
+    let data = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\"></mediawiki>";
+    assert!(parse(data).is_ok());
+}
+
+#[test]
+fn empty_root_element() {
+    let data = b"\xef\xbb\xbf<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\"/>";
+    assert!(parse(data).is_err());
+}
+
+#[test]
+fn page_restrictions_and_sha1() {
+    let document =
+        parse(include_bytes!("fixtures/page_elements.xml")).expect("page elements not handled");
+    let page = &document.pages[0];
+    assert!(page.closed);
+    assert_eq!(page.id.value(), Some(&15580374));
+    assert_eq!(
+        page.restrictions.value().map(String::as_str),
+        Some("edit=sysop:move=sysop")
+    );
+    assert_eq!(
+        page.sha1.value().map(String::as_str),
+        Some("mbj2tdg7tyt6vgbf2m6wr1t5dp4t3m3")
+    );
+    assert_eq!(page.revisions.len(), 1);
+    assert_eq!(
+        page.revisions[0].sha1.value().map(String::as_str),
+        Some("4ub7dkwyl6gtpwcz0c0jnnmmn5zjb5f")
+    );
+}
+
+#[test]
+fn escaped_titles() {
+    let document =
+        parse(include_bytes!("fixtures/escaped_title.xml")).expect("escaped titles not handled");
+    assert_eq!(
+        document.pages[0].title.value().map(String::as_str),
+        Some("Fish & chips")
+    );
+    assert_eq!(
+        document.pages[1].title.value().map(String::as_str),
+        Some("\"Fish\" <dish>")
+    );
+    assert_eq!(document.pages[1].redirect.as_deref(), Some("Fish & chips"));
+}
+
+#[test]
+fn self_closing_page() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page/>
+  <page>
+    <title>Anarchism</title>
+    <id>12</id>
+  </page>
+</mediawiki>"#;
+    let document = parse(data).expect("self-closing page not handled");
+    assert_eq!(document.pages.len(), 1);
+    assert_eq!(document.pages[0].id.value(), Some(&12));
+}
+
+#[test]
+fn split_namespace_prefix() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <siteinfo>
+    <namespaces>
+      <namespace key="0" case="first-letter" />
+      <namespace key="1" case="first-letter">Talk</namespace>
+      <namespace key="3" case="first-letter">User talk</namespace>
+    </namespaces>
+  </siteinfo>
+</mediawiki>"#;
+    let document = parse(data).expect("siteinfo not handled");
+    let site_info = &document.site_info;
+    assert_eq!(site_info.split_title("Anarchism"), ("", "Anarchism"));
+    assert_eq!(
+        site_info.split_title("Talk:Anarchism"),
+        ("Talk", "Anarchism")
+    );
+    assert_eq!(
+        site_info.split_title("User_talk:Example"),
+        ("User_talk", "Example")
+    );
+    assert_eq!(site_info.split_title("Talk:"), ("Talk", ""));
+    assert_eq!(
+        site_info.split_title("Star Wars: Episode I"),
+        ("", "Star Wars: Episode I")
+    );
+}
+
+#[test]
+fn page_limit() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page><title>A</title></page>
+  <page><title>B</title></page>
+  <page><title>C</title></page>
+</mediawiki>"#;
+    let document = DocumentContext::with_file_name("test.xml").with_page_limit(3);
+    assert_eq!(parse_into(data, document).unwrap().pages.len(), 3);
+    let document = DocumentContext::with_file_name("test.xml").with_page_limit(2);
+    assert!(matches!(
+        parse_into(data, document),
+        Err(ParseError::PageLimit { limit: 2 })
+    ));
+}
+
+#[test]
+fn skip_filtered_namespace() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>Talk:A</title>
+    <ns>1</ns>
+    <id>1</id>
+    <revision><id>2</id><text>Discussion</text></revision>
+  </page>
+  <page>
+    <title>B</title>
+    <ns>0</ns>
+    <id>3</id>
+    <revision><id>4</id><text>Article</text></revision>
+  </page>
+</mediawiki>"#;
+    let mut document = DocumentContext::with_file_name("test.xml");
+    document.namespace_filter = Some([0].into_iter().collect());
+    let document = parse_into(data, document).expect("filtered page not handled");
+    let (talk, article) = (&document.pages[0], &document.pages[1]);
+    assert!(talk.closed && talk.skip_content);
+    assert!(talk.revisions.is_empty());
+    assert_eq!(talk.id.value(), None);
+    assert!(article.closed && !article.skip_content);
+    assert_eq!(article.revisions.len(), 1);
+}
+
+#[test]
+fn skip_revision_text() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>A</title>
+    <ns>0</ns>
+    <id>1</id>
+    <revision><id>2</id><text>Old &amp; long</text></revision>
+    <revision><id>3</id><text bytes="0" /><sha1>abc</sha1></revision>
+  </page>
+</mediawiki>"#;
+    let mut document = DocumentContext::with_file_name("test.xml");
+    document.skip_text = true;
+    let document = parse_into(data, document).expect("skipped text not handled");
+    let page = &document.pages[0];
+    assert!(page.closed);
+    assert_eq!(page.revisions.len(), 2);
+    assert_eq!(page.revisions[0].id.value(), Some(&2));
+    assert_eq!(page.revisions[0].text.value(), None);
+    assert_eq!(page.revisions[1].id.value(), Some(&3));
+    assert_eq!(
+        page.revisions[1].sha1.value().map(String::as_str),
+        Some("abc")
+    );
+}
+
+#[test]
+fn skip_revisions() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>A</title>
+    <ns>0</ns>
+    <id>1</id>
+    <redirect title="B" />
+    <revision><id>2</id><text>#REDIRECT [[B]]</text></revision>
+  </page>
+</mediawiki>"#;
+    let mut document = DocumentContext::with_file_name("test.xml");
+    document.skip_revisions = true;
+    let document = parse_into(data, document).expect("skipped revisions not handled");
+    let page = &document.pages[0];
+    assert!(page.closed);
+    assert_eq!(page.id.value(), Some(&1));
+    assert_eq!(page.redirect.as_deref(), Some("B"));
+    assert!(page.revisions.is_empty());
+}
+
+#[test]
+fn pages_keep_document_order() {
+    let data = br#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page><title>A</title></page>
+  <page><title>B</title></page>
+  <page><title>C</title></page>
+</mediawiki>"#;
+    let mut document = parse(data).unwrap();
+    let mut titles = Vec::new();
+    while let Some(page) = document.pages.pop_front() {
+        titles.extend(page.title.value().cloned());
+    }
+    assert_eq!(titles, ["A", "B", "C"]);
+}
+
+#[test]
+fn negative_namespaces() {
+    let document = parse(include_bytes!("fixtures/special_namespaces.xml"))
+        .expect("negative namespaces not handled");
+    let site_info = &document.site_info;
+    assert_eq!(site_info.namespace_key("-1"), Some(-1));
+    assert_eq!(site_info.namespace_key("media"), Some(-2));
+    assert_eq!(
+        site_info.split_title("Special:Random"),
+        ("Special", "Random")
+    );
+    let namespaces: Vec<_> = document.pages.iter().map(|it| it.ns.value()).collect();
+    assert_eq!(namespaces, [Some(&-1), Some(&-2), Some(&0)]);
+}
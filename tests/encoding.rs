@@ -0,0 +1,32 @@
+use wiki_extractor::output::encoding::{OutputEncoding, TextWriter};
+
+fn encode(text: &str, encoding: OutputEncoding, substitute: char) -> Vec<u8> {
+    let mut writer = TextWriter::new(Vec::new(), encoding, substitute);
+    writer.write_text(text).unwrap();
+    writer.replace_inner(Vec::new())
+}
+
+#[test]
+fn utf8_is_written_as_is() {
+    let text = "Zürich – Ω 中 😀";
+    assert_eq!(encode(text, OutputEncoding::Utf8, '?'), text.as_bytes());
+}
+
+#[test]
+fn latin1_transcoding() {
+    assert_eq!(
+        encode("Zürich – € ñ", OutputEncoding::Latin1, '?'),
+        b"Z\xfcrich \x96 \x80 \xf1"
+    );
+}
+
+#[test]
+fn latin1_unmappable_characters() {
+    assert_eq!(
+        encode("a Ω b 中😀 c", OutputEncoding::Latin1, '?'),
+        b"a ? b ?? c"
+    );
+    assert_eq!(encode("Ω é", OutputEncoding::Latin1, '¿'), b"\xbf \xe9");
+    // substitute has to be representable itself
+    assert_eq!(encode("Ω é", OutputEncoding::Latin1, '中'), b"? \xe9");
+}
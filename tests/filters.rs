@@ -0,0 +1,57 @@
+//! Runs of the extractor binary with page filters.
+
+use std::{path::PathBuf, process::Command};
+
+mod common;
+
+use common::binary;
+
+fn fixture() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/escaped_title.xml")
+}
+
+#[test]
+fn title_list_matches_escaped_titles() {
+    let name = format!("wx-title-list-{}", std::process::id());
+    let list = std::env::temp_dir().join(&name).with_extension("txt");
+    std::fs::write(&list, "Fish & chips\n").unwrap();
+    let output = std::env::temp_dir().join(&name);
+
+    let status = Command::new(binary())
+        .args(["-T", "--title-list"])
+        .arg(&list)
+        .arg("-o")
+        .arg(&output)
+        .args(["local", fixture().to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .status()
+        .expect("unable to run wiki-extractor");
+    assert!(status.success(), "wiki-extractor exited with {}", status);
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("Fish & chips is a dish."), "{}", text);
+    assert!(!text.contains("Chips are fried."), "{}", text);
+
+    std::fs::remove_dir_all(output).unwrap();
+    std::fs::remove_file(list).unwrap();
+}
+
+#[test]
+fn resume_from_escaped_title() {
+    let output = std::env::temp_dir().join(format!("wx-resume-{}", std::process::id()));
+
+    let status = Command::new(binary())
+        .args(["-T", "--resume-from-title", "\"Fish\" <dish>", "-o"])
+        .arg(&output)
+        .args(["local", fixture().to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .status()
+        .expect("unable to run wiki-extractor");
+    assert!(status.success(), "wiki-extractor exited with {}", status);
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(!text.contains("Fish & chips is a dish."), "{}", text);
+    assert!(text.contains("Chips are fried."), "{}", text);
+
+    std::fs::remove_dir_all(output).unwrap();
+}
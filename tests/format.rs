@@ -0,0 +1,44 @@
+use wiki_extractor::format::{format_bar, left_pad, percent_pad};
+
+/// Width of a formatted bar, without color escape sequences.
+fn bar_width(percent: f32, max_width: usize) -> usize {
+    let mut out = Vec::new();
+    format_bar(&mut out, percent, max_width).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    text.replace("\x1b[92m", "")
+        .replace("\x1b[39m", "")
+        .chars()
+        .count()
+}
+
+#[test]
+fn left_pad_shorter() {
+    assert_eq!(left_pad("12", 4), "  12");
+}
+
+#[test]
+fn left_pad_overflow() {
+    assert_eq!(left_pad("12345", 4), "12345");
+    assert_eq!(left_pad("1234", 4), "1234");
+    assert_eq!(left_pad("", 0), "");
+}
+
+#[test]
+fn percent_pad_overflow() {
+    assert_eq!(percent_pad(0.5, 2), " 50.00%");
+    assert_eq!(percent_pad(123.0, 0), "12300%");
+}
+
+#[test]
+fn bar_tiny_widths() {
+    for max_width in 0..4 {
+        for percent in [0., 0.3, 0.5, 0.99, 1., 1.5, -0.5, f32::NAN] {
+            assert_eq!(
+                bar_width(percent, max_width),
+                max_width,
+                "percent: {}",
+                percent
+            );
+        }
+    }
+}
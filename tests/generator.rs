@@ -0,0 +1,168 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use clap::Parser as _;
+use quick_xml::{events::Event as XMLEvent, Reader as XMLReader};
+use wiki_extractor::{
+    dump_data::DocumentContext,
+    input::data::SourceLocation,
+    output::{
+        manifest::Manifest,
+        options::{GeneratorOptions, TextOptions},
+        writer::OutputStream,
+        DataGenerator,
+    },
+    xml_util::HandleEvent as _,
+};
+
+/// In-memory output shared with the test.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Parses whole XML document at `path`.
+fn read_document(path: &str) -> DocumentContext {
+    let mut document = DocumentContext::with_file_name(path);
+    let data = std::fs::read(path).unwrap();
+    let mut xml_reader = XMLReader::from_reader(data.as_slice());
+    let mut stream_buffer = Vec::new();
+    loop {
+        let event = xml_reader.read_event_into(&mut stream_buffer).unwrap();
+        let is_eof = matches!(event, XMLEvent::Eof);
+        document.handle_event(event).unwrap();
+        if is_eof {
+            break;
+        }
+        stream_buffer.clear();
+    }
+    document
+}
+
+#[test]
+fn custom_output_target() {
+    let input = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/single_page.xml"
+    );
+    let output = std::env::temp_dir().join(format!("wx-target-{}", std::process::id()));
+    let text = SharedBuffer::default();
+
+    let target = {
+        let text = text.clone();
+        move |name: &str| -> std::io::Result<OutputStream> {
+            assert_eq!(name, "wiki_sentences.txt");
+            Ok(Box::new(text.clone()))
+        }
+    };
+    let manifest = Manifest::new(
+        SourceLocation::Local { path: input.into() },
+        serde_json::Value::Null,
+    );
+    let mut generator = DataGenerator::with_target(
+        &output,
+        target,
+        GeneratorOptions::parse_from(["test", "-T"]),
+        TextOptions::parse_from(["test"]),
+        manifest,
+    )
+    .unwrap();
+
+    let mut document = read_document(input);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(generator.process_document(&mut document))
+        .unwrap();
+    generator.finalize().unwrap();
+
+    let text = String::from_utf8(text.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(text.trim(), "The only page.");
+    assert!(!output.join("wiki_sentences.txt").exists());
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+/// Output which records how many bytes were written at each flush.
+#[derive(Clone, Default)]
+struct FlushLog {
+    written: Arc<Mutex<usize>>,
+    flushes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Write for FlushLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        *self.written.lock().unwrap() += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let written = *self.written.lock().unwrap();
+        self.flushes.lock().unwrap().push(written);
+        Ok(())
+    }
+}
+
+/// Extracts text from the test dump with given `--flush-interval` and
+/// returns sizes of the text dump at each flush.
+fn text_flushes(interval: &str) -> Vec<usize> {
+    let input = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/dump/enwiki-test-pages-articles.xml"
+    );
+    let output = std::env::temp_dir().join(format!("wx-flush-{}-{}", interval, std::process::id()));
+    let log = FlushLog::default();
+    let target = {
+        let log = log.clone();
+        move |_: &str| -> std::io::Result<OutputStream> { Ok(Box::new(log.clone())) }
+    };
+    let manifest = Manifest::new(
+        SourceLocation::Local { path: input.into() },
+        serde_json::Value::Null,
+    );
+    let mut generator = DataGenerator::with_target(
+        &output,
+        target,
+        GeneratorOptions::parse_from(["test", "-T", "--flush-interval", interval]),
+        TextOptions::parse_from(["test"]),
+        manifest,
+    )
+    .unwrap();
+
+    let mut document = read_document(input);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(generator.process_document(&mut document))
+        .unwrap();
+    generator.finalize().unwrap();
+    std::fs::remove_dir_all(output).unwrap();
+
+    let written = *log.written.lock().unwrap();
+    let flushes = log.flushes.lock().unwrap().clone();
+    assert!(written > 0);
+    // text dump is always flushed once extraction finishes
+    assert_eq!(flushes.last(), Some(&written), "{:?}", flushes);
+    flushes
+}
+
+#[test]
+fn flush_interval() {
+    // flushed after every processed page, so text of earlier pages can be
+    // read before extraction finishes
+    let every_page = text_flushes("1");
+    let written = every_page[every_page.len() - 1];
+    assert!(
+        every_page.iter().any(|it| *it > 0 && *it < written),
+        "{:?}",
+        every_page
+    );
+
+    let at_end = text_flushes("0");
+    assert!(at_end.len() < every_page.len(), "{:?}", at_end);
+}
@@ -0,0 +1,159 @@
+//! Runs of the extractor binary in history mode.
+
+use std::process::Command;
+
+use serde_json::Value;
+
+mod common;
+
+use common::binary;
+
+const HISTORY_DUMP: &str = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>A &amp; B</title>
+    <ns>0</ns>
+    <id>1</id>
+    <revision>
+      <id>10</id>
+      <contributor deleted="deleted" />
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Fish &amp; chips.</text>
+    </revision>
+    <revision>
+      <id>11</id>
+      <parentid>10</parentid>
+      <contributor>
+        <username>Alice</username>
+        <id>42</id>
+      </contributor>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Fish &amp; chips &lt;3.</text>
+    </revision>
+  </page>
+  <page>
+    <title>Filtered</title>
+    <ns>1</ns>
+    <id>2</id>
+    <revision>
+      <id>20</id>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Talk.</text>
+    </revision>
+    <revision>
+      <id>21</id>
+      <parentid>20</parentid>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">More talk.</text>
+    </revision>
+  </page>
+</mediawiki>"#;
+
+#[test]
+fn history_revisions() {
+    let name = format!("wx-history-{}", std::process::id());
+    let input = std::env::temp_dir().join(&name).with_extension("xml");
+    std::fs::write(&input, HISTORY_DUMP).unwrap();
+    let output = std::env::temp_dir().join(&name);
+
+    let status = Command::new(binary())
+        .args(["--history", "--history-text", "--namespaces", "0", "-o"])
+        .arg(&output)
+        .args(["local", input.to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .status()
+        .expect("unable to run wiki-extractor");
+    assert!(status.success(), "wiki-extractor exited with {}", status);
+
+    let revisions = std::fs::read_to_string(output.join("revisions.jsonl")).unwrap();
+    let revisions: Vec<Value> = revisions
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    // revisions of pages excluded by filters are left out
+    assert_eq!(revisions.len(), 2, "{:?}", revisions);
+
+    assert_eq!(revisions[0]["rev_id"], 10);
+    assert_eq!(revisions[0]["text"], "Fish & chips.");
+    assert_eq!(revisions[0]["text_length"], "Fish & chips.".len());
+    assert_eq!(revisions[0]["contributor"]["kind"], "suppressed");
+
+    assert_eq!(revisions[1]["rev_id"], 11);
+    assert_eq!(revisions[1]["text"], "Fish & chips <3.");
+    assert_eq!(revisions[1]["contributor"]["kind"], "user");
+    assert_eq!(revisions[1]["contributor"]["name"], "Alice");
+
+    std::fs::remove_dir_all(output).unwrap();
+    std::fs::remove_file(input).unwrap();
+}
+
+const COMMENTS_DUMP: &str = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/">
+  <page>
+    <title>Fish</title>
+    <ns>0</ns>
+    <id>1</id>
+    <revision>
+      <id>10</id>
+      <comment>fixed &amp;bogus; &unknown; entity</comment>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Fish are animals.</text>
+    </revision>
+    <revision>
+      <id>11</id>
+      <parentid>10</parentid>
+      <comment deleted="deleted" />
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Fish are animals.</text>
+    </revision>
+    <revision>
+      <id>12</id>
+      <parentid>11</parentid>
+      <comment>/* Habitat */ &quot;A &amp; B&quot;</comment>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text xml:space="preserve">Fish live in water.</text>
+    </revision>
+  </page>
+</mediawiki>"#;
+
+#[test]
+fn revision_comments() {
+    let name = format!("wx-comments-{}", std::process::id());
+    let input = std::env::temp_dir().join(&name).with_extension("xml");
+    std::fs::write(&input, COMMENTS_DUMP).unwrap();
+    let output = std::env::temp_dir().join(&name);
+
+    let status = Command::new(binary())
+        .args(["-T", "--history", "--collect-comments", "-o"])
+        .arg(&output)
+        .args(["local", input.to_str().unwrap()])
+        .env("RUST_LOG", "error")
+        .status()
+        .expect("unable to run wiki-extractor");
+    assert!(status.success(), "wiki-extractor exited with {}", status);
+
+    // malformed entity in a comment doesn't affect the page
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("Fish live in water."), "{:?}", text);
+
+    let comments = std::fs::read_to_string(output.join("comments.jsonl")).unwrap();
+    let comments: Vec<Value> = comments
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    // suppressed comments (deleted="deleted") are left out
+    assert_eq!(comments.len(), 2, "{:?}", comments);
+    assert_eq!(comments[0]["rev_id"], 10);
+    // comments that can't be unescaped are written as they are
+    assert_eq!(comments[0]["comment"], "fixed &amp;bogus; &unknown; entity");
+    assert_eq!(comments[1]["rev_id"], 12);
+    assert_eq!(comments[1]["comment"], r#"/* Habitat */ "A & B""#);
+
+    std::fs::remove_dir_all(output).unwrap();
+    std::fs::remove_file(input).unwrap();
+}
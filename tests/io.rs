@@ -0,0 +1,169 @@
+use std::{
+    io::{Cursor, ErrorKind, Read as _, Write as _},
+    time::Duration,
+};
+
+use wiki_extractor::input::io::{
+    retry_delay, Compression, CompressionAdapter, DecompressionError, DocumentStream, SourceAdapter,
+};
+
+fn compressed_fixture() -> Vec<u8> {
+    std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/dump/enwiki-test-pages-articles.xml.bz2"
+    ))
+    .unwrap()
+}
+
+fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    CompressionAdapter::new_bzip2(data).read_to_end(&mut result)?;
+    Ok(result)
+}
+
+#[test]
+fn truncated_bzip2_stream() {
+    let data = compressed_fixture();
+    let err = decompress(&data[..data.len() / 2]).unwrap_err();
+    assert!(matches!(
+        DecompressionError::find(&err),
+        Some(DecompressionError::Truncated)
+    ));
+}
+
+#[test]
+fn corrupt_bzip2_stream() {
+    let mut data = compressed_fixture();
+    let middle = data.len() / 2;
+    data[middle] ^= 0xff;
+    let err = decompress(&data).unwrap_err();
+    assert!(matches!(
+        DecompressionError::find(&err),
+        Some(DecompressionError::Corrupt(_))
+    ));
+}
+
+#[test]
+fn complete_bzip2_stream() {
+    assert!(decompress(&compressed_fixture()).is_ok());
+}
+
+#[test]
+fn sniff_compression() {
+    assert_eq!(
+        Compression::sniff(&compressed_fixture()),
+        Some(Compression::Bzip2)
+    );
+    assert_eq!(
+        Compression::sniff(b"\x1f\x8b\x08\x00"),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(
+        Compression::sniff(b"\xfd7zXZ\x00\x00"),
+        Some(Compression::Xz)
+    );
+    assert_eq!(
+        Compression::sniff(b"\x28\xb5\x2f\xfd\x00"),
+        Some(Compression::Zstd)
+    );
+    assert_eq!(Compression::sniff(b"<mediawiki>"), Some(Compression::None));
+    assert_eq!(
+        Compression::sniff(b"\xef\xbb\xbf<?xml"),
+        Some(Compression::None)
+    );
+    assert_eq!(Compression::sniff(b"BZ"), None);
+    assert_eq!(Compression::sniff(b""), None);
+}
+
+/// Compresses `data` with each supported compression other than bzip2.
+fn compressed_variants(data: &[u8]) -> Vec<(Compression, Vec<u8>)> {
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gzip.write_all(data).unwrap();
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+    xz.write_all(data).unwrap();
+    vec![
+        (Compression::Gzip, gzip.finish().unwrap()),
+        (Compression::Xz, xz.finish().unwrap()),
+        (Compression::Zstd, zstd::encode_all(data, 1).unwrap()),
+    ]
+}
+
+#[test]
+fn decompress_other_formats() {
+    let xml = decompress(&compressed_fixture()).unwrap();
+    for (compression, data) in compressed_variants(&xml) {
+        assert_eq!(Compression::sniff(&data), Some(compression));
+        let mut result = Vec::new();
+        CompressionAdapter::new(compression, &data[..])
+            .unwrap()
+            .read_to_end(&mut result)
+            .unwrap();
+        assert_eq!(result, xml, "{}", compression);
+    }
+}
+
+#[test]
+fn truncated_other_formats() {
+    let xml = decompress(&compressed_fixture()).unwrap();
+    for (compression, data) in compressed_variants(&xml) {
+        let mut result = Vec::new();
+        let err = CompressionAdapter::new(compression, &data[..data.len() / 2])
+            .unwrap()
+            .read_to_end(&mut result)
+            .unwrap_err();
+        assert!(
+            matches!(
+                DecompressionError::find(&err),
+                Some(DecompressionError::Truncated)
+            ),
+            "{}: {}",
+            compression,
+            err
+        );
+    }
+}
+
+#[test]
+fn seek_local_source() {
+    let source = SourceAdapter::new_seekable(Cursor::new(b"<a/><b/>".to_vec()));
+    let mut stream = DocumentStream::new(CompressionAdapter::new_passthrough(source));
+    let mut first = [0; 4];
+    stream.read_exact(&mut first).unwrap();
+
+    let mut stream = stream.seek_source(4).unwrap();
+    assert_eq!(stream.source_position(), 4);
+    let mut rest = String::new();
+    stream.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "<b/>");
+}
+
+#[test]
+fn seek_compressed_source() {
+    let data = compressed_fixture();
+    let mut doubled = data.clone();
+    doubled.extend_from_slice(&data);
+    let source = SourceAdapter::new_seekable(Cursor::new(doubled));
+    let stream = DocumentStream::new(CompressionAdapter::new_bzip2(source));
+
+    let mut second = Vec::new();
+    let mut stream = stream.seek_source(data.len() as u64).unwrap();
+    stream.read_to_end(&mut second).unwrap();
+    assert_eq!(second, decompress(&data).unwrap());
+}
+
+#[test]
+fn seek_unseekable_source() {
+    let source = SourceAdapter::new_local(&b"<a/>"[..]);
+    let stream = DocumentStream::new(CompressionAdapter::new_passthrough(source));
+    let err = stream.seek_source(2).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn retry_delay_is_capped() {
+    assert_eq!(retry_delay(1), Duration::from_secs(1));
+    assert_eq!(retry_delay(3), Duration::from_secs(4));
+    assert_eq!(retry_delay(7), Duration::from_secs(60));
+    assert_eq!(retry_delay(40), Duration::from_secs(60));
+    assert_eq!(retry_delay(u32::MAX), Duration::from_secs(60));
+}
@@ -0,0 +1,19 @@
+use wiki_extractor::output::json_container::JsonContainer;
+
+#[test]
+fn crash_safe_object_is_always_valid() {
+    let path = std::env::temp_dir().join(format!("wx-container-{}.json", std::process::id()));
+
+    let mut container = JsonContainer::object(&path, true).unwrap();
+    let read = || serde_json::from_slice::<serde_json::Value>(&std::fs::read(&path).unwrap());
+    assert_eq!(read().unwrap(), serde_json::json!({}));
+
+    container.write_entry(br#"  "a": "b""#).unwrap();
+    assert_eq!(read().unwrap(), serde_json::json!({"a": "b"}));
+    container.write_entry(br#"  "c": "d""#).unwrap();
+    assert_eq!(read().unwrap(), serde_json::json!({"a": "b", "c": "d"}));
+
+    container.finish().unwrap();
+    assert_eq!(read().unwrap(), serde_json::json!({"a": "b", "c": "d"}));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,421 @@
+use clap::Parser as _;
+use wiki_extractor::output::{mediawiki, options::TextOptions, templates::TemplateRules};
+
+fn to_text_with(wikitext: &str, args: &[&str]) -> String {
+    let options = TextOptions::parse_from(std::iter::once("test").chain(args.iter().copied()));
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    mediawiki::nodes_to_text(&parsed.nodes, &options)
+}
+
+fn to_text(wikitext: &str) -> String {
+    to_text_with(wikitext, &[])
+}
+
+#[test]
+fn image_captions_are_excluded_by_default() {
+    assert_eq!(to_text("Text.[[File:X.jpg|thumb|caption]]").trim(), "Text.");
+}
+
+#[test]
+fn image_captions() {
+    assert_eq!(
+        to_text_with(
+            "[[File:X.jpg|thumb|caption]]",
+            &["--include-image-captions"]
+        )
+        .trim(),
+        "caption"
+    );
+}
+
+#[test]
+fn flatten_lists() {
+    assert_eq!(
+        to_text_with("* a\n* b.\n* c", &["--flatten-lists"]).trim(),
+        "a; b; c."
+    );
+}
+
+#[test]
+fn flatten_nested_lists() {
+    assert_eq!(
+        to_text_with("* a\n** b\n** c\n* d", &["--flatten-lists"]).trim(),
+        "a; b; c; d."
+    );
+    assert_eq!(
+        to_text_with("# a\n#* b\n# c", &["--flatten-lists"]).trim(),
+        "a; b; c."
+    );
+}
+
+#[test]
+fn nbsp_entity_between_words() {
+    assert_eq!(
+        to_text("Scale from 0&nbsp;to&nbsp;1."),
+        "Scale from 0 to 1."
+    );
+}
+
+const POEM: &str = "Intro.\n<poem>\nRoses are '''red''',\n  [[Viola|violets]] are blue.\n\nSugar is sweet.\n</poem>";
+
+#[test]
+fn poems_are_excluded_by_default() {
+    assert_eq!(to_text(POEM).trim(), "Intro.");
+}
+
+#[test]
+fn poem_keeps_line_breaks() {
+    assert_eq!(
+        to_text_with(POEM, &["--include-poems"]).trim(),
+        "Intro.\n\nRoses are red,\nviolets are blue.\n\nSugar is sweet."
+    );
+}
+
+#[test]
+fn poem_markdown_hard_breaks() {
+    assert_eq!(
+        to_text_with(POEM, &["--include-poems", "--markdown"]).trim(),
+        "Intro.\n\nRoses are **red**,\\\nviolets are blue.\n\nSugar is sweet."
+    );
+}
+
+const CATEGORIZED: &str = "Text.{{DEFAULTSORT:Smith, John}}\n[[Category:Living people|Smith]]\n[[category:English_writers]]\n[[Category:Living people]]";
+
+#[test]
+fn default_sort_is_not_text() {
+    assert_eq!(to_text(CATEGORIZED).trim(), "Text.");
+    assert_eq!(
+        to_text_with(CATEGORIZED, &["--keep-raw-templates"]).trim(),
+        "Text."
+    );
+}
+
+#[test]
+fn default_sort_key() {
+    let parsed = mediawiki::configuration(&[]).parse(CATEGORIZED).unwrap();
+    assert_eq!(
+        mediawiki::default_sort(&parsed.nodes).as_deref(),
+        Some("Smith, John")
+    );
+}
+
+#[test]
+fn categories_without_sort_keys() {
+    let parsed = mediawiki::configuration(&[]).parse(CATEGORIZED).unwrap();
+    assert_eq!(
+        mediawiki::categories(&parsed.nodes),
+        ["Living people", "English writers"]
+    );
+}
+
+#[test]
+fn normalize_option() {
+    assert_eq!(to_text("\u{ff37}iki."), "\u{ff37}iki.");
+    assert_eq!(
+        to_text_with("\u{ff37}iki.", &["--normalize", "nfkc"]),
+        "Wiki."
+    );
+}
+
+const SEE_ALSO: &str = "Lead [[Anarchism]].\n== History ==\n[[Enlightenment]]\n== See also ==\n* [[Libertarianism]]\n=== Nested ===\n[[Mutualism]]\n== Notes ==\n[[Proudhon]]";
+
+#[test]
+fn see_also_links_are_skipped() {
+    let parsed = mediawiki::configuration(&[]).parse(SEE_ALSO).unwrap();
+    assert_eq!(
+        mediawiki::text_link_targets(&parsed.nodes, false),
+        ["Anarchism", "Enlightenment", "Proudhon"]
+    );
+    assert_eq!(
+        mediawiki::text_link_targets(&parsed.nodes, true),
+        [
+            "Anarchism",
+            "Enlightenment",
+            "Libertarianism",
+            "Mutualism",
+            "Proudhon"
+        ]
+    );
+}
+
+#[test]
+fn horizontal_divider_breaks_paragraph() {
+    assert_eq!(
+        to_text("First paragraph.\n----\nSecond paragraph.").trim(),
+        "First paragraph.\n\nSecond paragraph."
+    );
+    assert_eq!(to_text("__TOC__\nText.").trim(), "Text.");
+}
+
+#[test]
+fn nested_template_parameters() {
+    assert_eq!(
+        to_text("It is {{convert|{{nowrap|5}}|km}} long.").trim(),
+        "It is 5 km long."
+    );
+    assert_eq!(
+        to_text("It is {{convert|{{#expr: 2 * 3}}|km}} long.").trim(),
+        "It is km long."
+    );
+    assert_eq!(
+        to_text("Say {{lang|fr|{{lang|fr|bonjour}}}}.").trim(),
+        "Say bonjour."
+    );
+}
+
+#[test]
+fn keep_raw_templates() {
+    assert_eq!(
+        to_text_with("It is {{convert|5|km}} long.", &["--keep-raw-templates"]).trim(),
+        "It is {{convert|5|km}} long."
+    );
+    assert_eq!(
+        to_text_with(
+            "Said {{lang|code=fr|text=bonjour}}.",
+            &["--keep-raw-templates"]
+        )
+        .trim(),
+        "Said {{lang|code=fr|text=bonjour}}."
+    );
+    assert_eq!(
+        to_text_with(
+            "It is {{convert|{{nowrap|5}}|km|abbr=on}} long.",
+            &["--keep-raw-templates"]
+        )
+        .trim(),
+        "It is {{convert|{{nowrap|5}}|km|abbr=on}} long."
+    );
+}
+
+#[test]
+fn paragraphs_skip_sections() {
+    let options = TextOptions::parse_from(["test"]);
+    let wikitext = "First paragraph.\n\nSecond\nparagraph.\n\n== History ==\nThird.\n\n\
+                    == See also ==\nSkipped.\n\n* [[Link]]";
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    assert_eq!(
+        mediawiki::nodes_to_paragraphs(&parsed.nodes, &options),
+        ["First paragraph.", "Second\nparagraph.", "Third."]
+    );
+}
+
+#[test]
+fn category_namespace_case() {
+    let wikitext =
+        "Text.\n[[Category:Upper]]\n[[category:Lower]]\n[[CATEGORY:Caps]]\n[[cAtEgOrY:Mixed]]";
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    assert_eq!(
+        mediawiki::categories(&parsed.nodes),
+        ["Upper", "Lower", "Caps", "Mixed"]
+    );
+    assert_eq!(to_text(wikitext).trim(), "Text.");
+}
+
+#[test]
+fn file_namespace_case() {
+    for wikitext in [
+        "Text.[[File:A.png|thumb]]",
+        "Text.[[file:A.png|thumb]]",
+        "Text.[[FILE:A.png|thumb]]",
+        "Text.[[Image:A.png|thumb]]",
+        "Text.[[IMAGE:A.png|thumb]]",
+    ] {
+        assert_eq!(to_text(wikitext).trim(), "Text.", "{}", wikitext);
+    }
+}
+
+#[test]
+fn passthrough_templates() {
+    let mut options = TextOptions::parse_from(["test", "--passthrough-template", "IPA_audio"]);
+    options.template_rules = TemplateRules::first_param_only(&options.passthrough_templates);
+    let parsed = mediawiki::configuration(&[])
+        .parse("Said {{ipa audio|/bɒnˈʒuːr/|x}} {{lang|fr|bonjour}}.")
+        .unwrap();
+    assert_eq!(
+        mediawiki::nodes_to_text(&parsed.nodes, &options).trim(),
+        "Said /bɒnˈʒuːr/."
+    );
+}
+
+#[test]
+fn drop_empty_sections() {
+    let wikitext = "Lead.\n== Empty ==\n== Parent ==\n=== Child ===\nText.\n\
+                    === Stub ===\n== Last ==\n";
+    assert_eq!(
+        to_text_with(wikitext, &["-H"]).trim(),
+        "Lead. Empty\nParent\nChild\nText. Stub\nLast"
+    );
+    assert_eq!(
+        to_text_with(wikitext, &["-H", "--drop-empty-sections"]).trim(),
+        "Lead. Parent\nChild\nText."
+    );
+}
+
+#[test]
+fn first_paragraph_only() {
+    let wikitext = "{{Infobox|x}}\n\nFirst paragraph.\nStill first.\n\nSecond paragraph.\n\n\
+                    == History ==\nThird.";
+    assert_eq!(
+        to_text_with(wikitext, &["--first-paragraph-only"]).trim(),
+        "First paragraph.\nStill first."
+    );
+}
+
+#[test]
+fn markdown_emphasis_is_balanced() {
+    let markdown = |wikitext| to_text_with(wikitext, &["--markdown"]).trim().to_string();
+    assert_eq!(markdown("A '''bold''' word."), "A **bold** word.");
+    assert_eq!(markdown("An ''italic'' word."), "An _italic_ word.");
+    assert_eq!(markdown("'''''Both''''' words."), "**_Both_** words.");
+    assert_eq!(
+        markdown("'''Unclosed bold.\n\nNext."),
+        "**Unclosed bold.** Next."
+    );
+    assert_eq!(
+        markdown("'''Bold ''both''' italic''."),
+        "**Bold _both_**_ italic_."
+    );
+    assert_eq!(markdown("'''Bold.''' Next."), "**Bold.** Next.");
+    assert_eq!(markdown("''Italic.''"), "_Italic._");
+    assert_eq!(
+        markdown("Lead. '''Bold.''' ''Italic.''"),
+        "Lead. **Bold.** _Italic._"
+    );
+}
+
+const HTML_TABLE: &str = "Before.\n<table class=\"wikitable\">\n<tr><th>Name</th><th>Note</th></tr>\n<tr><td>Alpha <b>bold</b> cell.</td><td>First <span>row</span>.</tr>\n<tr><td>Beta.<td>Second row.</td></tr>\n</table>\nAfter.";
+
+#[test]
+fn html_tables() {
+    // heading cells are left out, missing end tags close the open cell
+    assert_eq!(
+        to_text(HTML_TABLE),
+        "Before.\n\nAlpha bold cell.\nFirst row.\nBeta.\nSecond row.\n\nAfter."
+    );
+}
+
+#[test]
+fn html_tables_excluded() {
+    assert_eq!(
+        to_text_with(HTML_TABLE, &["--no-tables"]),
+        "Before.\n\nAfter."
+    );
+}
+
+#[test]
+fn html_tables_markdown() {
+    assert_eq!(
+        to_text_with(HTML_TABLE, &["--markdown"]),
+        "Before.\n\n| Name | Note |\n|-|-|\n| Alpha bold cell. | First row. |\n\
+         | Beta. | Second row. |\n\nAfter."
+    );
+}
+
+fn to_sections(wikitext: &str) -> mediawiki::Section {
+    let options = TextOptions::parse_from(["test"]);
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    mediawiki::nodes_to_sections(wikitext, &parsed.nodes, &options)
+}
+
+#[test]
+fn section_tree() {
+    let root = to_sections("Lead text.\n==A==\nIn A.\n===B===\nIn B.\n==C==\nIn C.");
+    assert_eq!(root.level, 0);
+    assert_eq!(root.heading, "");
+    assert_eq!(root.text, "Lead text.");
+    let shape: Vec<_> = root
+        .children
+        .iter()
+        .map(|it| {
+            (
+                it.heading.as_str(),
+                it.level,
+                it.text.as_str(),
+                it.children.len(),
+            )
+        })
+        .collect();
+    assert_eq!(shape, [("A", 2, "In A.", 1), ("C", 2, "In C.", 0)]);
+    let b = &root.children[0].children[0];
+    assert_eq!(
+        (b.heading.as_str(), b.level, b.text.as_str()),
+        ("B", 3, "In B.")
+    );
+}
+
+#[test]
+fn section_tree_skipped_level() {
+    // level 3 is skipped, level 4 section is still nested in the level 2 one
+    let root = to_sections("==A==\n====B====\nIn B.\n===C===\nIn C.");
+    assert_eq!(root.text, "");
+    assert_eq!(root.children.len(), 1);
+    let a = &root.children[0];
+    let children: Vec<_> = a
+        .children
+        .iter()
+        .map(|it| (it.heading.as_str(), it.level, it.text.as_str()))
+        .collect();
+    assert_eq!(children, [("B", 4, "In B."), ("C", 3, "In C.")]);
+}
+
+#[test]
+fn section_tree_skips_sections() {
+    let root = to_sections(
+        "Lead.\n==A==\nIn A.\n==See also==\n* [[B]]\n===Nested===\nHidden.\n==C==\nIn C.",
+    );
+    let headings: Vec<_> = root.children.iter().map(|it| it.heading.as_str()).collect();
+    assert_eq!(headings, ["A", "C"]);
+    assert!(root.children.iter().all(|it| it.children.is_empty()));
+}
+
+fn is_disambiguation(wikitext: &str) -> bool {
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    mediawiki::is_disambiguation(wikitext, &parsed.nodes)
+}
+
+#[test]
+fn disambiguation_pages() {
+    assert!(is_disambiguation(
+        "'''Mercury''' may refer to:\n__DISAMBIG__"
+    ));
+    assert!(is_disambiguation(
+        "* [[Mercury (planet)]]\n{{Disambiguation}}"
+    ));
+    assert!(is_disambiguation(
+        "* [[Mercury (planet)]]\n{{hndis|Mercury}}"
+    ));
+    // templates can be nested in other markup
+    assert!(is_disambiguation(
+        "* [[Mercury (planet)]]\n{|\n| {{disambig}}\n|}"
+    ));
+    assert!(is_disambiguation("{{Navbox|list={{dab}}}}"));
+    assert!(!is_disambiguation(
+        "'''Mercury''' is a planet.{{Infobox planet}}"
+    ));
+}
+
+#[test]
+fn extension_tags() {
+    let wikitext = "Melody: <sheetmusic>c' d' e'</sheetmusic> Played [[slowly]].";
+    let to_text_with_tags = |tags: &[&str]| {
+        let mut args = vec!["test"];
+        for tag in tags {
+            args.extend(["--extension-tag", tag]);
+        }
+        let options = TextOptions::parse_from(args);
+        let parsed = mediawiki::configuration(&options.extension_tags)
+            .parse(wikitext)
+            .unwrap();
+        mediawiki::nodes_to_text(&parsed.nodes, &options)
+    };
+    // unknown tags are left in text along with their content
+    assert_eq!(
+        to_text_with_tags(&[]).trim(),
+        "Melody: <sheetmusic>c' d' e'</sheetmusic> Played slowly."
+    );
+    // names are trimmed and case insensitive
+    assert_eq!(
+        to_text_with_tags(&[" SheetMusic "]).trim(),
+        "Melody: Played slowly."
+    );
+}
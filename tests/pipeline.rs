@@ -0,0 +1,1462 @@
+//! End-to-end runs of the extractor binary against fixture dumps.
+//!
+//! Produced files are compared with golden files in `tests/fixtures/expected`.
+
+use std::{
+    io::{BufRead as _, BufReader, Write as _},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+mod common;
+
+use common::{binary, write_dump};
+
+const DUMP_NAME: &str = "enwiki-test-pages-articles.xml";
+const GOLDEN_FILES: &[&str] = &[
+    "wiki_sentences.txt",
+    "redirects.json",
+    "wiki_page_info.json",
+    "links.jsonl",
+];
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wx-pipeline-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Runs the binary with `args`, logging messages of `log_level` and above.
+fn run_extractor(args: &[&str], log_level: &str) -> Output {
+    Command::new(binary())
+        .args(args)
+        .env("RUST_LOG", log_level)
+        .output()
+        .expect("unable to run wiki-extractor")
+}
+
+/// Runs extraction of text, redirects, metadata and links with given input
+/// arguments.
+fn extract(output: &Path, input: &[&str]) {
+    let mut args = vec![
+        "-T",
+        "-R",
+        "-M",
+        "--collect-links",
+        "-o",
+        output.to_str().unwrap(),
+    ];
+    args.extend(input);
+    let result = run_extractor(&args, "warn");
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+}
+
+fn assert_golden(output: &Path) {
+    for name in GOLDEN_FILES {
+        let expected = std::fs::read_to_string(fixtures().join("expected").join(name)).unwrap();
+        let produced = std::fs::read_to_string(output.join(name))
+            .unwrap_or_else(|err| panic!("missing output {}: {}", name, err));
+        assert_eq!(produced, expected, "{} differs from golden file", name);
+    }
+    assert!(output.join("manifest.json").is_file());
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn local_xml() {
+    let output = output_dir("xml");
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    extract(&output, &["local", input.to_str().unwrap()]);
+    assert_golden(&output);
+}
+
+#[test]
+fn manifest_counts_filtered_pages_out() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    for (namespaces, processed) in [("0,14", 4), ("0", 3)] {
+        let output = output_dir("manifest-pages");
+        extract(
+            &output,
+            &["--namespaces", namespaces, "local", input.to_str().unwrap()],
+        );
+
+        let manifest = std::fs::read_to_string(output.join("manifest.json")).unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["pages_processed"], processed, "{}", namespaces);
+        std::fs::remove_dir_all(output).unwrap();
+    }
+}
+
+#[test]
+fn links_use_unescaped_titles() {
+    let output = output_dir("links-escaped");
+    let input = fixtures().join("escaped_title.xml");
+    extract(&output, &["local", input.to_str().unwrap()]);
+
+    let links = std::fs::read_to_string(output.join("links.jsonl")).unwrap();
+    let links: Vec<serde_json::Value> = links
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(links.len(), 2, "{:?}", links);
+    assert_eq!(links[0]["from"], "Fish & chips");
+    assert_eq!(links[0]["to"], serde_json::json!(["Chips"]));
+    assert_eq!(links[1]["from"], "Chips");
+    assert_eq!(links[1]["to"], serde_json::json!(["Fish & chips"]));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn follow_redirects_with_escapes() {
+    let output = output_dir("follow-escaped");
+    let input = fixtures().join("escaped_title.xml");
+    extract(
+        &output,
+        &["--follow-redirects", "local", input.to_str().unwrap()],
+    );
+
+    let resolved: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(output.join("redirects_resolved.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        resolved,
+        serde_json::json!({"\"Fish\" <dish>": "Fish & chips"})
+    );
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn manifest_site_info() {
+    let output = output_dir("manifest-site");
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    extract(&output, &["local", input.to_str().unwrap()]);
+
+    let manifest = std::fs::read_to_string(output.join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["site"]["site_name"], "Wikipedia");
+    assert_eq!(manifest["site"]["db_name"], "enwiki");
+    assert_eq!(
+        manifest["site"]["base"],
+        "https://en.wikipedia.org/wiki/Main_Page"
+    );
+    assert_eq!(manifest["site"]["generator"], "MediaWiki 1.43.0-wmf.8");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn local_bzip2() {
+    let output = output_dir("bz2");
+    let input = fixtures().join("dump").join(format!("{}.bz2", DUMP_NAME));
+    extract(&output, &["local", input.to_str().unwrap()]);
+    assert_golden(&output);
+}
+
+#[test]
+fn local_misnamed_bzip2() {
+    let output = output_dir("misnamed");
+    let input = std::env::temp_dir().join(format!("wx-misnamed-{}.xml", std::process::id()));
+    std::fs::copy(
+        fixtures().join("dump").join(format!("{}.bz2", DUMP_NAME)),
+        &input,
+    )
+    .unwrap();
+    extract(&output, &["local", input.to_str().unwrap()]);
+    std::fs::remove_file(input).unwrap();
+    assert_golden(&output);
+}
+
+#[test]
+fn local_other_compressions() {
+    let xml = std::fs::read(fixtures().join("dump").join(DUMP_NAME)).unwrap();
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gzip.write_all(&xml).unwrap();
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+    xz.write_all(&xml).unwrap();
+    let variants = [
+        ("gz", gzip.finish().unwrap()),
+        ("xz", xz.finish().unwrap()),
+        ("zst", zstd::encode_all(&xml[..], 1).unwrap()),
+    ];
+
+    for (ext, data) in variants {
+        let output = output_dir(&format!("compressed-{}", ext));
+        let input = std::env::temp_dir().join(format!(
+            "wx-compressed-{}-{}.xml.{}",
+            ext,
+            std::process::id(),
+            ext
+        ));
+        std::fs::write(&input, data).unwrap();
+        extract(&output, &["local", input.to_str().unwrap()]);
+        std::fs::remove_file(input).unwrap();
+        assert_golden(&output);
+    }
+}
+
+#[test]
+fn sharded_pages() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let read_shards = |output: &Path| {
+        (0..3)
+            .map(|i| {
+                let shard = output.join(format!("wiki_pages_{:05}.jsonl", i));
+                std::fs::read_to_string(shard).unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut runs = Vec::new();
+    for run in ["shards-a", "shards-b"] {
+        let output = output_dir(run);
+        let result = run_extractor(
+            &[
+                "--pages-jsonl",
+                "--shards",
+                "3",
+                "-o",
+                output.to_str().unwrap(),
+                "local",
+                input.to_str().unwrap(),
+            ],
+            "warn",
+        );
+        assert!(
+            result.status.success(),
+            "wiki-extractor exited with {}",
+            result.status
+        );
+        runs.push(read_shards(&output));
+        std::fs::remove_dir_all(output).unwrap();
+    }
+
+    assert_eq!(runs[0], runs[1], "shard assignment isn't stable");
+    let lines: Vec<&str> = runs[0].iter().flat_map(|it| it.lines()).collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines
+        .iter()
+        .any(|it| it.contains("\"title\":\"Anarchism\"")));
+}
+
+#[test]
+fn pages_use_unescaped_titles() {
+    let output = output_dir("pages-escaped");
+    let input = fixtures().join("escaped_title.xml");
+    let result = run_extractor(
+        &[
+            "--pages-jsonl",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+
+    let pages = std::fs::read_to_string(output.join("wiki_pages.jsonl")).unwrap();
+    let titles: Vec<serde_json::Value> = pages
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["title"].clone())
+        .collect();
+    assert_eq!(titles, ["Fish & chips", "Chips"]);
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn redirects_as_pages() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("redirect-pages");
+    extract(
+        &output,
+        &[
+            "--pages-jsonl",
+            "--emit-redirects-as-pages",
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+
+    let pages = std::fs::read_to_string(output.join("wiki_pages.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = pages
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0]["title"], "AccessibleComputing");
+    assert_eq!(records[0]["text"], "");
+    assert_eq!(records[0]["redirect_to"], "Computer accessibility");
+    assert!(records[1].get("redirect_to").is_none());
+    // redirects file is still written
+    let redirects = std::fs::read_to_string(output.join("redirects.json")).unwrap();
+    assert!(redirects.contains("AccessibleComputing"));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn process_redirect_bodies() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("redirect-bodies");
+    extract(
+        &output,
+        &[
+            "--pages-jsonl",
+            "--process-redirect-bodies",
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+
+    let pages = std::fs::read_to_string(output.join("wiki_pages.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = pages
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0]["title"], "AccessibleComputing");
+    assert_eq!(records[0]["redirect_to"], "Computer accessibility");
+    let links = std::fs::read_to_string(output.join("links.jsonl")).unwrap();
+    assert!(
+        links.contains("\"from\":\"AccessibleComputing\""),
+        "{}",
+        links
+    );
+    // redirects file is still written
+    let redirects = std::fs::read_to_string(output.join("redirects.json")).unwrap();
+    assert!(redirects.contains("AccessibleComputing"));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn progress_json() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("progress-json");
+    let result = run_extractor(
+        &[
+            "-T",
+            "--progress-json",
+            "--progress-interval",
+            "0",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(!stderr.contains('\x1b'), "progress bar was drawn");
+    let last: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(last["file"], DUMP_NAME);
+    assert_eq!(last["percent"], 100.0);
+    assert_eq!(last["pages"], 4);
+    assert!(last["bytes"].as_u64().unwrap() > 0);
+    assert!(last["eta_seconds"].is_u64());
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn negative_namespace_filter() {
+    let input = fixtures().join("special_namespaces.xml");
+    let output = output_dir("negative-namespaces");
+    let result = run_extractor(
+        &[
+            "-T",
+            "--namespaces",
+            "-1,Media",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+    // excluded pages are skipped silently
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("WARN"));
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("Special page."));
+    assert!(text.contains("Media page."));
+    assert!(!text.contains("Article page."));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn exclude_namespaces() {
+    let input = fixtures().join("special_namespaces.xml");
+    let output = output_dir("exclude-namespaces");
+    extract(
+        &output,
+        &[
+            "--exclude-namespaces",
+            "-1,Media",
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(!text.contains("Special page."));
+    assert!(!text.contains("Media page."));
+    assert!(text.contains("Article page."));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn exclusive_namespace_filters() {
+    let result = run_extractor(
+        &[
+            "--namespaces",
+            "0",
+            "--exclude-namespaces",
+            "1",
+            "local",
+            DUMP_NAME,
+        ],
+        "error",
+    );
+    assert_eq!(result.status.code(), Some(2));
+}
+
+#[test]
+fn list_generators() {
+    let result = run_extractor(&["--list-generators"], "error");
+    assert!(result.status.success(), "{:?}", result);
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let history = stdout
+        .lines()
+        .find(|line| line.starts_with("history "))
+        .expect("history generator not listed");
+    assert!(history.contains("--history"), "{history}");
+    assert!(history.contains("revisions.jsonl"), "{history}");
+    assert!(stdout.contains("--format conll"), "{stdout}");
+}
+
+#[test]
+fn markdown_with_format() {
+    for format in ["conll", "paragraphs"] {
+        let result = run_extractor(
+            &["--format", format, "--markdown", "local", DUMP_NAME],
+            "error",
+        );
+        assert_eq!(result.status.code(), Some(2), "{format}");
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(
+            stderr.contains("`--markdown` can't be combined"),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn unknown_namespace() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("unknown-namespace");
+    let result = run_extractor(
+        &[
+            "-T",
+            "--namespaces",
+            "Typo",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "error",
+    );
+    let _ = std::fs::remove_dir_all(output);
+    assert_ne!(result.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("unknown namespaces: Typo"), "{stderr}");
+    assert!(stderr.contains("0 (main)"), "{stderr}");
+    assert!(stderr.contains("1 (Talk)"), "{stderr}");
+    assert!(stderr.contains("14 (Category)"), "{stderr}");
+}
+
+#[test]
+fn zero_page_limit() {
+    let result = run_extractor(&["--max-pages-in-memory", "0", "local", DUMP_NAME], "error");
+    assert_eq!(result.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("at least one page"), "{}", stderr);
+}
+
+/// Builds a tar archive of `members`, in given order.
+fn tar_archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = tar::Builder::new(Vec::new());
+    for (name, data) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, name, *data).unwrap();
+    }
+    archive.into_inner().unwrap()
+}
+
+#[test]
+fn local_tar_archive() {
+    let dump = std::fs::read(fixtures().join("dump").join(DUMP_NAME)).unwrap();
+    let data = tar_archive(&[
+        ("dump.xml.md5", b"d41d8cd98f00b204e9800998ecf8427e"),
+        ("dump.xml", &dump),
+        ("dump.xmlfoo", b"<mediawiki>"),
+    ]);
+    let input = std::env::temp_dir().join(format!("wx-archive-{}.tar", std::process::id()));
+    std::fs::write(&input, data).unwrap();
+
+    let output = output_dir("archive");
+    extract(&output, &["local", input.to_str().unwrap()]);
+    std::fs::remove_file(input).unwrap();
+    assert_golden(&output);
+}
+
+#[test]
+fn local_bzip2_archive() {
+    let dump = std::fs::read(fixtures().join("dump").join(DUMP_NAME)).unwrap();
+    let single = std::fs::read(fixtures().join("single_page.xml")).unwrap();
+    // members are read in name order, which differs from archive order
+    let data = tar_archive(&[("b.xml", &dump), ("a.xml", &single), ("c.xml", &dump)]);
+    let mut compressed = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+    compressed.write_all(&data).unwrap();
+    let input = std::env::temp_dir().join(format!("wx-archive-{}.tar.bz2", std::process::id()));
+    std::fs::write(&input, compressed.finish().unwrap()).unwrap();
+
+    let output = output_dir("archive-bz2");
+    extract(&output, &["local", input.to_str().unwrap()]);
+    std::fs::remove_file(input).unwrap();
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    let expected = std::fs::read_to_string(fixtures().join("expected/wiki_sentences.txt")).unwrap();
+    assert!(text.contains("The only page."), "{}", text);
+    assert_eq!(text.matches(expected.trim()).count(), 2, "{}", text);
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn local_corrupt_archive() {
+    let input = std::env::temp_dir().join(format!("wx-corrupt-{}.tar", std::process::id()));
+    std::fs::write(&input, vec![0xff; 1024]).unwrap();
+
+    let output = output_dir("archive-corrupt");
+    let result = run_extractor(
+        &[
+            "-T",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "error",
+    );
+    std::fs::remove_file(input).unwrap();
+    let _ = std::fs::remove_dir_all(output);
+    assert_eq!(result.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("unable to read dump"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn keep_going() {
+    let input = std::env::temp_dir().join(format!("wx-keep-going-{}.tar", std::process::id()));
+    let mut archive = tar::Builder::new(std::fs::File::create(&input).unwrap());
+    let broken: &[u8] = b"<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\">\
+                          <page><title>Broken</title></revision></mediawiki>";
+    let good = std::fs::read(fixtures().join("single_page.xml")).unwrap();
+    for (name, data) in [("a_broken.xml", broken), ("b_good.xml", &good)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, name, data).unwrap();
+    }
+    archive.into_inner().unwrap();
+
+    for (keep_going, extracted) in [(false, false), (true, true)] {
+        let output = output_dir(if keep_going { "keep-going" } else { "stop" });
+        let mut args = vec!["-T", "-o", output.to_str().unwrap()];
+        if keep_going {
+            args.push("--keep-going");
+        }
+        args.extend(["local", input.to_str().unwrap()]);
+        assert_eq!(run_extractor(&args, "error").status.code(), Some(1));
+
+        let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+        assert_eq!(text.contains("The only page."), extracted);
+        std::fs::remove_dir_all(output).unwrap();
+    }
+    std::fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn validate_only() {
+    let input = fixtures().join("single_page.xml");
+    let output = output_dir("validate-only");
+    let result = run_extractor(
+        &[
+            "--validate-only",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "error",
+    );
+    assert_eq!(result.status.code(), Some(0), "{:?}", result);
+    let written = std::fs::read_dir(&output)
+        .map(|it| it.count())
+        .unwrap_or_default();
+    assert_eq!(written, 0);
+    let _ = std::fs::remove_dir_all(output);
+
+    let broken = std::env::temp_dir().join(format!("wx-validate-{}.xml", std::process::id()));
+    std::fs::write(
+        &broken,
+        "<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\">\
+         <page><title>Broken</title></revision></mediawiki>",
+    )
+    .unwrap();
+    let output = output_dir("validate-broken");
+    let result = run_extractor(
+        &[
+            "--validate-only",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            broken.to_str().unwrap(),
+        ],
+        "error",
+    );
+    std::fs::remove_file(broken).unwrap();
+    let _ = std::fs::remove_dir_all(output);
+    assert_eq!(result.status.code(), Some(1), "{:?}", result);
+}
+
+#[test]
+fn validate_only_with_generator() {
+    let input = fixtures().join("single_page.xml");
+    let output = output_dir("validate-generator");
+    let result = run_extractor(
+        &[
+            "--validate-only",
+            "-T",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "error",
+    );
+    assert_eq!(result.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("--validate-only"), "{stderr}");
+    assert!(!output.exists());
+}
+
+#[test]
+fn errors_file() {
+    let input = fixtures().join("content_models.xml");
+    let output = output_dir("errors");
+    let errors = output.join("errors.jsonl");
+    extract(
+        &output,
+        &[
+            "--errors-file",
+            errors.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+
+    let errors = std::fs::read_to_string(errors).unwrap();
+    let lines: Vec<serde_json::Value> = errors
+        .lines()
+        .map(|it| serde_json::from_str(it).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["id"], 5);
+    assert_eq!(lines[0]["title"], "Module:Hello");
+    assert_eq!(lines[0]["kind"], "unsupported");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn single_page_with_trailing_bytes() {
+    let input = fixtures().join("single_page.xml");
+    let output = output_dir("single-page");
+    extract(&output, &["local", input.to_str().unwrap()]);
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text.trim(), "The only page.");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn content_model_filter() {
+    let input = fixtures().join("content_models.xml");
+    let output = output_dir("content-model");
+    let result = run_extractor(
+        &[
+            "-T",
+            "--content-model",
+            "Scribunto",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text.trim(), "local p = {}\nreturn p");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn storage_full_keeps_valid_json() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("storage-full");
+    std::fs::create_dir_all(&output).unwrap();
+    std::os::unix::fs::symlink("/dev/full", output.join("wiki_sentences.txt")).unwrap();
+
+    let result = run_extractor(
+        &[
+            "-T",
+            "-R",
+            "-M",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert_eq!(result.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("storage is full"));
+
+    for name in ["redirects.json", "wiki_page_info.json"] {
+        let content = std::fs::read_to_string(output.join(name)).unwrap();
+        serde_json::from_str::<serde_json::Value>(&content)
+            .unwrap_or_else(|err| panic!("{} isn't valid JSON: {}", name, err));
+    }
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn detect_language() {
+    let input = fixtures().join("languages.xml");
+    let output = output_dir("detect-language");
+    extract(
+        &output,
+        &["--detect-language", "eng", "local", input.to_str().unwrap()],
+    );
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("quick brown fox"));
+    assert!(!text.contains("Fuchs"));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn latin1_text_output() {
+    let input = write_dump(
+        "latin1",
+        &[("Zürich", "Zürich costs 5 € in Ω [[Ωmega]] 中.")],
+    );
+    let output = output_dir("latin1");
+    extract(
+        &output,
+        &[
+            "--output-encoding",
+            "latin1",
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+    std::fs::remove_file(input).unwrap();
+
+    let text = std::fs::read(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text.trim_ascii(), b"Z\xfcrich costs 5 \x80 in ? ?mega ?.");
+    // JSON outputs are always UTF-8
+    let links = std::fs::read_to_string(output.join("links.jsonl")).unwrap();
+    let links: serde_json::Value = serde_json::from_str(&links).unwrap();
+    assert_eq!(links["from"], "Zürich");
+    assert_eq!(links["to"], serde_json::json!(["Ωmega"]));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+/// Runs page sampling on a dump of 40 pages, every other one of which is a
+/// redirect, and returns the written page records.
+fn sample_pages(name: &str, redirect_parity: usize, sample: &str, seed: &str) -> String {
+    let pages: String = (1..=40)
+        .map(|id| {
+            let redirect = if id % 2 == redirect_parity {
+                "<redirect title=\"Target\" />"
+            } else {
+                ""
+            };
+            format!(
+                "<page><title>Page {id}</title><ns>0</ns><id>{id}</id>{redirect}\
+                 <revision><id>{id}</id><model>wikitext</model><format>text/x-wiki</format>\
+                 <text xml:space=\"preserve\">Text of page {id}.</text></revision></page>"
+            )
+        })
+        .collect();
+    let input = std::env::temp_dir().join(format!("wx-{}-{}.xml", name, std::process::id()));
+    std::fs::write(
+        &input,
+        format!(
+            "<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\">{pages}</mediawiki>"
+        ),
+    )
+    .unwrap();
+    let output = output_dir(name);
+    extract(
+        &output,
+        &[
+            "--pages-jsonl",
+            "--emit-redirects-as-pages",
+            "--sample",
+            sample,
+            "--seed",
+            seed,
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+    std::fs::remove_file(input).unwrap();
+    let pages = std::fs::read_to_string(output.join("wiki_pages.jsonl")).unwrap_or_default();
+    std::fs::remove_dir_all(output).unwrap();
+    pages
+}
+
+fn page_titles(pages: &str) -> Vec<String> {
+    pages
+        .lines()
+        .map(|line| {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            record["title"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn sampling_is_deterministic() {
+    let first = sample_pages("sample-first", 0, "0.5", "7");
+    let second = sample_pages("sample-second", 0, "0.5", "7");
+    assert_eq!(first, second);
+    let titles = page_titles(&first);
+    assert!(!titles.is_empty() && titles.len() < 40, "{:?}", titles);
+
+    let other_seed = page_titles(&sample_pages("sample-seed", 0, "0.5", "8"));
+    assert_ne!(titles, other_seed);
+
+    // redirects are sampled the same way as any other page
+    let swapped = page_titles(&sample_pages("sample-swapped", 1, "0.5", "7"));
+    assert_eq!(titles, swapped);
+}
+
+#[test]
+fn sampling_rate_bounds() {
+    assert_eq!(
+        page_titles(&sample_pages("sample-none", 0, "0.0", "7")).len(),
+        0
+    );
+    assert_eq!(
+        page_titles(&sample_pages("sample-all", 0, "1.0", "7")).len(),
+        40
+    );
+}
+
+#[test]
+fn disambiguation_pages() {
+    let input = write_dump(
+        "disambiguation",
+        &[
+            (
+                "Mercury",
+                "'''Mercury''' may refer to:\n* [[Mercury (planet)]]\n\
+                 * [[Mercury (element)|element]]\n&lt;div&gt;{{Disambiguation}}&lt;/div&gt;",
+            ),
+            ("Venus", "Also a planet, see [[Mercury]].\n__DISAMBIG__"),
+            (
+                "Mars",
+                "'''Mars''' is a planet, unlike [[Mercury (element)]].",
+            ),
+        ],
+    );
+    let output = output_dir("disambiguation");
+    extract(
+        &output,
+        &["--collect-disambiguation", "local", input.to_str().unwrap()],
+    );
+    std::fs::remove_file(input).unwrap();
+
+    let records = std::fs::read_to_string(output.join("disambiguation.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = records
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        records,
+        [
+            serde_json::json!({
+                "title": "Mercury",
+                "targets": ["Mercury (planet)", "Mercury (element)"],
+            }),
+            serde_json::json!({"title": "Venus", "targets": ["Mercury"]}),
+        ]
+    );
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn page_timeout() {
+    let page = |id: usize, title: &str, text: &str| {
+        format!(
+            "<page><title>{title}</title><ns>0</ns><id>{id}</id><revision><id>{id}</id>\
+             <model>wikitext</model><format>text/x-wiki</format>\
+             <text xml:space=\"preserve\">{text}</text></revision></page>"
+        )
+    };
+    let input = std::env::temp_dir().join(format!("wx-slow-{}.xml", std::process::id()));
+    std::fs::write(
+        &input,
+        format!(
+            "<mediawiki xmlns=\"http://www.mediawiki.org/xml/export-0.11/\">{}{}</mediawiki>",
+            // nested unclosed templates take exponential time to parse
+            page(1, "Slow", &"{{a|".repeat(200)),
+            page(2, "Fast", "Parsed in time."),
+        ),
+    )
+    .unwrap();
+    let output = output_dir("page-timeout");
+    let errors = output.join("errors.jsonl");
+    extract(
+        &output,
+        &[
+            "--page-timeout",
+            "1",
+            "--errors-file",
+            errors.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+    std::fs::remove_file(input).unwrap();
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text.trim(), "Parsed in time.");
+    let errors: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(errors).unwrap()).unwrap();
+    assert_eq!(errors["title"], "Slow");
+    assert_eq!(errors["kind"], "timed-out");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn min_sentences() {
+    let input = write_dump(
+        "min-sentences",
+        &[
+            ("Stub", "Only one sentence, e.g. a stub."),
+            ("Article", "First sentence. Is this the second? It is!"),
+        ],
+    );
+    let output = output_dir("min-sentences");
+    extract(
+        &output,
+        &["--min-sentences", "2", "local", input.to_str().unwrap()],
+    );
+    std::fs::remove_file(input).unwrap();
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("First sentence."));
+    assert!(!text.contains("Only one sentence"));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn dedup_titles() {
+    let input = write_dump(
+        "dedup-titles",
+        &[
+            ("Fish_tank", "First copy."),
+            ("fish  tank", "Second copy."),
+            ("Fish", "Other page."),
+        ],
+    );
+    let output = output_dir("dedup-titles");
+    let result = run_extractor(
+        &[
+            "-T",
+            "--dedup-titles",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "info",
+    );
+    std::fs::remove_file(input).unwrap();
+    assert!(result.status.success());
+
+    // titles are compared after normalization
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.contains("First copy."));
+    assert!(!text.contains("Second copy."));
+    assert!(text.contains("Other page."));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.contains("skipped 1 pages with duplicate titles"),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+/// Serves a single request with a fixture file chosen by `resolve` from the
+/// requested path.
+fn serve_fixture(stream: TcpStream, resolve: &dyn Fn(&str) -> PathBuf) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut range_start = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header == "\r\n" {
+            break;
+        }
+        if let Some(range) = header.to_ascii_lowercase().strip_prefix("range: bytes=") {
+            range_start = range.trim().trim_end_matches('-').parse::<usize>().ok();
+        }
+    }
+
+    let path = request_line.split(' ').nth(1).unwrap_or_default();
+    let mut stream = stream;
+    match std::fs::read(resolve(path)) {
+        Ok(body) => {
+            let (status, body) = match range_start {
+                Some(start) if start < body.len() => ("206 Partial Content", &body[start..]),
+                _ => ("200 OK", &body[..]),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            )?;
+            stream.write_all(body)?;
+        }
+        Err(_) => {
+            stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )?;
+        }
+    }
+    stream.flush()
+}
+
+/// Starts a minimal HTTP server responding with fixture files and returns
+/// its address.
+fn start_server(resolve: impl Fn(&str) -> PathBuf + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = serve_fixture(stream, &resolve);
+        }
+    });
+    format!("http://{}/", address)
+}
+
+/// Starts a minimal HTTP mirror serving fixture dump files and returns its
+/// base URL.
+fn start_mirror() -> String {
+    start_mirror_at(fixtures().join("dump"))
+}
+
+/// Starts a minimal HTTP mirror serving files from `dir`, ignoring the
+/// directory part of requested paths.
+fn start_mirror_at(dir: PathBuf) -> String {
+    start_server(move |path| dir.join(path.rsplit('/').next().unwrap_or_default()))
+}
+
+/// Starts a minimal MediaWiki API serving site info and page revisions from
+/// `tests/fixtures/api`, and returns its endpoint URL.
+fn start_api() -> String {
+    let base = start_server(|path| {
+        if path.contains("meta=siteinfo") {
+            fixtures().join("api/siteinfo.json")
+        } else if path.contains("gcmtitle=Category%3AAnarchism") {
+            fixtures().join("api/category.json")
+        } else {
+            fixtures().join("api/pages.json")
+        }
+    });
+    base + "w/api.php"
+}
+
+#[test]
+fn remote_mirror() {
+    let output = output_dir("remote");
+    let mirror = start_mirror();
+    extract(&output, &["remote", &mirror, "-L", "en", "-w", "latest"]);
+    assert_golden(&output);
+}
+
+#[test]
+fn remote_mirror_fallback() {
+    let output = output_dir("remote-fallback");
+    // port of a closed listener refuses connections
+    let dead = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+    let mirror = start_mirror();
+    extract(
+        &output,
+        &["remote", &dead, "--mirror", &mirror, "-w", "latest"],
+    );
+    assert_golden(&output);
+}
+
+#[test]
+fn remote_running_dump() {
+    let output = output_dir("remote-running");
+    let mirror = start_mirror_at(fixtures().join("running_dump"));
+    let result = run_extractor(
+        &[
+            "-T",
+            "-o",
+            output.to_str().unwrap(),
+            "remote",
+            &mirror,
+            "-w",
+            "latest",
+        ],
+        "error",
+    );
+    assert_eq!(result.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("still being generated"), "{}", stderr);
+}
+
+#[test]
+fn api_input() {
+    let output = output_dir("api");
+    let api = start_api();
+    extract(
+        &output,
+        &[
+            "api",
+            &api,
+            "--title",
+            "Anarchism",
+            "--title",
+            "Missing page",
+            "--title",
+            "Bad[title",
+        ],
+    );
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(
+        text.trim(),
+        "Anarchism is a political philosophy & movement."
+    );
+    let manifest = std::fs::read_to_string(output.join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["site"]["db_name"], "enwiki");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn api_category_input() {
+    let output = output_dir("api-category");
+    extract(&output, &["api", &start_api(), "--category", "Anarchism"]);
+
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text.trim(), "Mutualism is an anarchist school of thought.");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn remote_since_unchanged_version() {
+    let output = output_dir("remote-since");
+    let mirror = start_mirror();
+    // mirror serves the same files for every version, so no page changed
+    extract(
+        &output,
+        &[
+            "remote",
+            &mirror,
+            "-w",
+            "latest",
+            "--since-version",
+            "20240101",
+        ],
+    );
+    let text = std::fs::read_to_string(output.join("wiki_sentences.txt")).unwrap();
+    assert!(text.is_empty(), "unchanged pages were extracted");
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn markdown_files() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("markdown-files");
+    extract(
+        &output,
+        &[
+            "--output-format",
+            "markdown-files",
+            "local",
+            input.to_str().unwrap(),
+        ],
+    );
+
+    let document = std::fs::read_to_string(output.join("markdown/59/d9/Anarchism.md")).unwrap();
+    let (frontmatter, text) = document
+        .strip_prefix("---\n")
+        .and_then(|it| it.split_once("---\n\n"))
+        .expect("missing frontmatter");
+    assert!(frontmatter.contains("title: \"Anarchism\"\nid: 12\nnamespace: 0\n"));
+    assert!(text.starts_with("**Anarchism**"));
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn paragraphs_jsonl() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("paragraphs");
+    extract(
+        &output,
+        &["--format", "paragraphs", "local", input.to_str().unwrap()],
+    );
+
+    let paragraphs = std::fs::read_to_string(output.join("wiki_paragraphs.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = paragraphs
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let anarchism: Vec<_> = records
+        .iter()
+        .filter(|it| it["title"] == "Anarchism")
+        .collect();
+    assert_eq!(anarchism.len(), 2);
+    assert_eq!(anarchism[1]["id"], 12);
+    assert_eq!(anarchism[1]["paragraph_index"], 1);
+    assert_eq!(
+        anarchism[1]["text"],
+        "Anarchism emerged from the Enlightenment."
+    );
+    std::fs::remove_dir_all(output).unwrap();
+}
+
+#[test]
+fn head_preview() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let output = output_dir("head");
+    let result = run_extractor(
+        &[
+            "--head",
+            "2",
+            "-o",
+            output.to_str().unwrap(),
+            "local",
+            input.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+    assert!(!output.exists(), "output directory was created");
+
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    let pages: Vec<serde_json::Value> = serde_json::Deserializer::from_str(&stdout)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[1]["title"], "Anarchism");
+    assert_eq!(pages[1]["ns"], 0);
+    assert!(pages[1]["text"].as_str().unwrap().starts_with("Anarchism"));
+}
+
+#[test]
+fn count_only() {
+    let input = fixtures().join("dump").join(DUMP_NAME);
+    let result = run_extractor(&["--count-only", "local", input.to_str().unwrap()], "warn");
+    assert!(
+        result.status.success(),
+        "wiki-extractor exited with {}",
+        result.status
+    );
+
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().map(str::trim).collect();
+    assert_eq!(lines[..3], ["Pages: 4", "Redirects: 1", "Namespaces:"]);
+    assert!(lines[3].starts_with("0 (main)") && lines[3].ends_with(" 3"));
+    assert!(lines[4].starts_with("14 Category") && lines[4].ends_with(" 1"));
+}
+
+/// Path of the fixture dump file in a cache directory.
+fn cached_dump(cache: &Path) -> PathBuf {
+    cache
+        .join("enwiki/latest")
+        .join(format!("{}.bz2", DUMP_NAME))
+}
+
+/// Starts a mirror listing the fixture dump without serving the dump file.
+fn start_listing_mirror(name: &str) -> String {
+    let dir = output_dir(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(
+        fixtures().join("dump/dumpstatus.json"),
+        dir.join("dumpstatus.json"),
+    )
+    .unwrap();
+    start_mirror_at(dir)
+}
+
+#[test]
+fn remote_cache_dir() {
+    let output = output_dir("cache-output");
+    let cache = output_dir("cache");
+    let cache_arg = cache.to_str().unwrap();
+    extract(
+        &output,
+        &["remote", &start_mirror(), "--cache-dir", cache_arg],
+    );
+    assert_golden(&output);
+    let original = std::fs::read(fixtures().join("dump").join(format!("{}.bz2", DUMP_NAME)));
+    assert_eq!(
+        std::fs::read(cached_dump(&cache)).unwrap(),
+        original.unwrap()
+    );
+
+    // dump file is only available from the cache
+    let output = output_dir("cache-output-cached");
+    let mirror = start_listing_mirror("cache-listing");
+    extract(&output, &["remote", &mirror, "--cache-dir", cache_arg]);
+    assert_golden(&output);
+    std::fs::remove_dir_all(cache).unwrap();
+}
+
+#[test]
+fn remote_cache_resumes_download() {
+    let output = output_dir("cache-resume-output");
+    let cache = output_dir("cache-resume");
+    let dump = std::fs::read(fixtures().join("dump").join(format!("{}.bz2", DUMP_NAME))).unwrap();
+    let part = cached_dump(&cache).with_extension("bz2.part");
+    std::fs::create_dir_all(part.parent().unwrap()).unwrap();
+    std::fs::write(&part, &dump[..dump.len() / 2]).unwrap();
+
+    extract(
+        &output,
+        &[
+            "remote",
+            &start_mirror(),
+            "--cache-dir",
+            cache.to_str().unwrap(),
+        ],
+    );
+    assert_golden(&output);
+    assert_eq!(std::fs::read(cached_dump(&cache)).unwrap(), dump);
+    assert!(!part.exists());
+    std::fs::remove_dir_all(cache).unwrap();
+}
+
+#[test]
+fn remote_cache_replaces_stale_file() {
+    let output = output_dir("cache-stale-output");
+    let cache = output_dir("cache-stale");
+    let dump = std::fs::read(fixtures().join("dump").join(format!("{}.bz2", DUMP_NAME))).unwrap();
+    // an older `latest` dump of the same size
+    std::fs::create_dir_all(cached_dump(&cache).parent().unwrap()).unwrap();
+    std::fs::write(cached_dump(&cache), vec![0; dump.len()]).unwrap();
+
+    extract(
+        &output,
+        &[
+            "remote",
+            &start_mirror(),
+            "--cache-dir",
+            cache.to_str().unwrap(),
+        ],
+    );
+    assert_golden(&output);
+    assert_eq!(std::fs::read(cached_dump(&cache)).unwrap(), dump);
+    std::fs::remove_dir_all(cache).unwrap();
+}
+
+#[test]
+fn remote_cache_rejects_checksum_mismatch() {
+    let mirror_dir = output_dir("cache-mismatch-mirror");
+    std::fs::create_dir_all(&mirror_dir).unwrap();
+    let dump_file = format!("{}.bz2", DUMP_NAME);
+    std::fs::copy(
+        fixtures().join("dump").join(&dump_file),
+        mirror_dir.join(&dump_file),
+    )
+    .unwrap();
+    let status = std::fs::read_to_string(fixtures().join("dump/dumpstatus.json")).unwrap();
+    let status = status.replace("3d6bc947cef06b4d88c3228f1e17d4cb5a0e40b6", &"0".repeat(40));
+    std::fs::write(mirror_dir.join("dumpstatus.json"), status).unwrap();
+
+    let output = output_dir("cache-mismatch-output");
+    let cache = output_dir("cache-mismatch");
+    let result = run_extractor(
+        &[
+            "-T",
+            "-o",
+            output.to_str().unwrap(),
+            "remote",
+            &start_mirror_at(mirror_dir),
+            "--cache-dir",
+            cache.to_str().unwrap(),
+        ],
+        "warn",
+    );
+    assert!(result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("not caching it"), "{}", stderr);
+    assert!(!cached_dump(&cache).exists());
+    assert!(!cached_dump(&cache).with_extension("bz2.part").exists());
+    std::fs::remove_dir_all(cache).unwrap();
+}
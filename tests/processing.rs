@@ -0,0 +1,114 @@
+use wiki_extractor::output::processing::{
+    sentence_count, CollapseWhitespace, ConllTokens, MapXMLEntities, NormalizeUnicode,
+    ProcessingPass as _, StripHtmlTags, StripWords, UnicodeForm,
+};
+
+#[test]
+fn strip_words_keeps_contractions() {
+    assert_eq!(StripWords::process("don't"), "don't");
+    assert_eq!(StripWords::process("don\u{2019}t"), "don't");
+    assert_eq!(StripWords::process("'quoted' words"), "quoted words");
+}
+
+#[test]
+fn strip_words_keeps_diacritics() {
+    assert_eq!(StripWords::process("naïve"), "naïve");
+    // decomposed form: 'i' followed by combining diaeresis
+    assert_eq!(StripWords::process("nai\u{0308}ve"), "nai\u{0308}ve");
+}
+
+#[test]
+fn strip_words_keeps_hyphens() {
+    assert_eq!(StripWords::process("co-operate"), "co-operate");
+    assert_eq!(StripWords::process("co\u{2010}operate"), "co-operate");
+}
+
+#[test]
+fn code_block_indentation_survives() {
+    let text =
+        "An   example:\n```\nfn main() {\n    if true {\n        run();\n    }\n}\n```\n  After.";
+    assert_eq!(
+        CollapseWhitespace::process(text),
+        "An example:\n```\nfn main() {\n    if true {\n        run();\n    }\n}\n```\nAfter."
+    );
+}
+
+#[test]
+fn nbsp_collapses_to_single_space() {
+    assert_eq!(
+        CollapseWhitespace::process("A paragraph with\u{00A0}non-breaking \u{00A0} spaces."),
+        "A paragraph with non-breaking spaces."
+    );
+}
+
+#[test]
+fn typographic_spaces_collapse() {
+    assert_eq!(
+        CollapseWhitespace::process("10\u{202F}km and\u{2003}\u{2009}more"),
+        "10 km and more"
+    );
+}
+
+#[test]
+fn escaped_nbsp_entity_is_decoded_once() {
+    assert_eq!(MapXMLEntities::process("&amp;nbsp;"), "&nbsp;");
+}
+
+#[test]
+fn normalize_unicode_forms() {
+    // "e" followed by combining acute accent
+    let decomposed = "Cafe\u{301}";
+    assert_eq!(NormalizeUnicode::process(decomposed), "Caf\u{e9}");
+    assert_eq!(
+        NormalizeUnicode::apply("Caf\u{e9}", UnicodeForm::Nfd),
+        decomposed
+    );
+    assert_eq!(
+        NormalizeUnicode::apply("\u{ff37}iki \u{fb01}le", UnicodeForm::Nfkc),
+        "Wiki file"
+    );
+    assert_eq!(
+        NormalizeUnicode::apply("\u{ff37}iki", UnicodeForm::None),
+        "\u{ff37}iki"
+    );
+}
+
+#[test]
+fn conll_tokens_split_sentences() {
+    let text = "It's a well-known fact, the doctor said. Next one, e.g. 42\nNew line";
+    assert_eq!(
+        ConllTokens::process(text),
+        "It's\na\nwell-known\nfact\nthe\ndoctor\nsaid\n\nNext\none\neg\n\nNew\nline\n\n"
+    );
+}
+
+#[test]
+fn strip_html_tags() {
+    assert_eq!(
+        StripHtmlTags::process("H<sub>2</sub>O and <span style=\"color:red\">red</span>"),
+        "H2O and red"
+    );
+    assert_eq!(
+        StripHtmlTags::process("one<br/>two<BR>three"),
+        "one two three"
+    );
+    assert_eq!(
+        StripHtmlTags::process("if a < b and b > c"),
+        "if a < b and b > c"
+    );
+    assert_eq!(
+        StripHtmlTags::process("<math>x</math> <sup"),
+        "<math>x</math> <sup"
+    );
+}
+
+#[test]
+fn count_sentences() {
+    assert_eq!(sentence_count("One. Two? Three!"), 3);
+    assert_eq!(
+        sentence_count("Written by J. Smith in 1900.\nMore text."),
+        2
+    );
+    assert_eq!(sentence_count("Just a fragment"), 0);
+    assert_eq!(sentence_count("Etc. is short for et cetera."), 1);
+}
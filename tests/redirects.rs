@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use wiki_extractor::output::redirects::resolve_chains;
+
+fn redirects(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(title, target)| (title.to_string(), target.to_string()))
+        .collect()
+}
+
+#[test]
+fn chains_resolve_to_final_target() {
+    let (resolved, cycles) = resolve_chains(&redirects(&[
+        ("UK", "United_Kingdom"),
+        ("United Kingdom", "United Kingdom of Great Britain#Today"),
+        ("GB", "UK"),
+        ("Anarchy", "Anarchism"),
+    ]));
+    assert_eq!(cycles, 0);
+    assert_eq!(resolved["GB"], "United Kingdom of Great Britain#Today");
+    assert_eq!(resolved["UK"], "United Kingdom of Great Britain#Today");
+    assert_eq!(resolved["Anarchy"], "Anarchism");
+}
+
+#[test]
+fn cycles_are_left_out() {
+    let (resolved, cycles) = resolve_chains(&redirects(&[
+        ("A", "B"),
+        ("B", "A"),
+        ("C", "A"),
+        ("D", "E"),
+    ]));
+    assert_eq!(cycles, 3);
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved["D"], "E");
+}
@@ -0,0 +1,22 @@
+use wiki_extractor::output::split::sanitize_file_name;
+
+#[test]
+fn sanitize_path_separators() {
+    assert_eq!(sanitize_file_name("AC/DC"), "AC_DC");
+    assert_eq!(sanitize_file_name("Talk:Main Page"), "Talk_Main Page");
+}
+
+#[test]
+fn sanitize_reserved_names() {
+    assert_eq!(sanitize_file_name("CON"), "CON_");
+    assert_eq!(sanitize_file_name("Etc."), "Etc");
+    assert_eq!(sanitize_file_name("..."), "_");
+}
+
+#[test]
+fn sanitize_long_names() {
+    let title = "é".repeat(200);
+    let name = sanitize_file_name(&title);
+    assert!(name.len() <= 200);
+    assert!(name.chars().all(|it| it == 'é'));
+}
@@ -0,0 +1,56 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use wiki_extractor::{
+    input::data::{DumpInfo, FileDescriptor, FileName, SourceLocation},
+    state::{DownloadTracker, ResumeError},
+};
+
+fn files(fixture: &str) -> BTreeMap<FileName, FileDescriptor> {
+    let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(fixture);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let client = wiki_extractor::client(false);
+    DumpInfo::new(rt.handle(), &client, &SourceLocation::Local { path })
+        .unwrap()
+        .files
+}
+
+#[test]
+fn resume_from_checkpoint() {
+    let files = files("page_elements.xml");
+    let mut checkpoint = DownloadTracker::new(&files);
+    checkpoint.set_current_position(100);
+
+    let tracker = DownloadTracker::resume(&files, &checkpoint).unwrap();
+    assert_eq!(tracker.position(), (0, 100));
+    assert_eq!(tracker.downloaded(), 100);
+}
+
+#[test]
+fn resume_rejects_different_files() {
+    let checkpoint = DownloadTracker::new(&files("page_elements.xml"));
+    assert!(matches!(
+        DownloadTracker::resume(&files("bom.xml"), &checkpoint),
+        Err(ResumeError::FilesMismatch)
+    ));
+}
+
+#[test]
+fn fast_forward_validates_position() {
+    let files = files("page_elements.xml");
+    let size = files.values().next().unwrap().size;
+    let mut tracker = DownloadTracker::new(&files);
+
+    assert!(matches!(
+        tracker.fast_forward(0, size + 1),
+        Err(ResumeError::InvalidPosition { file_index: 0, .. })
+    ));
+    assert!(matches!(
+        tracker.fast_forward(2, 0),
+        Err(ResumeError::InvalidPosition { file_index: 2, .. })
+    ));
+    assert_eq!(tracker.position(), (0, 0));
+
+    tracker.fast_forward(0, size).unwrap();
+    assert_eq!(tracker.position(), (0, size));
+    assert_eq!(tracker.downloaded(), tracker.total_size());
+}
@@ -0,0 +1,93 @@
+use clap::Parser as _;
+use wiki_extractor::output::{
+    mediawiki,
+    options::TextOptions,
+    templates::{TemplateRule, TemplateRules},
+};
+
+fn rules(source: &str) -> TemplateRules {
+    let mut rules = TemplateRules::first_param_only(Vec::<String>::new());
+    rules.extend_from_str(source).unwrap();
+    rules
+}
+
+fn to_text_with(wikitext: &str, rules: TemplateRules) -> String {
+    let mut options = TextOptions::parse_from(["test"]);
+    options.template_rules = rules;
+    let parsed = mediawiki::configuration(&[]).parse(wikitext).unwrap();
+    mediawiki::nodes_to_text(&parsed.nodes, &options)
+}
+
+#[test]
+fn exact_and_prefix_rules() {
+    let rules = rules(
+        r#"[templates]
+"lang" = { param = "2" }
+"lang-*" = "first-param"
+"lang-x*" = "drop"
+"Spaced_Dash" = { text = " – " }
+"#,
+    );
+    assert_eq!(rules.get("lang"), &TemplateRule::Param("2".to_string()));
+    assert_eq!(rules.get("lang-fr"), &TemplateRule::FirstParam);
+    // longest prefix wins
+    assert_eq!(rules.get("lang-xx"), &TemplateRule::Drop);
+    // names are normalized when rules are loaded
+    assert_eq!(
+        rules.get("spaced dash"),
+        &TemplateRule::Text(" – ".to_string())
+    );
+    assert_eq!(rules.get("langx"), &TemplateRule::Drop);
+    assert_eq!(rules.get("unknown"), &TemplateRule::Drop);
+}
+
+#[test]
+fn param_lookup() {
+    let rules = rules(
+        r#"[templates]
+"positional" = { param = "2" }
+"named" = { param = "text" }
+"#,
+    );
+    assert_eq!(
+        to_text_with("A {{positional|x|y|z}} b.", rules.clone()).trim(),
+        "A y b."
+    );
+    assert_eq!(
+        to_text_with("A {{named|x|text=y}} b.", rules.clone()).trim(),
+        "A y b."
+    );
+    // named parameters aren't counted as positional ones
+    assert_eq!(
+        to_text_with("A {{positional|text=x|y|z}} b.", rules.clone()).trim(),
+        "A z b."
+    );
+    assert_eq!(to_text_with("A {{named|x}} b.", rules).trim(), "A b.");
+}
+
+#[test]
+fn override_bundled_rules() {
+    let mut rules = TemplateRules::default();
+    assert_eq!(rules.get("lang"), &TemplateRule::Param("2".to_string()));
+    rules
+        .extend_from_str("[templates]\n\"lang\" = \"drop\"\n\"custom\" = \"passthrough\"")
+        .unwrap();
+    assert_eq!(rules.get("lang"), &TemplateRule::Drop);
+    assert_eq!(rules.get("custom"), &TemplateRule::Passthrough);
+    // other bundled rules are kept
+    assert_eq!(rules.get("nowrap"), &TemplateRule::FirstParam);
+}
+
+#[test]
+fn override_file() {
+    let path = std::env::temp_dir().join(format!("wx-templates-{}.toml", std::process::id()));
+    std::fs::write(&path, "[templates]\n\"nowrap\" = { text = \"x\" }").unwrap();
+    let mut rules = TemplateRules::default();
+    rules.extend_from_file(&path).unwrap();
+    assert_eq!(rules.get("nowrap"), &TemplateRule::Text("x".to_string()));
+
+    std::fs::write(&path, "[templates]\n\"nowrap\" = \"unknown-rule\"").unwrap();
+    let err = rules.extend_from_file(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use clap::Parser as _;
+use wiki_extractor::output::{
+    options::{GeneratorOptions, TextOptions},
+    writer::{JsonlFile, OutputWriter, Record},
+};
+
+#[test]
+fn batches_are_written_in_sequence_order() {
+    let dir = std::env::temp_dir().join(format!("wx-writer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let generator_options = GeneratorOptions::parse_from(["test", "-T", "--collect-comments"]);
+    let text_options = TextOptions::parse_from(["test"]);
+    let mut writer = OutputWriter::spawn(&dir, &generator_options, &text_options).unwrap();
+
+    let first = writer.next_sequence();
+    let second = writer.next_sequence();
+    let third = writer.next_sequence();
+    let text = |value: &str| Record::Text(Arc::new(value.to_string()));
+    writer.send(third, vec![text("third")]).unwrap();
+    writer
+        .send(
+            second,
+            vec![Record::Jsonl(JsonlFile::Comments, b"{}\n".to_vec())],
+        )
+        .unwrap();
+    writer
+        .send(first, vec![text("first"), text("second")])
+        .unwrap();
+    writer.finish().unwrap();
+
+    let text = std::fs::read_to_string(dir.join("wiki_sentences.txt")).unwrap();
+    assert_eq!(text, "firstsecondthird");
+    let comments = std::fs::read_to_string(dir.join("comments.jsonl")).unwrap();
+    assert_eq!(comments, "{}\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesEnd, BytesText, Event as XMLEvent};
+use wiki_extractor::xml_util::{
+    Closeable as _, CloseableState, Handle, HandleEvent as _, ParseError, ValueTag,
+};
+
+type Title = Handle<ValueTag<String, "title">, "title">;
+
+fn open_title() -> Title {
+    Handle::new_open(ValueTag::Open {
+        attributes: HashMap::new(),
+        buffer: String::new(),
+    })
+}
+
+#[test]
+fn handle_open_and_close() {
+    let mut handle = open_title();
+    assert_eq!(handle.close_state(), CloseableState::Open);
+    handle
+        .handle_event(XMLEvent::Text(BytesText::new("Anarchism")))
+        .unwrap();
+    assert_eq!(
+        handle.partial_value().and_then(|it| it.buffer()),
+        Some("Anarchism")
+    );
+    assert!(handle.value().is_none());
+
+    handle
+        .handle_event(XMLEvent::End(BytesEnd::new("title")))
+        .unwrap();
+    assert_eq!(handle.close_state(), CloseableState::Closed);
+    assert_eq!(handle.value().and_then(|it| it.buffer()), Some("Anarchism"));
+
+    let cloned = handle.clone();
+    assert!(matches!(
+        handle.close(),
+        Err(ParseError::BadCloseableState(CloseableState::Closed))
+    ));
+    drop(handle);
+    assert_eq!(cloned.value().and_then(|it| it.buffer()), Some("Anarchism"));
+}
+
+#[test]
+fn handle_dropped_while_open() {
+    let mut handle = open_title();
+    handle
+        .handle_event(XMLEvent::Text(BytesText::new("partial")))
+        .unwrap();
+    let cloned = handle.clone();
+    drop(handle);
+    assert_eq!(
+        cloned.partial_value().and_then(|it| it.buffer()),
+        Some("partial")
+    );
+
+    let mut unopened = Title::default();
+    assert!(unopened.close().is_err());
+    assert!(unopened
+        .handle_event(XMLEvent::Text(BytesText::new("text")))
+        .is_err());
+}